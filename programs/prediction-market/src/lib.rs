@@ -1,336 +1,8982 @@
+// The `#[program]` macro still calls the deprecated `AccountInfo::realloc`.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("9KQjnCXwNcnaojsfvuD894UjnCKvgwEDe4Kt1nfpDNHB");
 
+/// Description length accepted at creation, as reserved by `Market::INIT_SPACE`.
+pub const MAX_DESCRIPTION_LEN: usize = 280;
+
+/// Hard cap for a description grown later through `update_metadata`.
+pub const MAX_EXTENDED_DESCRIPTION_LEN: usize = 1_000;
+
+/// Longest set of detailed resolution rules a market can carry, kept apart
+/// from the short description.
+pub const MAX_RESOLUTION_CRITERIA_LEN: usize = 512;
+
+/// Longest off-chain metadata pointer a market can carry.
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+/// Most outcomes a market can have; binary markets use 2.
+pub const MAX_OUTCOMES: u8 = 8;
+pub const MAX_RESOLVERS: usize = 5;
+
+/// Most price feeds an Oracle market can aggregate.
+pub const MAX_ORACLE_FEEDS: usize = 5;
+
+/// Prior probability of YES, even odds, used to split `seed_amount` when a
+/// market names none.
+pub const DEFAULT_PRIOR_YES_BPS: u16 = 5_000;
+
+/// Highest fee a market can charge on payouts (10%).
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Most recipients protocol revenue can be split between.
+pub const MAX_FEE_SPLITS: usize = 5;
+
+/// Layout versions written by this build. Accounts created before versioning
+/// read as version 0, and version 1 accounts lack the fields appended since;
+/// `migrate_market` and `migrate_bet` bring both up.
+pub const MARKET_VERSION: u8 = 3;
+pub const BET_VERSION: u8 = 3;
+
+/// Furthest a market's end_time may lie from the moment it is set (two
+/// years), so a typo cannot lock funds for centuries.
+pub const MAX_MARKET_DURATION: i64 = 2 * 365 * 24 * 60 * 60;
+
+/// Wait between `request_emergency_withdraw` and `emergency_withdraw` (48
+/// hours), long enough for bettors to notice the request and react.
+pub const EMERGENCY_TIMELOCK: i64 = 48 * 60 * 60;
+
+/// How long a payout held for exceeding `high_value_threshold` waits for
+/// `approve_large_claim` (seven days) before the bettor may claim it anyway.
+pub const CLAIM_APPROVAL_TIMEOUT: i64 = 7 * 24 * 60 * 60;
+
+/// How long after the challenge window the admin has to rule on a pending
+/// dispute (seven days); past it `void_unadjudicated` settles the dispute in
+/// the disputer's favour.
+pub const ADJUDICATION_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+/// Owner of the Pyth `PriceUpdateV2` accounts read by Oracle markets.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Fixed-point scale for LMSR pricing math (12 decimal places).
+pub const LMSR_SCALE: u128 = 1_000_000_000_000;
+
+/// ln(2) at `LMSR_SCALE`.
+const LMSR_LN_2: u128 = 693_147_180_560;
+
+/// Odds are reported as fixed-point values with `odds_decimals` decimal places
+/// (4 = basis points). Capped so the scale stays well inside u128 math.
+pub const MAX_ODDS_DECIMALS: u8 = 9;
+
 #[program]
 pub mod prediction_market {
     use super::*;
 
+    /// Creates a market under `market_id`. Returns the market and vault
+    /// addresses and bumps so clients need not re-derive them.
     pub fn create_market(
         ctx: Context<CreateMarket>,
         market_id: u64,
         description: String,
         end_time: i64,
         min_bet_amount: u64,
-    ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        let clock = Clock::get()?;
+        options: MarketOptions,
+    ) -> Result<CreatedMarket> {
+        let accounts = NewMarket {
+            market: &mut ctx.accounts.market,
+            market_vault: &mut ctx.accounts.market_vault,
+            mint: &ctx.accounts.mint,
+            authority: &ctx.accounts.authority,
+            authority_token_account: ctx.accounts.authority_token_account.as_ref(),
+            bond_vault: ctx.accounts.bond_vault.as_mut(),
+            creator_fee_vault: ctx.accounts.creator_fee_vault.as_ref(),
+            sol_vault: ctx.accounts.sol_vault.as_mut(),
+            config: &ctx.accounts.config,
+            authority_stats: &mut ctx.accounts.authority_stats,
+            vault_bump: ctx.bumps.market_vault,
+            payer: &ctx.accounts.payer,
+            token_program: &ctx.accounts.token_program,
+        };
+        init_market(accounts, market_id, description, end_time, min_bet_amount, options)?;
 
-        // Validate inputs
-        require!(end_time > clock.unix_timestamp, ErrorCode::InvalidEndTime);
-        require!(description.len() <= 280, ErrorCode::DescriptionTooLong);
-        require!(min_bet_amount > 0, ErrorCode::InvalidBetAmount);
+        Ok(CreatedMarket {
+            market_id,
+            market: ctx.accounts.market.key(),
+            market_bump: ctx.bumps.market,
+            market_vault: ctx.accounts.market_vault.key(),
+            vault_bump: ctx.bumps.market_vault,
+        })
+    }
 
-        // Initialize market
-        market.authority = ctx.accounts.authority.key();
-        market.market_id = market_id;
-        market.description = description;
-        market.end_time = end_time;
-        market.min_bet_amount = min_bet_amount;
-        market.total_yes_bets = 0;
-        market.total_no_bets = 0;
-        market.is_resolved = false;
-        market.winning_outcome = None;
-        market.created_at = clock.unix_timestamp;
+    /// Creates a market under the registry's next id, so concurrent creators
+    /// never race for one id. Returns the id assigned with the market and
+    /// vault addresses.
+    pub fn create_market_auto(
+        ctx: Context<CreateMarketAuto>,
+        description: String,
+        end_time: i64,
+        min_bet_amount: u64,
+        options: MarketOptions,
+    ) -> Result<CreatedMarket> {
+        let registry = &mut ctx.accounts.registry;
+        let market_id = registry.next_market_id;
+        registry.next_market_id = market_id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        let accounts = NewMarket {
+            market: &mut ctx.accounts.market,
+            market_vault: &mut ctx.accounts.market_vault,
+            mint: &ctx.accounts.mint,
+            authority: &ctx.accounts.authority,
+            authority_token_account: ctx.accounts.authority_token_account.as_ref(),
+            bond_vault: ctx.accounts.bond_vault.as_mut(),
+            creator_fee_vault: ctx.accounts.creator_fee_vault.as_ref(),
+            sol_vault: ctx.accounts.sol_vault.as_mut(),
+            config: &ctx.accounts.config,
+            authority_stats: &mut ctx.accounts.authority_stats,
+            vault_bump: ctx.bumps.market_vault,
+            payer: &ctx.accounts.payer,
+            token_program: &ctx.accounts.token_program,
+        };
+        init_market(accounts, market_id, description, end_time, min_bet_amount, options)?;
 
-        emit!(MarketCreated {
+        Ok(CreatedMarket {
             market_id,
-            authority: ctx.accounts.authority.key(),
-            description: market.description.clone(),
-            end_time,
-        });
+            market: ctx.accounts.market.key(),
+            market_bump: ctx.bumps.market,
+            market_vault: ctx.accounts.market_vault.key(),
+            vault_bump: ctx.bumps.market_vault,
+        })
+    }
 
+    /// Creates the market registry once; only the program's upgrade authority
+    /// can. `first_market_id` starts automatic ids above any picked by hand
+    /// through `create_market`.
+    pub fn initialize_registry(
+        ctx: Context<InitializeRegistry>,
+        first_market_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.registry.next_market_id = first_market_id;
         Ok(())
     }
 
+    /// Stakes `amount` on an outcome. On LMSR markets `amount` is the number
+    /// of shares to buy instead, and the bettor pays their current cost.
+    /// `min_expected_ratio` rejects the bet if `expected_ratio_bps` at
+    /// placement falls below it. Whitelisted markets need a `whitelist_proof`
+    /// of the bettor's `whitelist_leaf`.
+    #[allow(clippy::too_many_arguments)]
     pub fn place_bet(
         ctx: Context<PlaceBet>,
         market_id: u64,
-        bet_outcome: bool, // true for YES, false for NO
+        bet_index: u64,
+        bet_outcome: u8, // for binary markets 0 is YES, 1 is NO
+        amount: u64,
+        campaign_id: Option<u64>,
+        min_expected_ratio: Option<u64>,
+        whitelist_proof: Option<Vec<[u8; 32]>>,
+    ) -> Result<()> {
+        // Validate market state
+        check_spl_bet(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            market_id,
+            ctx.accounts.bettor.key(),
+            bet_outcome,
+            amount,
+            min_expected_ratio,
+            whitelist_proof.as_deref().unwrap_or_default(),
+        )?;
+
+        // Transfer tokens from bettor to market vault, booking what arrived
+        let stake = ctx.accounts.market.stake_for(bet_outcome, amount)?;
+        let received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.bettor_token_account,
+            &mut ctx.accounts.market_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.bettor.to_account_info(),
+            stake,
+        )?;
+
+        // LMSR bets hold shares, so the transfer must arrive in full
+        let is_lmsr = ctx.accounts.market.market_type != MarketType::Parimutuel;
+        if is_lmsr {
+            require!(received == stake, ErrorCode::TransferFeeUnsupported);
+            let market = &mut ctx.accounts.market;
+            let outcome = bet_outcome as usize;
+            market.outcome_shares[outcome] = market.outcome_shares[outcome]
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        record_bet(
+            &mut ctx.accounts.market,
+            &mut ctx.accounts.bet,
+            ctx.accounts.bettor.key(),
+            bet_index,
+            bet_outcome,
+            received,
+            campaign_id,
+            ctx.accounts.campaign.as_mut(),
+            &mut ctx.accounts.user_position,
+        )?;
+        if is_lmsr {
+            ctx.accounts.bet.shares = amount;
+        }
+        ctx.accounts.user_profile.record_wager(ctx.accounts.bettor.key(), received, true)?;
+
+        Ok(())
+    }
+
+    /// Stakes `amount` lamports on an outcome of a NativeSol market.
+    pub fn place_bet_native(
+        ctx: Context<PlaceBetNative>,
+        market_id: u64,
+        bet_index: u64,
+        bet_outcome: u8,
+        amount: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        // Validate market state
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.market.require_currency(Currency::NativeSol)?;
+        ctx.accounts.market.validate_bet(market_id, bet_outcome, amount, clock.unix_timestamp)?;
+        ctx.accounts.market.require_bettor_allowed(ctx.accounts.bettor.key())?;
+        ctx.accounts.market.require_whitelisted(ctx.accounts.bettor.key(), &[])?;
+
+        // Transfer lamports from bettor to the escrow
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bettor.to_account_info(),
+                    to: ctx.accounts.sol_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        record_bet(
+            &mut ctx.accounts.market,
+            &mut ctx.accounts.bet,
+            ctx.accounts.bettor.key(),
+            bet_index,
+            bet_outcome,
+            amount,
+            None,
+            None,
+            &mut ctx.accounts.user_position,
+        )?;
+        ctx.accounts.user_profile.record_wager(ctx.accounts.bettor.key(), amount, true)?;
+
+        Ok(())
+    }
+
+    /// Adds `amount` to an existing bet on the same outcome, so the position
+    /// settles as one larger bet. The combined stake is held to the market's
+    /// maximum bet. The whole bet pays the fee rate it recorded when placed,
+    /// so on a market with a fee schedule a top-up is refused once the rate
+    /// has moved; place a new bet instead.
+    pub fn increase_bet(
+        ctx: Context<IncreaseBet>,
+        market_id: u64,
+        bet_index: u64,
+        bet_outcome: u8,
         amount: u64,
     ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        // Validate market and bet state
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.market.require_parimutuel()?;
+        ctx.accounts.market.require_currency(Currency::Spl)?;
+        ctx.accounts.market.validate_bet(market_id, bet_outcome, amount, clock.unix_timestamp)?;
+        ctx.accounts.market.require_bettor_allowed(ctx.accounts.bettor.key())?;
+        let bet = &ctx.accounts.bet;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(bet.outcome == bet_outcome, ErrorCode::OutcomeMismatch);
+        require!(
+            bet.fee_bps.unwrap_or(ctx.accounts.market.fee_bps)
+                == ctx.accounts.market.fee_bps_at(clock.unix_timestamp),
+            ErrorCode::FeeRateChanged
+        );
+        require!(
+            bet.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?
+                <= ctx.accounts.market.max_bet_amount.unwrap_or(u64::MAX),
+            ErrorCode::BetTooLarge
+        );
+
+        // Transfer tokens from bettor to market vault, booking what arrived
+        let received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.bettor_token_account,
+            &mut ctx.accounts.market_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.bettor.to_account_info(),
+            amount,
+        )?;
+
+        ctx.accounts.user_profile.record_wager(ctx.accounts.bettor.key(), received, false)?;
+        if let Some(campaign) =
+            tagged_campaign(ctx.accounts.bet.campaign_id, ctx.accounts.campaign.as_mut())?
+        {
+            campaign.record_volume(received, false)?;
+        }
+        ctx.accounts.user_position.record_stake(
+            &ctx.accounts.market,
+            ctx.accounts.bettor.key(),
+            bet_outcome,
+            received,
+            false,
+        )?;
+
+        // Update market totals and the bet
         let market = &mut ctx.accounts.market;
         let bet = &mut ctx.accounts.bet;
+        let outcome = bet_outcome as usize;
+        market.outcome_pools[outcome] = market.outcome_pools[outcome]
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+        bet.amount = bet.amount.checked_add(received).ok_or(ErrorCode::MathOverflow)?;
+        let weight = market.early_bird_weight(received, clock.unix_timestamp)?;
+        market.outcome_weighted_pools[outcome] = market.outcome_weighted_pools[outcome]
+            .checked_add(weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        bet.weighted_amount = bet.weighted_amount.checked_add(weight).ok_or(ErrorCode::MathOverflow)?;
+        market.chain_audit(
+            b"increase",
+            &[bet.bettor.as_ref(), &[bet_outcome], &received.to_le_bytes()],
+        );
+
+        if market.logs(LogLevel::Full) {
+            emit!(BetIncreased {
+                market_id,
+                bettor: bet.bettor,
+                bet_index,
+                amount: received,
+                total_amount: bet.amount,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Places a bet on behalf of a bettor who approved the market vault as a
+    /// delegate on their token account beforehand, so a relayer can submit
+    /// (and pay for) the bet. The approval must be for exactly `amount`, and
+    /// the bettor co-signs the transaction so the relayer cannot choose the
+    /// outcome or replay the approval.
+    pub fn place_bet_delegated(
+        ctx: Context<PlaceBetDelegated>,
+        market_id: u64,
+        bet_index: u64,
+        bet_outcome: u8,
+        amount: u64,
+        campaign_id: Option<u64>,
+    ) -> Result<()> {
         let clock = Clock::get()?;
+        let market_vault = &mut ctx.accounts.market_vault;
+        let bettor_token_account = &ctx.accounts.bettor_token_account;
 
         // Validate market state
-        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
-        require!(clock.unix_timestamp < market.end_time, ErrorCode::MarketExpired);
-        require!(amount >= market.min_bet_amount, ErrorCode::BetTooSmall);
-        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.market.require_parimutuel()?;
+        ctx.accounts.market.require_currency(Currency::Spl)?;
+        ctx.accounts.market.validate_bet(market_id, bet_outcome, amount, clock.unix_timestamp)?;
+        ctx.accounts.market.require_bettor_allowed(ctx.accounts.bettor.key())?;
+        ctx.accounts.market.require_whitelisted(ctx.accounts.bettor.key(), &[])?;
 
-        // Transfer tokens from bettor to market vault
-        let transfer_ctx = CpiContext::new(
+        // Validate the bettor's approval
+        require!(
+            bettor_token_account.owner == ctx.accounts.bettor.key(),
+            ErrorCode::UnauthorizedClaimer
+        );
+        require!(
+            bettor_token_account.delegate == COption::Some(market_vault.key())
+                && bettor_token_account.delegated_amount == amount,
+            ErrorCode::InvalidDelegation
+        );
+
+        // Pull the tokens as the delegated vault PDA
+        let market_key = ctx.accounts.market.key();
+        let seeds = &[
+            b"market_vault",
+            market_key.as_ref(),
+            &[ctx.accounts.market.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.bettor_token_account.to_account_info(),
-                to: ctx.accounts.market_vault.to_account_info(),
-                authority: ctx.accounts.bettor.to_account_info(),
+            TransferChecked {
+                from: bettor_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: market_vault.to_account_info(),
+                authority: market_vault.to_account_info(),
             },
+            signer_seeds,
         );
-        token::transfer(transfer_ctx, amount)?;
+        let vault_before = market_vault.amount;
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.mint.decimals)?;
+        market_vault.reload()?;
+        let received = market_vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(ErrorCode::MathUnderflow)?;
 
-        // Update market totals
-        if bet_outcome {
-            market.total_yes_bets = market.total_yes_bets.checked_add(amount).unwrap();
-        } else {
-            market.total_no_bets = market.total_no_bets.checked_add(amount).unwrap();
-        }
+        record_bet(
+            &mut ctx.accounts.market,
+            &mut ctx.accounts.bet,
+            ctx.accounts.bettor.key(),
+            bet_index,
+            bet_outcome,
+            received,
+            campaign_id,
+            ctx.accounts.campaign.as_mut(),
+            &mut ctx.accounts.user_position,
+        )?;
+        ctx.accounts.user_profile.record_wager(ctx.accounts.bettor.key(), received, true)?;
 
-        // Initialize bet account
-        bet.bettor = ctx.accounts.bettor.key();
-        bet.market_id = market_id;
-        bet.outcome = bet_outcome;
-        bet.amount = amount;
-        bet.timestamp = clock.unix_timestamp;
-        bet.is_claimed = false;
+        Ok(())
+    }
 
-        emit!(BetPlaced {
-            market_id,
-            bettor: ctx.accounts.bettor.key(),
-            outcome: bet_outcome,
-            amount,
-        });
+    /// Withdraws a bet before the betting cutoff: the stake goes back to the
+    /// bettor and the bet account is closed to them.
+    pub fn cancel_bet(
+        ctx: Context<CancelBet>,
+        market_id: u64,
+        bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_open()?;
+        market.require_parimutuel()?;
+        market.require_currency(Currency::Spl)?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(clock.unix_timestamp < market.end_time, ErrorCode::MarketExpired);
+        // Stakes are fixed from the cutoff on, as they are for new bets
+        require!(
+            clock.unix_timestamp < market.end_time - market.betting_cutoff_offset,
+            ErrorCode::BettingClosed
+        );
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+
+        // Transfer the stake back to the bettor
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            market.key(),
+            market.vault_bump,
+            bet.amount,
+        )?;
+
+        // Remove the bet from the market totals
+        let outcome = bet.outcome as usize;
+        market.outcome_pools[outcome] = market.outcome_pools[outcome]
+            .checked_sub(bet.amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        market.outcome_weighted_pools[outcome] = market.outcome_weighted_pools[outcome]
+            .checked_sub(bet.weighted_amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        market.outcome_bet_counts[outcome] = market.outcome_bet_counts[outcome]
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        market.bettor_count = market.bettor_count.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+        market.release_bet()?;
+        ctx.accounts.user_position.release_stake(bet.outcome, bet.amount, true)?;
+        ctx.accounts.user_profile.release_wager(bet.amount, true)?;
+        if let Some(campaign) = tagged_campaign(bet.campaign_id, ctx.accounts.campaign.as_mut())? {
+            campaign.release_volume(bet.amount, true)?;
+        }
+        market.chain_audit(
+            b"cancel",
+            &[bet.bettor.as_ref(), &[bet.outcome], &bet.amount.to_le_bytes()],
+        );
+
+        if market.logs(LogLevel::Full) {
+            emit!(BetCancelled {
+                market_id,
+                bettor: bet.bettor,
+                bet_index,
+                amount: bet.amount,
+            });
+        }
 
         Ok(())
     }
 
-    pub fn resolve_market(
-        ctx: Context<ResolveMarket>,
+    /// Withdraws part of a bet before the betting cutoff, returning
+    /// `withdraw_amount` to the bettor. At least `min_bet_amount` must stay
+    /// staked; use `cancel_bet` to exit fully.
+    pub fn reduce_bet(
+        ctx: Context<ReduceBet>,
         market_id: u64,
-        winning_outcome: bool,
+        bet_index: u64,
+        withdraw_amount: u64,
     ) -> Result<()> {
         let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
         let clock = Clock::get()?;
 
-        // Validate authority
+        // Validate market state
+        market.require_open()?;
+        market.require_parimutuel()?;
+        market.require_currency(Currency::Spl)?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(clock.unix_timestamp < market.end_time, ErrorCode::MarketExpired);
+        // Stakes are fixed from the cutoff on, as they are for new bets
         require!(
-            ctx.accounts.authority.key() == market.authority,
-            ErrorCode::UnauthorizedResolver
+            clock.unix_timestamp < market.end_time - market.betting_cutoff_offset,
+            ErrorCode::BettingClosed
         );
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
 
-        // Validate market state
-        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
-        require!(clock.unix_timestamp >= market.end_time, ErrorCode::MarketNotExpired);
+        // Validate the reduction
+        require!(
+            withdraw_amount > 0 && withdraw_amount < bet.amount,
+            ErrorCode::InvalidBetAmount
+        );
+        let remaining = bet
+            .amount
+            .checked_sub(withdraw_amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        require!(remaining >= market.min_bet_amount, ErrorCode::WouldGoBelowMinimum);
+
+        // Transfer the withdrawn stake back to the bettor
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            market.key(),
+            market.vault_bump,
+            withdraw_amount,
+        )?;
+
+        // Remove it from the market totals
+        let outcome = bet.outcome as usize;
+        market.outcome_pools[outcome] = market.outcome_pools[outcome]
+            .checked_sub(withdraw_amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        let weight = u64::try_from(
+            bet.weighted_amount as u128 * withdraw_amount as u128 / bet.amount as u128,
+        )
+        .map_err(|_| error!(ErrorCode::MathOverflow))?;
+        market.outcome_weighted_pools[outcome] = market.outcome_weighted_pools[outcome]
+            .checked_sub(weight)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        bet.weighted_amount = bet.weighted_amount.checked_sub(weight).ok_or(ErrorCode::MathUnderflow)?;
+        bet.amount = remaining;
+        ctx.accounts.user_position.release_stake(bet.outcome, withdraw_amount, false)?;
+        ctx.accounts.user_profile.release_wager(withdraw_amount, false)?;
+        if let Some(campaign) = tagged_campaign(bet.campaign_id, ctx.accounts.campaign.as_mut())? {
+            campaign.release_volume(withdraw_amount, false)?;
+        }
+        market.chain_audit(
+            b"reduce",
+            &[bet.bettor.as_ref(), &[bet.outcome], &withdraw_amount.to_le_bytes()],
+        );
+
+        if market.logs(LogLevel::Full) {
+            emit!(BetReduced {
+                market_id,
+                bettor: bet.bettor,
+                bet_index,
+                withdrawn: withdraw_amount,
+                remaining,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Places bets on two markets in one instruction, recorded together in a
+    /// Hedge account. Either leg failing validation aborts both.
+    pub fn hedge_bet(
+        ctx: Context<HedgeBet>,
+        first_market_id: u64,
+        second_market_id: u64,
+        first: HedgeLeg,
+        second: HedgeLeg,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let bettor = ctx.accounts.bettor.key();
+
+        // Validate both legs before moving any tokens
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        require!(first_market_id != second_market_id, ErrorCode::InvalidHedge);
+        ctx.accounts.first_market.require_parimutuel()?;
+        ctx.accounts.second_market.require_parimutuel()?;
+        ctx.accounts.first_market.require_currency(Currency::Spl)?;
+        ctx.accounts.second_market.require_currency(Currency::Spl)?;
+        ctx.accounts.first_market.validate_bet(
+            first_market_id,
+            first.outcome,
+            first.amount,
+            clock.unix_timestamp,
+        )?;
+        ctx.accounts.second_market.validate_bet(
+            second_market_id,
+            second.outcome,
+            second.amount,
+            clock.unix_timestamp,
+        )?;
+        ctx.accounts.first_market.require_bettor_allowed(bettor)?;
+        ctx.accounts.second_market.require_bettor_allowed(bettor)?;
+        ctx.accounts.first_market.require_whitelisted(bettor, &[])?;
+        ctx.accounts.second_market.require_whitelisted(bettor, &[])?;
+
+        // Transfer tokens from bettor to each market vault
+        require!(
+            ctx.accounts.second_market.mint == ctx.accounts.mint.key(),
+            ErrorCode::InvalidHedge
+        );
+        let first_received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.bettor_token_account,
+            &mut ctx.accounts.first_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.bettor.to_account_info(),
+            first.amount,
+        )?;
+        let second_received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.bettor_token_account,
+            &mut ctx.accounts.second_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.bettor.to_account_info(),
+            second.amount,
+        )?;
+
+        record_bet(
+            &mut ctx.accounts.first_market,
+            &mut ctx.accounts.first_bet,
+            bettor,
+            first.bet_index,
+            first.outcome,
+            first_received,
+            None,
+            None,
+            &mut ctx.accounts.first_position,
+        )?;
+        record_bet(
+            &mut ctx.accounts.second_market,
+            &mut ctx.accounts.second_bet,
+            bettor,
+            second.bet_index,
+            second.outcome,
+            second_received,
+            None,
+            None,
+            &mut ctx.accounts.second_position,
+        )?;
+        let profile = &mut ctx.accounts.user_profile;
+        profile.record_wager(bettor, first_received, true)?;
+        profile.record_wager(bettor, second_received, true)?;
+
+        let hedge = &mut ctx.accounts.hedge;
+        hedge.bettor = bettor;
+        hedge.first_market_id = first_market_id;
+        hedge.second_market_id = second_market_id;
+        hedge.first = first;
+        hedge.second = second;
+        hedge.created_at = clock.unix_timestamp;
+
+        // Either market's full log wants the pairing alongside its BetPlaced
+        if ctx.accounts.first_market.logs(LogLevel::Full)
+            || ctx.accounts.second_market.logs(LogLevel::Full)
+        {
+            emit!(HedgePlaced {
+                bettor,
+                first_market_id,
+                second_market_id,
+                first,
+                second,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Registers an on-chain volume counter for a marketing campaign.
+    pub fn create_campaign(
+        ctx: Context<CreateCampaign>,
+        campaign_id: u64,
+    ) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        campaign.campaign_id = campaign_id;
+        campaign.owner = ctx.accounts.owner.key();
+        campaign.total_volume = 0;
+        campaign.bet_count = 0;
+
+        Ok(())
+    }
+
+    /// Creates the program config once, naming the admin who can pause
+    /// betting. Only the program's upgrade authority can call it, so nobody
+    /// can front-run the deployment and name themselves admin.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = admin;
+        config.paused = false;
+        config.max_open_markets_per_authority = 0;
+        config.fee_splits = Vec::new();
+
+        Ok(())
+    }
+
+    /// Stops or resumes betting on every market. Resolution, claims and
+    /// refunds keep working while paused so bettors can always exit.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        // Validate admin
+        require!(ctx.accounts.admin.key() == config.admin, ErrorCode::UnauthorizedAdmin);
+
+        config.paused = paused;
+
+        emit!(PausedSet {
+            admin: config.admin,
+            paused,
+        });
+
+        Ok(())
+    }
+
+    /// Caps how many markets one authority can have open (created and not yet
+    /// closed) at a time; 0 removes the cap.
+    pub fn set_max_open_markets(ctx: Context<SetMaxOpenMarkets>, max_open_markets: u32) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        // Validate admin
+        require!(ctx.accounts.admin.key() == config.admin, ErrorCode::UnauthorizedAdmin);
+
+        config.max_open_markets_per_authority = max_open_markets;
+
+        emit!(MaxOpenMarketsSet {
+            admin: config.admin,
+            max_open_markets,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a market written under an older layout in place: the
+    /// account is grown to the current size, appended fields read as zero,
+    /// and those that must match existing state are derived from it.
+    pub fn migrate_market(ctx: Context<MigrateMarket>, market_id: u64) -> Result<()> {
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+
+        let info = ctx.accounts.market.to_account_info();
+        grow_account(
+            &info,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            8 + Market::INIT_SPACE,
+        )?;
+        let mut market = Market::try_deserialize(&mut &info.try_borrow_data()?[..])?;
         require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.version < MARKET_VERSION, ErrorCode::AlreadyMigrated);
+        grow_account(
+            &info,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            8 + Market::space_with_description(market.description.len()),
+        )?;
 
-        // Resolve market
-        market.is_resolved = true;
-        market.winning_outcome = Some(winning_outcome);
+        let from_version = market.version;
+        let (_, vault_bump) = Pubkey::find_program_address(
+            &[b"market_vault", info.key.as_ref()],
+            ctx.program_id,
+        );
+        market.upgrade(vault_bump);
+        let mut data = info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        market.try_serialize(&mut writer)?;
 
-        emit!(MarketResolved {
+        emit!(MarketMigrated {
             market_id,
-            winning_outcome,
-            resolver: ctx.accounts.authority.key(),
+            from_version,
+            to_version: market.version,
         });
 
         Ok(())
     }
 
-    pub fn claim_winnings(
-        ctx: Context<ClaimWinnings>,
+    /// Upgrades a bet written under an older layout in place, like
+    /// `migrate_market`.
+    pub fn migrate_bet(
+        ctx: Context<MigrateBet>,
         market_id: u64,
+        _bettor: Pubkey,
+        _bet_index: u64,
     ) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let bet = &mut ctx.accounts.bet;
-
-        // Validate market and bet state
-        require!(market.is_resolved, ErrorCode::MarketNotResolved);
-        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
-        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        // Validate admin
         require!(
-            bet.bettor == ctx.accounts.bettor.key(),
-            ErrorCode::UnauthorizedClaimer
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
         );
 
-        // Check if bet won
-        let winning_outcome = market.winning_outcome.unwrap();
-        require!(bet.outcome == winning_outcome, ErrorCode::LosingBet);
+        let info = ctx.accounts.bet.to_account_info();
+        grow_account(
+            &info,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            8 + Bet::INIT_SPACE,
+        )?;
+        let mut bet = Bet::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(bet.version < BET_VERSION, ErrorCode::AlreadyMigrated);
 
-        // Calculate winnings
-        let total_pool = market.total_yes_bets + market.total_no_bets;
-        let winning_pool = if winning_outcome {
-            market.total_yes_bets
-        } else {
-            market.total_no_bets
-        };
+        let from_version = bet.version;
+        bet.upgrade(Clock::get()?.unix_timestamp);
+        let mut data = info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        bet.try_serialize(&mut writer)?;
 
-        let winnings = (bet.amount as u128)
-            .checked_mul(total_pool as u128).unwrap()
-            .checked_div(winning_pool as u128).unwrap() as u64;
+        emit!(BetMigrated {
+            market_id,
+            bettor: bet.bettor,
+            bet_index: bet.bet_index,
+            from_version,
+            to_version: bet.version,
+        });
 
-        // Transfer winnings
-        let market_key = market.key();
-        let seeds = &[
-            b"market_vault",
-            market_key.as_ref(),
-            &[ctx.bumps.market_vault],
-        ];
-        let signer_seeds = &[&seeds[..]];
+        Ok(())
+    }
+
+    /// Creates the protocol treasury once, naming the admin who can withdraw
+    /// from it. Only the program's upgrade authority can call it.
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>, admin: Pubkey) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+
+        treasury.admin = admin;
+        treasury.total_collected = 0;
+
+        Ok(())
+    }
+
+    /// Opens the treasury's vault for a mint so markets in it can pay fees.
+    /// Anyone can call it.
+    pub fn create_treasury_vault(_ctx: Context<CreateTreasuryVault>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Moves treasury revenue out to a token account of the admin's choice.
+    /// Once fee splits are configured revenue only leaves through
+    /// `distribute_treasury`.
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.treasury.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(
+            ctx.accounts.config.fee_splits.is_empty(),
+            ErrorCode::FeeSplitConfigured
+        );
 
+        let seeds = &[b"treasury".as_ref(), &[ctx.bumps.treasury]];
+        let signer_seeds = &[&seeds[..]];
         let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.market_vault.to_account_info(),
-                to: ctx.accounts.bettor_token_account.to_account_info(),
-                authority: ctx.accounts.market_vault.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.treasury_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
             },
             signer_seeds,
         );
-        token::transfer(transfer_ctx, winnings)?;
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.mint.decimals)?;
 
-        // Mark as claimed
-        bet.is_claimed = true;
+        emit!(TreasuryWithdrawn {
+            admin: ctx.accounts.admin.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+        });
 
-        emit!(WinningsClaimed {
-            market_id,
-            bettor: ctx.accounts.bettor.key(),
-            amount: winnings,
+        Ok(())
+    }
+
+    /// Replaces the protocol revenue splits, e.g. between a team, a DAO and
+    /// an insurance fund. The shares must sum to 10_000 bps; an empty list
+    /// returns revenue to the treasury admin's `withdraw_treasury`.
+    pub fn set_fee_splits(ctx: Context<SetFeeSplits>, splits: Vec<FeeSplit>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        // Validate admin
+        require!(ctx.accounts.admin.key() == config.admin, ErrorCode::UnauthorizedAdmin);
+
+        // Validate the splits
+        let total_bps = splits.iter().map(|split| split.bps as u32).sum::<u32>();
+        require!(
+            splits.is_empty()
+                || (splits.len() <= MAX_FEE_SPLITS
+                    && splits.iter().all(|split| split.bps > 0)
+                    && total_bps == 10_000),
+            ErrorCode::InvalidFeeSplit
+        );
+
+        config.fee_splits = splits.clone();
+
+        emit!(FeeSplitsSet {
+            admin: config.admin,
+            splits,
         });
 
         Ok(())
     }
-}
+
+    /// Pays `amount` of a mint's treasury revenue out across the configured
+    /// fee splits. `remaining_accounts` holds one token account per split, in
+    /// order, owned by its recipient; the last takes the rounding remainder.
+    /// Anyone can call it.
+    pub fn distribute_treasury<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DistributeTreasury<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let splits = &ctx.accounts.config.fee_splits;
+
+        // Validate the recipients
+        require!(!splits.is_empty(), ErrorCode::InvalidFeeSplit);
+        require!(
+            ctx.remaining_accounts.len() == splits.len(),
+            ErrorCode::InvalidRemainingAccounts
+        );
+
+        let seeds = &[b"treasury".as_ref(), &[ctx.bumps.treasury]];
+        let signer_seeds = &[&seeds[..]];
+        let mut distributed: u64 = 0;
+        for (i, (split, info)) in splits.iter().zip(ctx.remaining_accounts).enumerate() {
+            let recipient = InterfaceAccount::<TokenAccount>::try_from(info)?;
+            require!(recipient.owner == split.recipient, ErrorCode::InvalidFeeSplit);
+
+            let share = if i + 1 == splits.len() {
+                amount.checked_sub(distributed).ok_or(ErrorCode::MathUnderflow)?
+            } else {
+                (amount as u128 * split.bps as u128 / 10_000) as u64
+            };
+            distributed = distributed.checked_add(share).ok_or(ErrorCode::MathOverflow)?;
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.treasury_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: info.clone(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                signer_seeds,
+            );
+            token_interface::transfer_checked(transfer_ctx, share, ctx.accounts.mint.decimals)?;
+        }
+
+        emit!(TreasuryDistributed {
+            mint: ctx.accounts.mint.key(),
+            amount,
+            splits: splits.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Starts the emergency timelock for draining a market's vault to
+    /// `destination`. Last resort for a bug that traps funds; the drain can
+    /// only run `EMERGENCY_TIMELOCK` seconds later. New bets are refused at
+    /// once; claims and cancels carry on, so bettors can still get their
+    /// funds out before the drain.
+    pub fn request_emergency_withdraw(
+        ctx: Context<RequestEmergencyWithdraw>,
+        market_id: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        ctx.accounts.market.require_currency(Currency::Spl)?;
+        ctx.accounts.market.emergency_halted = true;
+
+        let clock = Clock::get()?;
+        let request = &mut ctx.accounts.emergency_withdrawal;
+        request.market = ctx.accounts.market.key();
+        request.destination = destination;
+        request.requested_at = clock.unix_timestamp;
+        request.executable_at = clock
+            .unix_timestamp
+            .checked_add(EMERGENCY_TIMELOCK)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(EmergencyWithdrawRequested {
+            market_id,
+            admin: ctx.accounts.admin.key(),
+            destination,
+            executable_at: request.executable_at,
+        });
+
+        Ok(())
+    }
+
+    /// Drains the whole market vault to the destination named in the
+    /// request once its timelock has elapsed, cancelling the market. It stays
+    /// halted: nothing is left in the vault to bet into or claim.
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>, market_id: u64) -> Result<()> {
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= ctx.accounts.emergency_withdrawal.executable_at,
+            ErrorCode::TimelockNotElapsed
+        );
+
+        let amount = ctx.accounts.market_vault.amount;
+        let market_key = ctx.accounts.market.key();
+        let seeds = &[
+            b"market_vault",
+            market_key.as_ref(),
+            &[ctx.accounts.market.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.market_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.market_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.mint.decimals)?;
+
+        let market = &mut ctx.accounts.market;
+        market.void(Clock::get()?.unix_timestamp, VoidReason::EmergencyWithdrawn);
+
+        emit!(EmergencyWithdrawn {
+            market_id,
+            admin: ctx.accounts.admin.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws a pending emergency request, closing it and lifting the
+    /// halt so the market takes bets again.
+    pub fn cancel_emergency_withdraw(
+        ctx: Context<CancelEmergencyWithdraw>,
+        market_id: u64,
+    ) -> Result<()> {
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        ctx.accounts.market.emergency_halted = false;
+
+        emit!(EmergencyWithdrawCancelled {
+            market_id,
+            admin: ctx.accounts.admin.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Last resort for a market whose mint is dead: moves its settlement to
+    /// `new_mint` at `rate_num / rate_den` new units per old unit, funded by
+    /// the admin into an escape vault. Only runs on a market halted by
+    /// `request_emergency_withdraw` once the timelock has elapsed, and only
+    /// before anything has been paid out. An unsettled market is voided, so
+    /// every stake is refunded; a settled one keeps its outcome. Bets are
+    /// then paid with `claim_escaped` alone.
+    pub fn escape_to_mint(
+        ctx: Context<EscapeToMint>,
+        market_id: u64,
+        rate_num: u64,
+        rate_den: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+        require!(
+            clock.unix_timestamp >= ctx.accounts.emergency_withdrawal.executable_at,
+            ErrorCode::TimelockNotElapsed
+        );
+
+        // Validate market state
+        let market = &mut ctx.accounts.market;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        market.require_currency(Currency::Spl)?;
+        require!(
+            market.emergency_halted
+                && market.escape_mint.is_none()
+                && market.total_paid == 0
+                && !market.dispute_pending,
+            ErrorCode::EscapeNotAllowed
+        );
+        require!(rate_num > 0 && rate_den > 0, ErrorCode::InvalidConversionRate);
+
+        // Fund the escape vault with the vault's balance, converted
+        let funded = (ctx.accounts.market_vault.amount as u128)
+            .checked_mul(rate_num as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .div_ceil(rate_den as u128);
+        let funded = u64::try_from(funded).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.admin_token_account.to_account_info(),
+                mint: ctx.accounts.new_mint.to_account_info(),
+                to: ctx.accounts.escape_vault.to_account_info(),
+                authority: ctx.accounts.admin.to_account_info(),
+            },
+        );
+        token_interface::transfer_checked(transfer_ctx, funded, ctx.accounts.new_mint.decimals)?;
+
+        if !market.is_settled() {
+            market.void(clock.unix_timestamp, VoidReason::MintEscaped);
+        }
+        market.escape_mint = Some(ctx.accounts.new_mint.key());
+        market.escape_rate_num = rate_num;
+        market.escape_rate_den = rate_den;
+        market.emergency_halted = false;
+        market.chain_audit(
+            b"escape",
+            &[
+                ctx.accounts.new_mint.key().as_ref(),
+                &rate_num.to_le_bytes(),
+                &rate_den.to_le_bytes(),
+            ],
+        );
+
+        emit!(MintEscaped {
+            market_id,
+            admin: ctx.accounts.admin.key(),
+            old_mint: market.mint,
+            new_mint: ctx.accounts.new_mint.key(),
+            rate_num,
+            rate_den,
+            funded,
+        });
+
+        Ok(())
+    }
+
+    /// Pays a bet on an escaped market from the escape vault: what it is
+    /// owed in the old mint, converted at the escape rate. No fees are taken
+    /// and held claims are paid too; the escape itself was the admin's
+    /// approval. Closes the bet to the bettor.
+    pub fn claim_escaped(
+        ctx: Context<ClaimEscaped>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+        let now = Clock::get()?.unix_timestamp;
+
+        // Validate market and bet state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.escape_mint.is_some(), ErrorCode::EscapeNotAllowed);
+        require!(market.is_settled(), ErrorCode::MarketNotResolved);
+        require!(!market.dispute_pending, ErrorCode::DisputePending);
+        require!(
+            market.refund_only || now >= market.disputed_until,
+            ErrorCode::ChallengeWindowActive
+        );
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+
+        // Convert what the bet is owed, at most what the escape vault holds
+        let owed = market.payout_for(bet)?;
+        let converted = (owed as u128)
+            .checked_mul(market.escape_rate_num as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / market.escape_rate_den as u128;
+        let converted = u64::try_from(converted).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        let converted = cap_to_vault(converted, &ctx.accounts.escape_vault)?;
+
+        bet.is_claimed = true;
+        bet.claim_pending = false;
+        let market_key = market.key();
+        let seeds = &[
+            b"market_vault",
+            market_key.as_ref(),
+            &[market.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.escape_vault.to_account_info(),
+                mint: ctx.accounts.escape_mint.to_account_info(),
+                to: ctx.accounts.bettor_token_account.to_account_info(),
+                authority: ctx.accounts.market_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(
+            transfer_ctx,
+            converted,
+            ctx.accounts.escape_mint.decimals,
+        )?;
+
+        ctx.accounts.user_position.record_payout(converted)?;
+        if let Some(report) = market.record_payout(owed)? {
+            emit!(report);
+        }
+        if market.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id,
+                bettor: bet.bettor,
+                amount: converted,
+            });
+        }
+
+        market.release_bet()?;
+        bet.close(ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Ends betting on a market once end_time has passed, recording the
+    /// final pools. Anyone can call it; every resolution path requires it, so
+    /// no bet can land once an outcome is being decided.
+    pub fn close_betting(ctx: Context<CloseBetting>, market_id: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_open()?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(clock.unix_timestamp >= market.end_time, ErrorCode::MarketNotExpired);
+
+        market.status = MarketStatus::Closed;
+        market.chain_audit(b"close", &[&clock.unix_timestamp.to_le_bytes()]);
+
+        emit!(BettingClosed {
+            market_id,
+            outcome_pools: market.outcome_pools.clone(),
+            closed_at: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Settles the market on an outcome, or as void for a draw or an event
+    /// that did not happen, refunding every stake. Betting must have been
+    /// closed with `close_betting`.
+    pub fn resolve_market(
+        ctx: Context<ResolveMarket>,
+        market_id: u64,
+        resolution: Resolution,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate resolution source and resolver
+        market.require_resolution_source(ResolutionSource::ManualAuthority)?;
+        market.require_resolver(ctx.accounts.authority.key())?;
+        require!(market.resolver_threshold == 0, ErrorCode::VoteThresholdRequired);
+
+        // Validate market state
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+
+        let winning_outcome = match resolution {
+            Resolution::Outcome(outcome) => outcome,
+            Resolution::Void => {
+                market.void(clock.unix_timestamp, VoidReason::ResolvedVoid);
+                return Ok(());
+            }
+        };
+        require!(winning_outcome < market.outcome_count, ErrorCode::InvalidOutcome);
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(winning_outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        // Resolve market
+        market.resolve(winning_outcome, clock.unix_timestamp);
+
+        emit!(market.resolved_event(ctx.accounts.authority.key())?);
+
+        Ok(())
+    }
+
+    /// Records one resolver's vote on a threshold market. The market resolves
+    /// once `resolver_threshold` votes agree on an outcome; split votes below
+    /// the threshold leave it unresolved.
+    pub fn submit_resolution_vote(
+        ctx: Context<SubmitResolutionVote>,
+        market_id: u64,
+        outcome: u8,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let votes = &mut ctx.accounts.resolution_votes;
+        let resolver = ctx.accounts.resolver.key();
+        let clock = Clock::get()?;
+
+        // Validate resolution source and resolver
+        market.require_resolution_source(ResolutionSource::ManualAuthority)?;
+        require!(market.resolver_threshold > 0, ErrorCode::VotingNotEnabled);
+        market.require_resolver(resolver)?;
+
+        // Validate market state
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(outcome < market.outcome_count, ErrorCode::InvalidOutcome);
+
+        // Record the vote
+        votes.market = market.key();
+        require!(
+            votes.votes.iter().all(|vote| vote.resolver != resolver),
+            ErrorCode::DuplicateVote
+        );
+        votes.votes.push(ResolverVote { resolver, outcome });
+        let votes_for = votes.votes.iter().filter(|vote| vote.outcome == outcome).count() as u8;
+
+        emit!(ResolutionVoteSubmitted {
+            market_id,
+            resolver,
+            outcome,
+            votes_for,
+            threshold: market.resolver_threshold,
+        });
+
+        if votes_for < market.resolver_threshold {
+            return Ok(());
+        }
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        // Resolve market
+        market.resolve(outcome, clock.unix_timestamp);
+
+        emit!(market.resolved_event(resolver)?);
+
+        Ok(())
+    }
+
+    /// Permissionless resolution for Oracle markets: after end_time the
+    /// market resolves YES if the feed's price is at or above the threshold,
+    /// else NO. The price must be published after end_time and be fresh.
+    pub fn resolve_market_from_oracle(
+        ctx: Context<ResolveFromOracle>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_resolution_source(ResolutionSource::Oracle)?;
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+
+        // Validate the feed and its price
+        let feed = &ctx.accounts.oracle_feed;
+        require!(
+            market.oracle_feed == Some(feed.key()),
+            ErrorCode::OracleFeedMismatch
+        );
+        let price = PythPriceUpdate::read_verified(feed, market.oracle_feed_id)?;
+        require!(
+            market.is_fresh_price(&price, clock.unix_timestamp)?,
+            ErrorCode::StaleOracleData
+        );
+
+        let winning_outcome = if price.price >= market.resolution_threshold { 0 } else { 1 };
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(winning_outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        market.resolve(winning_outcome, clock.unix_timestamp);
+
+        emit!(market.resolved_event(feed.key())?);
+
+        Ok(())
+    }
+
+    /// Permissionless resolution for Oracle markets with a feed set: the
+    /// median of the fresh prices among `remaining_accounts`, each one of the
+    /// market's `oracle_feeds`, is compared to the threshold. Stale feeds are
+    /// left out; fewer than `min_oracle_feeds` fresh ones is an error.
+    pub fn resolve_from_multi_oracle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveFromMultiOracle<'info>>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_resolution_source(ResolutionSource::Oracle)?;
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(!market.oracle_feeds.is_empty(), ErrorCode::InvalidOracleConfig);
+
+        // Collect the fresh prices, each feed at most once
+        let mut seen = [false; MAX_ORACLE_FEEDS];
+        let mut prices: Vec<i64> = Vec::with_capacity(ctx.remaining_accounts.len());
+        let mut exponent = None;
+        for info in ctx.remaining_accounts {
+            let index = market
+                .oracle_feeds
+                .iter()
+                .position(|feed| feed.account == info.key())
+                .ok_or(ErrorCode::OracleFeedMismatch)?;
+            require!(!seen[index], ErrorCode::OracleFeedMismatch);
+            seen[index] = true;
+
+            let price = PythPriceUpdate::read_verified(info, market.oracle_feeds[index].feed_id)?;
+            if !market.is_fresh_price(&price, clock.unix_timestamp)? {
+                continue;
+            }
+            require!(
+                *exponent.get_or_insert(price.exponent) == price.exponent,
+                ErrorCode::OracleFeedMismatch
+            );
+            prices.push(price.price);
+        }
+        require!(
+            prices.len() >= market.min_oracle_feeds as usize,
+            ErrorCode::StaleOracleData
+        );
+
+        // The median resists a single outlying feed
+        prices.sort_unstable();
+        let mid = prices.len() / 2;
+        let median = if prices.len().is_multiple_of(2) {
+            ((prices[mid - 1] as i128 + prices[mid] as i128) / 2) as i64
+        } else {
+            prices[mid]
+        };
+        let winning_outcome = if median >= market.resolution_threshold { 0 } else { 1 };
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(winning_outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        market.resolve(winning_outcome, clock.unix_timestamp);
+
+        emit!(market.resolved_event(ctx.accounts.caller.key())?);
+
+        Ok(())
+    }
+
+    /// Permissionless resolution for Community markets: after end_time the
+    /// market resolves to whichever side holds more stake. If that side's lead
+    /// is under `majority_margin_bps` of the pool the market is voided instead.
+    /// With no margin required, a tie for the lead is broken by
+    /// `tiebreak_seed`, a hash of the market and the resolving slot.
+    pub fn resolve_by_majority(
+        ctx: Context<ResolveByMajority>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_resolution_source(ResolutionSource::Community)?;
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+
+        // The leading outcome wins; a tie for the lead is drawn between the
+        // tied outcomes when no margin is required, and voids below otherwise
+        let tied = market.leading_outcomes();
+        let tiebreak = (tied.len() > 1 && market.majority_margin_bps == 0)
+            .then(|| tiebreak_seed(&market.key(), clock.slot));
+        let winning_outcome = match tiebreak {
+            Some(seed) => tied[tiebreak_draw(&seed, tied.len())],
+            None => tied[0],
+        };
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(winning_outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        // Measure the majority's lead over the runner-up as a share of the pool
+        let total_pool = market.total_pool();
+        let leader_pool = market.outcome_pool(winning_outcome);
+        let runner_up = market
+            .outcome_pools
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != winning_outcome as usize)
+            .map(|(_, &pool)| pool)
+            .max()
+            .unwrap_or(0);
+        let lead = (leader_pool - runner_up) as u128;
+        let clear_majority = (lead > 0 || tiebreak.is_some())
+            && lead * 10_000 >= total_pool * market.majority_margin_bps as u128;
+
+        if !clear_majority {
+            market.void(clock.unix_timestamp, VoidReason::NarrowMajority);
+            return Ok(());
+        }
+
+        market.resolve(winning_outcome, clock.unix_timestamp);
+        if let Some(seed) = tiebreak {
+            market.tiebreak_seed = Some(seed);
+            if market.logs(LogLevel::Minimal) {
+                emit!(TieBroken {
+                    market_id,
+                    tied_outcomes: tied,
+                    slot: clock.slot,
+                    seed,
+                    winning_outcome,
+                });
+            }
+        }
+
+        emit!(market.resolved_event(ctx.accounts.caller.key())?);
+
+        Ok(())
+    }
+
+    /// Commits the authority of a CommitReveal market to an outcome before
+    /// end_time. The commitment is `outcome_commitment(market_id, outcome,
+    /// salt)` and can only be made once.
+    pub fn commit_outcome(
+        ctx: Context<CommitOutcome>,
+        market_id: u64,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate resolution source and authority
+        market.require_resolution_source(ResolutionSource::CommitReveal)?;
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(clock.unix_timestamp < market.end_time, ErrorCode::MarketExpired);
+        require!(market.outcome_commitment.is_none(), ErrorCode::OutcomeAlreadyCommitted);
+
+        market.outcome_commitment = Some(commitment);
+
+        Ok(())
+    }
+
+    /// Resolves a CommitReveal market within `reveal_window` of end_time by
+    /// revealing the committed outcome. A reveal that does not match the
+    /// commitment voids the market.
+    pub fn reveal_outcome(
+        ctx: Context<RevealOutcome>,
+        market_id: u64,
+        winning_outcome: u8,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate resolution source and authority
+        market.require_resolution_source(ResolutionSource::CommitReveal)?;
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state
+        market.require_unsettled()?;
+        require!(market.status == MarketStatus::Closed, ErrorCode::BettingNotClosed);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_resolution_window(clock.unix_timestamp)?;
+        require!(
+            clock.unix_timestamp < market.reveal_deadline(),
+            ErrorCode::RevealWindowClosed
+        );
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        let commitment = market.outcome_commitment.ok_or(ErrorCode::NoOutcomeCommitment)?;
+
+        if outcome_commitment(market_id, winning_outcome, &salt) != commitment {
+            market.void(clock.unix_timestamp, VoidReason::CommitmentMismatch);
+            return Ok(());
+        }
+
+        require!(winning_outcome < market.outcome_count, ErrorCode::InvalidOutcome);
+
+        // Without enough stake, on both sides, there is no fair payout
+        if let Some(reason) = market.unsettleable_reason(winning_outcome) {
+            market.void(clock.unix_timestamp, reason);
+            return Ok(());
+        }
+
+        // Resolve market
+        market.resolve(winning_outcome, clock.unix_timestamp);
+
+        emit!(market.resolved_event(ctx.accounts.authority.key())?);
+
+        Ok(())
+    }
+
+    /// Voids a CommitReveal market whose outcome was not revealed before the
+    /// reveal window closed. Anyone can call it.
+    pub fn void_unrevealed(
+        ctx: Context<VoidUnrevealed>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_resolution_source(ResolutionSource::CommitReveal)?;
+        market.require_unsettled()?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            clock.unix_timestamp >= market.reveal_deadline(),
+            ErrorCode::RevealWindowOpen
+        );
+
+        market.void(clock.unix_timestamp, VoidReason::Unrevealed);
+
+        Ok(())
+    }
+
+    /// Pushes back the end of betting on an open market, e.g. when the event
+    /// is delayed. The new end must still fall before the resolution deadline.
+    pub fn extend_market(
+        ctx: Context<ExtendMarket>,
+        market_id: u64,
+        new_end_time: i64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state
+        market.require_open()?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(clock.unix_timestamp < market.end_time, ErrorCode::MarketExpired);
+        require!(
+            new_end_time > market.end_time && new_end_time < market.resolution_deadline,
+            ErrorCode::InvalidExtension
+        );
+        require!(
+            new_end_time <= clock.unix_timestamp.saturating_add(MAX_MARKET_DURATION),
+            ErrorCode::EndTimeTooFar
+        );
+
+        let old_end_time = market.end_time;
+        market.end_time = new_end_time;
+
+        emit!(MarketExtended {
+            market_id,
+            old_end_time,
+            new_end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Cancels an unresolved market that can no longer be settled fairly.
+    /// Betting stops and every bettor can claim back their stake.
+    pub fn cancel_market(
+        ctx: Context<CancelMarket>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state
+        market.require_unsettled()?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+
+        market.void(clock.unix_timestamp, VoidReason::Cancelled);
+
+        emit!(MarketCancelled {
+            market_id,
+            authority: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Challenges a resolution inside its challenge window: a bettor escrows
+    /// the dispute bond and claims are held until the protocol admin rules
+    /// on it with `adjudicate_dispute`. One dispute is pending at a time.
+    pub fn dispute_resolution(
+        ctx: Context<DisputeResolution>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(ctx.accounts.bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        let disputed_outcome = match (market.status, market.winning_outcome) {
+            (MarketStatus::Resolved, Some(outcome)) => outcome,
+            _ => return err!(ErrorCode::MarketNotResolved),
+        };
+        require!(
+            clock.unix_timestamp < market.disputed_until,
+            ErrorCode::ChallengeWindowClosed
+        );
+        require!(!market.dispute_pending, ErrorCode::DisputePending);
+
+        // Escrow the bond, scaled to the pool, until the dispute is adjudicated
+        let bond = market.required_dispute_bond()?;
+        require!(
+            ctx.accounts.disputer_token_account.amount >= bond,
+            ErrorCode::InsufficientDisputeBond
+        );
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.disputer_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.dispute_bond_vault.to_account_info(),
+                authority: ctx.accounts.disputer.to_account_info(),
+            },
+        );
+        token_interface::transfer_checked(transfer_ctx, bond, ctx.accounts.mint.decimals)?;
+
+        market.disputer = Some(ctx.accounts.disputer.key());
+        market.dispute_pending = true;
+
+        emit!(ResolutionDisputed {
+            market_id,
+            disputer: ctx.accounts.disputer.key(),
+            disputed_outcome,
+            bond,
+        });
+
+        Ok(())
+    }
+
+    /// Rules on a pending dispute. The protocol admin is the adjudicator:
+    /// upholding the resolution forfeits the bond to the treasury and lets
+    /// claims proceed once the challenge window ends; overturning it refunds
+    /// the bond and cancels the market so every stake is refunded. The ruling
+    /// must come by `Market::adjudication_deadline`.
+    pub fn adjudicate_dispute(
+        ctx: Context<AdjudicateDispute>,
+        market_id: u64,
+        uphold: bool,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+
+        // Validate market state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.dispute_pending, ErrorCode::NoDisputePending);
+        require!(
+            clock.unix_timestamp <= market.adjudication_deadline(),
+            ErrorCode::AdjudicationDeadlinePassed
+        );
+        let disputer = market.disputer.ok_or(ErrorCode::NoDisputePending)?;
+
+        // Validate the bond's destination
+        let bond_destination = if uphold {
+            ctx.accounts.treasury_vault.to_account_info()
+        } else {
+            require!(
+                ctx.accounts.disputer_token_account.owner == disputer,
+                ErrorCode::UnauthorizedClaimer
+            );
+            ctx.accounts.disputer_token_account.to_account_info()
+        };
+
+        let bond = pay_out_dispute_bond(
+            &ctx.accounts.token_program,
+            &ctx.accounts.dispute_bond_vault,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.mint,
+            bond_destination,
+            ctx.accounts.disputer.to_account_info(),
+            market.key(),
+            market.vault_bump,
+        )?;
+
+        market.dispute_pending = false;
+        if uphold {
+            ctx.accounts.treasury.record_collected(bond)?;
+            market.disputer = None;
+        } else {
+            market.void(clock.unix_timestamp, VoidReason::Disputed);
+        }
+
+        emit!(DisputeAdjudicated {
+            market_id,
+            disputer,
+            upheld: uphold,
+            bond,
+        });
+
+        Ok(())
+    }
+
+    /// Settles a dispute the admin left unruled past its adjudication
+    /// deadline as if it had been overturned: the bond goes back to the
+    /// disputer and the market is cancelled so every stake is refunded.
+    /// Anyone can call it.
+    pub fn void_unadjudicated(
+        ctx: Context<VoidUnadjudicated>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.dispute_pending, ErrorCode::NoDisputePending);
+        require!(
+            clock.unix_timestamp > market.adjudication_deadline(),
+            ErrorCode::AdjudicationWindowOpen
+        );
+        let disputer = market.disputer.ok_or(ErrorCode::NoDisputePending)?;
+        require!(
+            ctx.accounts.disputer_token_account.owner == disputer,
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        let bond = pay_out_dispute_bond(
+            &ctx.accounts.token_program,
+            &ctx.accounts.dispute_bond_vault,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.disputer_token_account.to_account_info(),
+            ctx.accounts.disputer.to_account_info(),
+            market.key(),
+            market.vault_bump,
+        )?;
+
+        market.dispute_pending = false;
+        market.void(clock.unix_timestamp, VoidReason::Unadjudicated);
+
+        emit!(DisputeAdjudicated {
+            market_id,
+            disputer,
+            upheld: false,
+            bond,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out the resolver bond once the market is settled. An undisputed
+    /// resolution returns it to the authority after the challenge window;
+    /// otherwise it is forfeited, to the disputer who overturned the
+    /// resolution or, when the market was cancelled or left unresolved past
+    /// its deadline, to the treasury. Closes the bond vault to the rent
+    /// sponsor. Anyone can call it.
+    pub fn release_bond(ctx: Context<ReleaseBond>, market_id: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.resolver_bond > 0, ErrorCode::BondNotReleasable);
+        require!(
+            market.is_settled() && !market.dispute_pending,
+            ErrorCode::BondNotReleasable
+        );
+        let forfeited = market.resolver_bond_forfeited();
+        require!(
+            forfeited || clock.unix_timestamp >= market.disputed_until,
+            ErrorCode::BondNotReleasable
+        );
+
+        // Validate the recipient
+        let to_treasury = forfeited && market.disputer.is_none();
+        let (recipient, beneficiary) = if to_treasury {
+            let treasury = ctx
+                .accounts
+                .treasury
+                .as_mut()
+                .ok_or(ErrorCode::BondRecipientMissing)?;
+            let treasury_vault = ctx
+                .accounts
+                .treasury_vault
+                .as_ref()
+                .ok_or(ErrorCode::BondRecipientMissing)?;
+            treasury.record_collected(market.resolver_bond)?;
+            (treasury_vault.to_account_info(), treasury.key())
+        } else {
+            let beneficiary = market.disputer.unwrap_or(market.authority);
+            let recipient = ctx
+                .accounts
+                .recipient_token_account
+                .as_ref()
+                .ok_or(ErrorCode::BondRecipientMissing)?;
+            require!(recipient.owner == beneficiary, ErrorCode::UnauthorizedClaimer);
+            (recipient.to_account_info(), beneficiary)
+        };
+
+        let amount = market.resolver_bond;
+        market.resolver_bond = 0;
+        let market_key = market.key();
+        let seeds = &[
+            b"market_vault",
+            market_key.as_ref(),
+            &[market.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.bond_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: recipient,
+                authority: ctx.accounts.market_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(
+            transfer_ctx,
+            ctx.accounts.bond_vault.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let close_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.bond_vault.to_account_info(),
+                destination: ctx.accounts.rent_sponsor.to_account_info(),
+                authority: ctx.accounts.market_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::close_account(close_ctx)?;
+
+        emit!(BondReleased {
+            market_id,
+            recipient: beneficiary,
+            amount,
+            forfeited,
+        });
+
+        Ok(())
+    }
+
+    /// Sends the creator fees accrued so far to the authority's token account.
+    pub fn withdraw_creator_fees(
+        ctx: Context<WithdrawCreatorFees>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+        require!(market.creator_fees_accrued > 0, ErrorCode::NoFeesToWithdraw);
+
+        let amount = market.creator_fees_accrued;
+        market.creator_fees_accrued = 0;
+        let market_key = market.key();
+        let seeds = &[
+            b"market_vault",
+            market_key.as_ref(),
+            &[market.vault_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.creator_fee_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.authority_token_account.to_account_info(),
+                authority: ctx.accounts.market_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.mint.decimals)?;
+
+        emit!(CreatorFeesWithdrawn {
+            market_id,
+            authority: market.authority,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Moves what is left in the vault once every payout has been made, the
+    /// remainders of floored pro-rata payouts plus anything withheld by a
+    /// payout cap, to the treasury so the market can be closed. Anyone can
+    /// call it, once per market.
+    pub fn sweep_dust(ctx: Context<SweepDust>, market_id: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // Validate every payout has been made
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        market.require_currency(Currency::Spl)?;
+        require!(
+            market.settled_bets == market.claimable_bets()?,
+            ErrorCode::MarketNotFullySettled
+        );
+        // Vested payouts are recorded up front but stay in the vault until
+        // released
+        require!(
+            market.vesting_period == 0 || market.refund_only,
+            ErrorCode::VestingOutstanding
+        );
+        require!(!market.dust_swept, ErrorCode::DustAlreadySwept);
+        let amount = ctx.accounts.market_vault.amount;
+        require!(amount > 0, ErrorCode::NoDustToSweep);
+
+        market.dust_swept = true;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.treasury_vault,
+            &ctx.accounts.mint,
+            market.key(),
+            market.vault_bump,
+            amount,
+        )?;
+        ctx.accounts.treasury.record_collected(amount)?;
+
+        emit!(DustSwept {
+            market_id,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Voids a market left unresolved past its resolution deadline so its
+    /// bettors can be refunded. Anyone can call it.
+    pub fn void_unresolved(
+        ctx: Context<VoidUnresolved>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state
+        market.require_unsettled()?;
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            clock.unix_timestamp > market.resolution_deadline,
+            ErrorCode::ResolutionWindowOpen
+        );
+
+        market.void(clock.unix_timestamp, VoidReason::ResolutionDeadlinePassed);
+
+        Ok(())
+    }
+
+    /// Refunds a bet's stake once its market has passed the resolution
+    /// deadline unresolved, voiding the market on first use, and closes the
+    /// bet to its bettor. Anyone can submit it for any bet.
+    pub fn claim_timeout_refund(
+        ctx: Context<ClaimTimeoutRefund>,
+        market_id: u64,
+        bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+        let clock = Clock::get()?;
+
+        // Validate market and bet state
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.merged_into.is_none(), ErrorCode::MarketMerged);
+        market.require_payable()?;
+        market.require_currency(Currency::Spl)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(
+            ctx.accounts.bettor_token_account.owner == bet.bettor,
+            ErrorCode::UnauthorizedClaimer
+        );
+        if !market.is_settled() {
+            require!(
+                clock.unix_timestamp > market.resolution_deadline,
+                ErrorCode::ResolutionDeadlineNotReached
+            );
+            market.void(clock.unix_timestamp, VoidReason::ResolutionDeadlinePassed);
+        }
+        require!(market.refund_only, ErrorCode::MarketNotVoided);
+
+        // Mark as claimed before any tokens move; the bet is closed below
+        bet.is_claimed = true;
+        market.release_bet()?;
+
+        // Transfer the stake back to the bettor
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            market.key(),
+            market.vault_bump,
+            bet.amount,
+        )?;
+
+        ctx.accounts.user_position.record_payout(bet.amount)?;
+        if let Some(report) = market.record_payout(bet.amount)? {
+            emit!(report);
+        }
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(TimeoutRefund {
+                market_id,
+                bettor: bet.bettor,
+                bet_index,
+                amount: bet.amount,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_winnings(
+        ctx: Context<ClaimWinnings>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        // Validate market and bet state
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        market.require_currency(Currency::Spl)?;
+        require!(
+            market.vesting_period == 0 || market.refund_only,
+            ErrorCode::WinningsVest
+        );
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        bet.require_claim_released(Clock::get()?.unix_timestamp)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            bet.bettor == ctx.accounts.bettor.key(),
+            ErrorCode::UnauthorizedClaimer
+        );
+        if let Some(recipient) = &ctx.accounts.recipient_token_account {
+            require!(
+                recipient.mint == ctx.accounts.market_vault.mint,
+                ErrorCode::MintMismatch
+            );
+        }
+
+        // Throttle rapid claims from one wallet
+        ctx.accounts.user_profile.record_claim(
+            bet.bettor,
+            market.claim_cooldown,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Calculate winnings, holding large payouts for approval
+        let winnings = market.payout_for(bet)?;
+        if !bet.claim_approved && market.requires_claim_approval(winnings) {
+            bet.claim_pending = true;
+            bet.claim_held_at = Clock::get()?.unix_timestamp;
+            if market.logs(LogLevel::Minimal) {
+                emit!(LargeClaimHeld {
+                    market_id,
+                    bettor: bet.bettor,
+                    amount: winnings,
+                });
+            }
+            return Ok(());
+        }
+
+        // Mark as claimed before any tokens move
+        bet.is_claimed = true;
+
+        // Rounding across claimants can leave the vault a little short
+        let winnings = cap_to_vault(winnings, &ctx.accounts.market_vault)?;
+        let market_key = market.key();
+        let net = collect_fee(
+            &ctx.accounts.token_program,
+            market,
+            &ctx.accounts.market_vault,
+            ctx.accounts.treasury.as_mut(),
+            ctx.accounts.treasury_vault.as_ref(),
+            ctx.accounts.creator_fee_vault.as_ref(),
+            ctx.accounts.charity_token_account.as_ref(),
+            &ctx.accounts.mint,
+            market_key,
+            bet,
+            winnings,
+        )?;
+        let destination = ctx
+            .accounts
+            .recipient_token_account
+            .as_ref()
+            .unwrap_or(&ctx.accounts.bettor_token_account);
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            destination,
+            &ctx.accounts.mint,
+            market_key,
+            market.vault_bump,
+            net,
+        )?;
+
+        // Record the payout
+        if !market.refund_only {
+            ctx.accounts.user_profile.record_win(net)?;
+        }
+        ctx.accounts.user_position.record_payout(net)?;
+        if let Some(report) = market.record_payout(winnings)? {
+            emit!(report);
+        }
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id,
+                bettor: ctx.accounts.bettor.key(),
+                amount: net,
+            });
+        }
+
+        // Return the bet's rent once nothing more is owed on it; held claims
+        // returned early above and keep their account
+        let settled = market.is_bet_settled(bet);
+        close_settled_bet(market, bet, settled, ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Closes a losing bet on a resolved market, returning its rent to the
+    /// bettor. Bets on a merged market lose by the destination's resolution.
+    pub fn close_losing_bet(
+        ctx: Context<CloseLosingBet>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+
+        // Validate the bet lost
+        let settling = settling_market(market, ctx.accounts.dest_market.as_ref())?;
+        settling.require_settled(Clock::get()?.unix_timestamp)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            !settling.refund_only && settling.winning_outcome != Some(bet.outcome),
+            ErrorCode::BetNotLosing
+        );
+        market.release_bet()?;
+
+        Ok(())
+    }
+
+    /// Pays a winning bet into another token account owned by the bettor, for
+    /// when their usual account has been frozen or closed. Either the bettor or
+    /// the market authority (on the bettor's behalf) may submit it.
+    pub fn claim_to_alternate(
+        ctx: Context<ClaimToAlternate>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+        let claimer = ctx.accounts.claimer.key();
+
+        // Validate market and bet state
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        market.require_currency(Currency::Spl)?;
+        require!(
+            market.vesting_period == 0 || market.refund_only,
+            ErrorCode::WinningsVest
+        );
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        bet.require_claim_released(Clock::get()?.unix_timestamp)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            claimer == bet.bettor || claimer == market.authority,
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Validate the alternate destination
+        let recipient = &ctx.accounts.recipient_token_account;
+        require!(recipient.owner == bet.bettor, ErrorCode::UnauthorizedClaimer);
+        require!(
+            recipient.mint == ctx.accounts.market_vault.mint && !recipient.is_frozen(),
+            ErrorCode::TokenAccountUnusable
+        );
+
+        // Throttle rapid claims from one wallet
+        ctx.accounts.user_profile.record_claim(
+            bet.bettor,
+            market.claim_cooldown,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Calculate winnings, holding large payouts for approval
+        let winnings = market.payout_for(bet)?;
+        if !bet.claim_approved && market.requires_claim_approval(winnings) {
+            bet.claim_pending = true;
+            bet.claim_held_at = Clock::get()?.unix_timestamp;
+            if market.logs(LogLevel::Minimal) {
+                emit!(LargeClaimHeld {
+                    market_id,
+                    bettor: bet.bettor,
+                    amount: winnings,
+                });
+            }
+            return Ok(());
+        }
+
+        // Mark as claimed before any tokens move
+        bet.is_claimed = true;
+
+        // Rounding across claimants can leave the vault a little short
+        let winnings = cap_to_vault(winnings, &ctx.accounts.market_vault)?;
+        let market_key = market.key();
+        let net = collect_fee(
+            &ctx.accounts.token_program,
+            market,
+            &ctx.accounts.market_vault,
+            ctx.accounts.treasury.as_mut(),
+            ctx.accounts.treasury_vault.as_ref(),
+            ctx.accounts.creator_fee_vault.as_ref(),
+            ctx.accounts.charity_token_account.as_ref(),
+            &ctx.accounts.mint,
+            market_key,
+            bet,
+            winnings,
+        )?;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            recipient,
+            &ctx.accounts.mint,
+            market_key,
+            market.vault_bump,
+            net,
+        )?;
+
+        // Record the payout
+        if !market.refund_only {
+            ctx.accounts.user_profile.record_win(net)?;
+        }
+        ctx.accounts.user_position.record_payout(net)?;
+        if let Some(report) = market.record_payout(winnings)? {
+            emit!(report);
+        }
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id,
+                bettor: bet.bettor,
+                amount: net,
+            });
+        }
+
+        // Return the bet's rent once nothing more is owed on it
+        let settled = market.is_bet_settled(bet);
+        close_settled_bet(market, bet, settled, ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Pays a winning bet, or refunds a voided one, on a NativeSol market in
+    /// lamports. The payout math is the same as `claim_winnings`.
+    pub fn claim_winnings_native(
+        ctx: Context<ClaimWinningsNative>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        // Validate market and bet state
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        market.require_currency(Currency::NativeSol)?;
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            bet.bettor == ctx.accounts.bettor.key(),
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Throttle rapid claims from one wallet
+        ctx.accounts.user_profile.record_claim(
+            bet.bettor,
+            market.claim_cooldown,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Mark as claimed before any lamports move
+        let winnings = market.payout_for(bet)?;
+        bet.is_claimed = true;
+
+        // Rounding across claimants can leave the escrow a little short; its
+        // rent-exempt minimum is never paid out
+        let winnings = winnings.min(sol_vault_balance(&ctx.accounts.sol_vault)?);
+        ctx.accounts.sol_vault.sub_lamports(winnings)?;
+        ctx.accounts.bettor.add_lamports(winnings)?;
+
+        // Record the payout
+        if !market.refund_only {
+            ctx.accounts.user_profile.record_win(winnings)?;
+        }
+        ctx.accounts.user_position.record_payout(winnings)?;
+        if let Some(report) = market.record_payout(winnings)? {
+            emit!(report);
+        }
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id,
+                bettor: ctx.accounts.bettor.key(),
+                amount: winnings,
+            });
+        }
+
+        // Return the bet's rent once nothing more is owed on it
+        let settled = market.is_bet_settled(bet);
+        close_settled_bet(market, bet, settled, ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Opens a fixed-odds wager outside the pool: the maker escrows `stake`
+    /// on `outcome` and asks a taker to escrow `stake * odds_bps / 10_000`
+    /// against it. The winner takes both stakes; neither touches the pools.
+    pub fn create_p2p_offer(
+        ctx: Context<CreateP2pOffer>,
+        market_id: u64,
+        outcome: u8,
+        stake: u64,
+        odds_bps: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate market state and input
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        market.require_p2p_open(market_id, clock.unix_timestamp)?;
+        market.require_bettor_allowed(ctx.accounts.maker.key())?;
+        require!(outcome < market.outcome_count, ErrorCode::InvalidOutcome);
+        require!(stake >= market.min_bet_amount, ErrorCode::BetTooSmall);
+        let taker_stake = u64::try_from(stake as u128 * odds_bps as u128 / 10_000)
+            .map_err(|_| error!(ErrorCode::MathOverflow))?;
+        require!(taker_stake > 0, ErrorCode::InvalidOdds);
+
+        // Escrow the maker's stake
+        let received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.maker_token_account,
+            &mut ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            ctx.accounts.maker.to_account_info(),
+            stake,
+        )?;
+        require!(received == stake, ErrorCode::TransferFeeUnsupported);
+
+        let offer = &mut ctx.accounts.offer;
+        offer.market = market.key();
+        offer.maker = ctx.accounts.maker.key();
+        offer.outcome = outcome;
+        offer.maker_stake = stake;
+        offer.taker_stake = taker_stake;
+        offer.taker = None;
+        market.open_p2p_offers = market.open_p2p_offers.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        if market.logs(LogLevel::Full) {
+            emit!(P2pOfferCreated {
+                market_id,
+                maker: offer.maker,
+                outcome,
+                maker_stake: stake,
+                taker_stake,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Takes the other side of an open offer, escrowing the counter-stake.
+    pub fn accept_p2p_offer(
+        ctx: Context<AcceptP2pOffer>,
+        market_id: u64,
+    ) -> Result<()> {
+        let offer = &mut ctx.accounts.offer;
+        let clock = Clock::get()?;
+
+        // Validate market and offer state
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.market.require_p2p_open(market_id, clock.unix_timestamp)?;
+        ctx.accounts.market.require_bettor_allowed(ctx.accounts.taker.key())?;
+        require!(offer.taker.is_none(), ErrorCode::OfferAlreadyAccepted);
+        require!(
+            ctx.accounts.taker.key() != offer.maker,
+            ErrorCode::CannotAcceptOwnOffer
+        );
+
+        // Escrow the counter-stake
+        let received = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.taker_token_account,
+            &mut ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            ctx.accounts.taker.to_account_info(),
+            offer.taker_stake,
+        )?;
+        require!(received == offer.taker_stake, ErrorCode::TransferFeeUnsupported);
+
+        offer.taker = Some(ctx.accounts.taker.key());
+
+        if ctx.accounts.market.logs(LogLevel::Full) {
+            emit!(P2pOfferAccepted {
+                market_id,
+                maker: offer.maker,
+                taker: ctx.accounts.taker.key(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Withdraws an offer nobody has accepted, returning the maker's stake.
+    pub fn cancel_p2p_offer(ctx: Context<CancelP2pOffer>, market_id: u64) -> Result<()> {
+        let offer = &ctx.accounts.offer;
+
+        // Validate offer state
+        require!(offer.taker.is_none(), ErrorCode::OfferAlreadyMatched);
+
+        let market_key = ctx.accounts.market.key();
+        release_p2p_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.maker_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.market_vault,
+            market_key,
+            ctx.accounts.market.vault_bump,
+            offer.maker_stake,
+        )?;
+        close_p2p_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            ctx.accounts.maker.to_account_info(),
+            &ctx.accounts.market_vault,
+            market_key,
+            ctx.accounts.market.vault_bump,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.open_p2p_offers = market.open_p2p_offers.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+
+        if market.logs(LogLevel::Full) {
+            emit!(P2pOfferCancelled {
+                market_id,
+                maker: offer.maker,
+                refunded: offer.maker_stake,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Pays out an accepted offer once the market settles: the side that
+    /// backed the winning outcome takes both stakes, and a voided market
+    /// returns each stake. Anyone can call it; the offer and escrow rent go
+    /// back to the maker.
+    pub fn settle_p2p(ctx: Context<SettleP2p>, market_id: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let offer = &ctx.accounts.offer;
+
+        // Validate market and offer state
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        require!(offer.taker.is_some(), ErrorCode::OfferNotAccepted);
+
+        let (maker_payout, taker_payout) = match market.winning_outcome {
+            Some(outcome) if !market.refund_only => {
+                let pot = offer
+                    .maker_stake
+                    .checked_add(offer.taker_stake)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                if outcome == offer.outcome {
+                    (pot, 0)
+                } else {
+                    (0, pot)
+                }
+            }
+            _ => (offer.maker_stake, offer.taker_stake),
+        };
+
+        let market_key = market.key();
+        for (payout, to) in [
+            (maker_payout, &ctx.accounts.maker_token_account),
+            (taker_payout, &ctx.accounts.taker_token_account),
+        ] {
+            if payout > 0 {
+                release_p2p_escrow(
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.escrow,
+                    to,
+                    &ctx.accounts.mint,
+                    &ctx.accounts.market_vault,
+                    market_key,
+                    market.vault_bump,
+                    payout,
+                )?;
+            }
+        }
+        close_p2p_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            ctx.accounts.maker.to_account_info(),
+            &ctx.accounts.market_vault,
+            market_key,
+            market.vault_bump,
+        )?;
+        let market = &mut ctx.accounts.market;
+        market.open_p2p_offers = market.open_p2p_offers.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(P2pSettled {
+                market_id,
+                maker: offer.maker,
+                taker: offer.taker.unwrap_or_default(),
+                maker_payout,
+                taker_payout,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Releases a payout held for exceeding the market's high-value threshold.
+    /// On vesting markets it only lifts the hold, and the payout then vests
+    /// through `claim_vested`. A hold left unapproved for
+    /// `CLAIM_APPROVAL_TIMEOUT` no longer blocks the bettor's own claim.
+    pub fn approve_large_claim(
+        ctx: Context<ApproveLargeClaim>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate the bet is awaiting approval
+        market.require_payable()?;
+        market.require_currency(Currency::Spl)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.claim_pending, ErrorCode::ClaimNotPending);
+        require!(
+            ctx.accounts.bettor_token_account.owner == bet.bettor,
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Vesting payouts still vest, so approval only lifts the hold
+        if market.vesting_period > 0 && !market.refund_only {
+            bet.claim_pending = false;
+            bet.claim_approved = true;
+            return Ok(());
+        }
+
+        // Mark as claimed before any tokens move
+        bet.claim_pending = false;
+        bet.is_claimed = true;
+
+        // Transfer winnings, at most what the vault still holds
+        let winnings = cap_to_vault(market.payout_for(bet)?, &ctx.accounts.market_vault)?;
+        let market_key = market.key();
+        let net = collect_fee(
+            &ctx.accounts.token_program,
+            market,
+            &ctx.accounts.market_vault,
+            ctx.accounts.treasury.as_mut(),
+            ctx.accounts.treasury_vault.as_ref(),
+            ctx.accounts.creator_fee_vault.as_ref(),
+            ctx.accounts.charity_token_account.as_ref(),
+            &ctx.accounts.mint,
+            market_key,
+            bet,
+            winnings,
+        )?;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            market_key,
+            market.vault_bump,
+            net,
+        )?;
+
+        // Record the payout
+        if !market.refund_only {
+            ctx.accounts.user_profile.record_win(net)?;
+        }
+        ctx.accounts.user_position.record_payout(net)?;
+        if let Some(report) = market.record_payout(winnings)? {
+            emit!(report);
+        }
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id,
+                bettor: bet.bettor,
+                amount: net,
+            });
+        }
+
+        // Return the bet's rent once nothing more is owed on it
+        let settled = market.is_bet_settled(bet);
+        close_settled_bet(market, bet, settled, ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Pays the creator back their seed liquidity once the market settles:
+    /// the winning side's seed share of the pool, or the whole seed if voided.
+    /// If anyone else bet on a losing side the seed stays in the pool for the
+    /// winners and there is nothing to reclaim.
+    pub fn reclaim_seed(ctx: Context<ReclaimSeed>, _market_id: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+        require!(
+            ctx.accounts.authority_token_account.owner == market.authority,
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Validate the seed is owed a payout
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+        require!(!market.seed_claimed, ErrorCode::AlreadyClaimed);
+        require!(market.seed_owed(), ErrorCode::NoSeedToReclaim);
+
+        let payout = cap_to_vault(market.seed_payout()?, &ctx.accounts.market_vault)?;
+        market.seed_claimed = true;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.mint,
+            market.key(),
+            market.vault_bump,
+            payout,
+        )?;
+
+        if let Some(report) = market.record_payout(payout)? {
+            emit!(report);
+        }
+
+        Ok(())
+    }
+
+    /// Releases the vested part of a winning bet's payout on markets with a
+    /// vesting period. The first call locks the full payout into a Vesting
+    /// account; it then unlocks linearly from resolution over `vesting_period`.
+    /// Large payouts are held for approval first, as in `claim_winnings`.
+    pub fn claim_vested(
+        ctx: Context<ClaimVested>,
+        market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let bet = &mut ctx.accounts.bet;
+        let vesting = &mut ctx.accounts.vesting;
+        let clock = Clock::get()?;
+
+        // Validate market and bet state
+        market.require_settled(clock.unix_timestamp)?;
+        market.require_currency(Currency::Spl)?;
+        require!(
+            market.vesting_period > 0 && !market.refund_only,
+            ErrorCode::MarketNotVesting
+        );
+        bet.require_claim_released(clock.unix_timestamp)?;
+        require!(bet.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            bet.bettor == ctx.accounts.bettor.key(),
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Throttle rapid claims from one wallet
+        ctx.accounts.user_profile.record_claim(
+            bet.bettor,
+            market.claim_cooldown,
+            clock.unix_timestamp,
+        )?;
+
+        // Lock the payout, net of the fee, into the schedule on the first
+        // claim, holding large payouts for approval
+        let market_key = market.key();
+        if !bet.is_claimed {
+            let payout = market.payout_for(bet)?;
+            if !bet.claim_approved && market.requires_claim_approval(payout) {
+                bet.claim_pending = true;
+                bet.claim_held_at = clock.unix_timestamp;
+                if market.logs(LogLevel::Minimal) {
+                    emit!(LargeClaimHeld {
+                        market_id,
+                        bettor: bet.bettor,
+                        amount: payout,
+                    });
+                }
+                return Ok(());
+            }
+            bet.is_claimed = true;
+            vesting.bettor = bet.bettor;
+            vesting.market_id = market_id;
+            vesting.total_amount = collect_fee(
+                &ctx.accounts.token_program,
+                market,
+                &ctx.accounts.market_vault,
+                ctx.accounts.treasury.as_mut(),
+                ctx.accounts.treasury_vault.as_ref(),
+                ctx.accounts.creator_fee_vault.as_ref(),
+                ctx.accounts.charity_token_account.as_ref(),
+                &ctx.accounts.mint,
+                market_key,
+                bet,
+                payout,
+            )?;
+            vesting.released_amount = 0;
+            vesting.start_time = market.resolved_at;
+            vesting.end_time = market
+                .resolved_at
+                .checked_add(market.vesting_period)
+                .ok_or(ErrorCode::MathOverflow)?;
+            if let Some(report) = market.record_payout(payout)? {
+                emit!(report);
+            }
+        }
+
+        let releasable = vesting
+            .vested_amount(clock.unix_timestamp)?
+            .checked_sub(vesting.released_amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        require!(releasable > 0, ErrorCode::NothingVested);
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            market_key,
+            market.vault_bump,
+            releasable,
+        )?;
+        vesting.released_amount = vesting
+            .released_amount
+            .checked_add(releasable)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.user_profile.record_win(releasable)?;
+        ctx.accounts.user_position.record_payout(releasable)?;
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(VestedWinningsReleased {
+                market_id,
+                bettor: bet.bettor,
+                amount: releasable,
+                total_released: vesting.released_amount,
+                total_amount: vesting.total_amount,
+            });
+        }
+
+        // Once the whole payout is out neither the schedule nor the bet is
+        // needed
+        let settled = vesting.released_amount == vesting.total_amount;
+        if settled {
+            vesting.close(ctx.accounts.bettor.to_account_info())?;
+        }
+        close_settled_bet(market, bet, settled, ctx.accounts.bettor.to_account_info())?;
+
+        Ok(())
+    }
+
+    /// Folds a duplicate market into another one on the same question. The
+    /// source vault is emptied into the destination, its pools are added to the
+    /// destination's, and the source is marked as merged so its bettors claim
+    /// through `claim_merged_winnings` once the destination resolves. Admin
+    /// only; the markets must share their fee, payout and resolution terms.
+    pub fn merge_markets(
+        ctx: Context<MergeMarkets>,
+        source_market_id: u64,
+        dest_market_id: u64,
+    ) -> Result<()> {
+        let source = &mut ctx.accounts.source_market;
+        let dest = &mut ctx.accounts.dest_market;
+
+        // Validate admin
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::UnauthorizedAdmin
+        );
+
+        // Validate both markets are live and describe the same bet
+        require!(source_market_id != dest_market_id, ErrorCode::MarketsNotMergeable);
+        source.require_unsettled()?;
+        dest.require_unsettled()?;
+        source.require_parimutuel()?;
+        dest.require_parimutuel()?;
+        source.require_currency(Currency::Spl)?;
+        dest.require_currency(Currency::Spl)?;
+        require!(
+            source.merged_into.is_none() && dest.merged_into.is_none(),
+            ErrorCode::MarketMerged
+        );
+        require!(
+            source.mint == dest.mint
+                && source.description == dest.description
+                && source.vesting_period == 0
+                && dest.vesting_period == 0
+                && source.high_value_threshold == 0
+                && dest.high_value_threshold == 0
+                && source.outcome_count == dest.outcome_count
+                && source.early_bird_bps == 0
+                && dest.early_bird_bps == 0
+                && source.seed_pools.iter().all(|&seed| seed == 0)
+                && source.open_p2p_offers == 0
+                && source.charity == dest.charity
+                && source.charity_bps == dest.charity_bps
+                && source.resolver_bond == 0,
+            ErrorCode::MarketsNotMergeable
+        );
+
+        // Source bettors settle on the destination's terms, so they must match
+        require!(
+            source.fee_bps == dest.fee_bps
+                && source.closing_fee_bps == dest.closing_fee_bps
+                && source.creator_fee_bps == dest.creator_fee_bps
+                && source.creator_fee_exempt == dest.creator_fee_exempt
+                && source.max_payout_multiplier_bps == dest.max_payout_multiplier_bps
+                && source.resolution_source == dest.resolution_source
+                && source.end_time == dest.end_time
+                && source.min_side_pool == dest.min_side_pool,
+            ErrorCode::MarketsNotMergeable
+        );
+
+        // Move the source liquidity into the destination vault
+        let moved = ctx.accounts.source_vault.amount;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.source_vault,
+            &ctx.accounts.dest_vault,
+            &ctx.accounts.mint,
+            source.key(),
+            source.vault_bump,
+            moved,
+        )?;
+
+        // Combine totals and redirect the source
+        for (i, pool) in source.outcome_pools.iter().enumerate() {
+            dest.outcome_pools[i] = dest.outcome_pools[i]
+                .checked_add(*pool)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        for (i, pool) in source.outcome_weighted_pools.iter().enumerate() {
+            dest.outcome_weighted_pools[i] = dest.outcome_weighted_pools[i]
+                .checked_add(*pool)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        for (i, count) in source.outcome_bet_counts.iter().enumerate() {
+            dest.outcome_bet_counts[i] = dest.outcome_bet_counts[i]
+                .checked_add(*count)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        dest.bettor_count = dest
+            .bettor_count
+            .checked_add(source.bettor_count)
+            .ok_or(ErrorCode::MathOverflow)?;
+        source.merged_into = Some(dest_market_id);
+
+        // The source takes no more bets, so free its authority's open-market
+        // slot now; `close_market` closes it once every bet has been claimed
+        if let Some(stats) = ctx.accounts.source_authority_stats.as_mut() {
+            stats.open_market_count = stats.open_market_count.saturating_sub(1);
+        }
+        source.chain_audit(b"merge_out", &[&dest_market_id.to_le_bytes()]);
+        dest.chain_audit(
+            b"merge_in",
+            &[&source_market_id.to_le_bytes(), &moved.to_le_bytes()],
+        );
+
+        emit!(MarketsMerged {
+            source_market_id,
+            dest_market_id,
+            amount: moved,
+            outcome_pools: dest.outcome_pools.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Claims a winning bet placed on a market that was merged into
+    /// `dest_market_id`, paid from the destination's pool and vault.
+    pub fn claim_merged_winnings(
+        ctx: Context<ClaimMergedWinnings>,
+        source_market_id: u64,
+        dest_market_id: u64,
+        _bet_index: u64,
+    ) -> Result<()> {
+        let dest = &mut ctx.accounts.dest_market;
+        let bet = &mut ctx.accounts.bet;
+
+        // Validate the merge link and bet state
+        require!(
+            ctx.accounts.source_market.merged_into == Some(dest_market_id),
+            ErrorCode::MarketNotMerged
+        );
+        dest.require_settled(Clock::get()?.unix_timestamp)?;
+        dest.require_currency(Currency::Spl)?;
+        require!(!bet.is_claimed, ErrorCode::AlreadyClaimed);
+        require!(bet.market_id == source_market_id, ErrorCode::InvalidMarketId);
+        require!(
+            bet.bettor == ctx.accounts.bettor.key(),
+            ErrorCode::UnauthorizedClaimer
+        );
+
+        // Throttle rapid claims from one wallet
+        ctx.accounts.user_profile.record_claim(
+            bet.bettor,
+            dest.claim_cooldown,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Mark as claimed before any tokens move
+        bet.is_claimed = true;
+
+        // Calculate and transfer winnings from the destination pool
+        let winnings = cap_to_vault(dest.payout_for(bet)?, &ctx.accounts.dest_vault)?;
+        let dest_key = dest.key();
+        let net = collect_fee(
+            &ctx.accounts.token_program,
+            dest,
+            &ctx.accounts.dest_vault,
+            ctx.accounts.treasury.as_mut(),
+            ctx.accounts.treasury_vault.as_ref(),
+            ctx.accounts.creator_fee_vault.as_ref(),
+            ctx.accounts.charity_token_account.as_ref(),
+            &ctx.accounts.mint,
+            dest_key,
+            bet,
+            winnings,
+        )?;
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.dest_vault,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.mint,
+            dest_key,
+            dest.vault_bump,
+            net,
+        )?;
+
+        // Record the payout
+        if !dest.refund_only {
+            ctx.accounts.user_profile.record_win(net)?;
+        }
+        ctx.accounts.user_position.record_payout(net)?;
+        if let Some(report) = dest.record_payout(winnings)? {
+            emit!(report);
+        }
+
+        if dest.logs(LogLevel::Minimal) {
+            emit!(WinningsClaimed {
+                market_id: dest_market_id,
+                bettor: bet.bettor,
+                amount: net,
+            });
+        }
+
+        // Return the bet's rent once nothing more is owed on it, booked on
+        // the market it was placed on
+        let settled = dest.is_bet_settled(bet);
+        close_settled_bet(
+            &mut ctx.accounts.source_market,
+            bet,
+            settled,
+            ctx.accounts.bettor.to_account_info(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Replaces the description while no bets exist, growing the Market account
+    /// (paid by the authority) when the new text exceeds the creation limit.
+    /// Rent freed by shrinking it again returns what the authority paid first,
+    /// and the rest goes to the rent sponsor.
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        market_id: u64,
+        description: String,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state and input
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        market.require_unsettled()?;
+        require!(market.bettor_count == 0, ErrorCode::MarketHasBets);
+        require!(
+            description.len() <= MAX_EXTENDED_DESCRIPTION_LEN,
+            ErrorCode::DescriptionTooLong
+        );
+
+        // Fit the account to the new text: the authority pays to grow it, and
+        // rent freed by a shorter text goes back to the authority up to what
+        // it paid, then to the sponsor
+        let info = ctx.accounts.market.to_account_info();
+        let len = 8 + Market::space_with_description(description.len());
+        let mut authority_rent = market.authority_rent;
+        if len > info.data_len() {
+            let paid =
+                grow_account(&info, &ctx.accounts.authority, &ctx.accounts.system_program, len)?;
+            authority_rent = authority_rent.checked_add(paid).ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            let freed = shrink_account(&info, len)?;
+            let refund = freed.min(authority_rent);
+            info.sub_lamports(freed)?;
+            ctx.accounts.authority.add_lamports(refund)?;
+            ctx.accounts.rent_sponsor.add_lamports(freed - refund)?;
+            authority_rent -= refund;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.description = description;
+        market.authority_rent = authority_rent;
+
+        emit!(MetadataUpdated {
+            market_id,
+            description: market.description.clone(),
+            metadata_uri: market.metadata_uri.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Points the market at new off-chain metadata. Unlike the description,
+    /// this can change after bets are placed, up until resolution.
+    pub fn update_metadata_uri(
+        ctx: Context<UpdateMetadataUri>,
+        market_id: u64,
+        metadata_uri: String,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state and input
+        market.require_unsettled()?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(
+            metadata_uri.len() <= MAX_METADATA_URI_LEN,
+            ErrorCode::MetadataUriTooLong
+        );
+
+        market.metadata_uri = metadata_uri;
+
+        emit!(MetadataUpdated {
+            market_id,
+            description: market.description.clone(),
+            metadata_uri: market.metadata_uri.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Corrects the minimum bet while no bets exist. Once the first bet is
+    /// placed the minimum is fixed for everyone.
+    pub fn update_min_bet(
+        ctx: Context<UpdateMinBet>,
+        market_id: u64,
+        min_bet_amount: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state and input
+        market.require_unsettled()?;
+        require!(market.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(market.bettor_count == 0, ErrorCode::MarketHasBets);
+        require!(min_bet_amount > 0, ErrorCode::InvalidBetAmount);
+        require!(
+            market.max_bet_amount.unwrap_or(u64::MAX) >= min_bet_amount,
+            ErrorCode::InvalidBetAmount
+        );
+
+        market.min_bet_amount = min_bet_amount;
+
+        emit!(MinBetUpdated {
+            market_id,
+            min_bet_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Closes a resolved market, or a merged one whose bets have all been
+    /// claimed, once its vault has been fully paid out, returning the market
+    /// and vault rent to whoever sponsored their creation, and any rent the
+    /// authority paid to grow the description to the authority. NativeSol
+    /// markets pass their escrow too, which is closed with them.
+    pub fn close_market(
+        ctx: Context<CloseMarket>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state; a merged source settles through its
+        // destination once its bets are claimed
+        let merged = market.merged_into.is_some();
+        if !merged {
+            market.require_settled(Clock::get()?.unix_timestamp)?;
+        }
+        require!(ctx.accounts.market_vault.amount == 0, ErrorCode::VaultNotEmpty);
+        if market.currency == Currency::NativeSol {
+            let sol_vault = ctx.accounts.sol_vault.as_ref().ok_or(ErrorCode::SolVaultMissing)?;
+            require!(sol_vault_balance(sol_vault)? == 0, ErrorCode::VaultNotEmpty);
+        }
+        require!(market.resolver_bond == 0, ErrorCode::BondOutstanding);
+        require!(market.creator_fees_accrued == 0, ErrorCode::CreatorFeesOutstanding);
+        require!(market.open_p2p_offers == 0, ErrorCode::P2pOffersOutstanding);
+        require!(market.live_bets == 0, ErrorCode::BetsOutstanding);
+
+        // Free the authority's open-market slot; merging already freed it
+        if let (false, Some(stats)) = (merged, ctx.accounts.authority_stats.as_mut()) {
+            stats.open_market_count = stats.open_market_count.saturating_sub(1);
+        }
+
+        // Close the vault; the market account is closed by its constraint,
+        // less the rent the authority paid to grow it
+        close_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.rent_sponsor,
+            market.key(),
+            market.vault_bump,
+        )?;
+        refund_authority_rent(market, &ctx.accounts.authority)?;
+
+        emit!(MarketClosed {
+            market_id,
+            rent_recipient: market.rent_sponsor,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces a fully settled market with a compact Archive record, closing
+    /// the market and its emptied vault and returning their rent to the
+    /// sponsor. The archive's own rent is paid by the authority.
+    pub fn archive_market(
+        ctx: Context<ArchiveMarket>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate every payout has been made
+        market.require_settled(clock.unix_timestamp)?;
+        require!(
+            market.settled_bets == market.claimable_bets()?,
+            ErrorCode::MarketNotFullySettled
+        );
+        require!(ctx.accounts.market_vault.amount == 0, ErrorCode::VaultNotEmpty);
+        market.require_currency(Currency::Spl)?;
+        require!(market.resolver_bond == 0, ErrorCode::BondOutstanding);
+        require!(market.creator_fees_accrued == 0, ErrorCode::CreatorFeesOutstanding);
+        require!(market.open_p2p_offers == 0, ErrorCode::P2pOffersOutstanding);
+        require!(market.live_bets == 0, ErrorCode::BetsOutstanding);
+
+        // Free the authority's open-market slot
+        if let Some(stats) = ctx.accounts.authority_stats.as_mut() {
+            stats.open_market_count = stats.open_market_count.saturating_sub(1);
+        }
+
+        let archive = &mut ctx.accounts.archive;
+        archive.market_id = market_id;
+        archive.authority = market.authority;
+        archive.winning_outcome = market.winning_outcome;
+        archive.refund_only = market.refund_only;
+        archive.outcome_pools = market.outcome_pools.clone();
+        archive.total_paid = market.total_paid;
+        archive.created_at = market.created_at;
+        archive.end_time = market.end_time;
+        archive.resolved_at = market.resolved_at;
+        archive.archived_at = clock.unix_timestamp;
+
+        // Close the vault; the market account is closed by its constraint,
+        // less the rent the authority paid to grow it
+        close_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.market_vault,
+            &ctx.accounts.rent_sponsor,
+            market.key(),
+            market.vault_bump,
+        )?;
+        refund_authority_rent(market, &ctx.accounts.authority)?;
+
+        emit!(MarketArchived {
+            market_id,
+            archive: archive.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Closes settled bets in bulk, returning each account's rent to its owner.
+    /// `remaining_accounts` holds (bet, owner wallet) pairs; bets that still
+    /// carry a claim are skipped rather than failing the batch. Bets on a
+    /// merged market settle by the destination's resolution.
+    pub fn close_bets_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseBetsBatch<'info>>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let dest_market = ctx.accounts.dest_market.as_ref();
+        let market_key = market.key();
+
+        let pairs = ctx.remaining_accounts.chunks_exact(2);
+        settling_market(market, dest_market)?.require_settled(Clock::get()?.unix_timestamp)?;
+        require!(pairs.remainder().is_empty(), ErrorCode::InvalidRemainingAccounts);
+
+        let mut closed: u32 = 0;
+        let mut skipped: u32 = 0;
+        for pair in pairs {
+            let (bet_info, owner_info) = (&pair[0], &pair[1]);
+            let bet = Account::<Bet>::try_from(bet_info)?;
+
+            // Only the canonical bet PDA for this market may be closed, and
+            // only back to its own bettor
+            let (expected, _) = Pubkey::find_program_address(
+                &[
+                    b"bet",
+                    market_key.as_ref(),
+                    bet.bettor.as_ref(),
+                    &bet.bet_index.to_le_bytes(),
+                ],
+                ctx.program_id,
+            );
+            require!(
+                bet_info.key() == expected && bet.market_id == market_id,
+                ErrorCode::InvalidMarketId
+            );
+            require!(owner_info.key() == bet.bettor, ErrorCode::UnauthorizedClaimer);
+
+            if !settling_market(market, dest_market)?.is_bet_settled(&bet) {
+                skipped += 1;
+                continue;
+            }
+            market.release_bet()?;
+            bet.close(owner_info.clone())?;
+            closed += 1;
+        }
+
+        emit!(BetsClosed {
+            market_id,
+            closed,
+            skipped,
+        });
+
+        Ok(())
+    }
+
+    /// Refunds a voided market's bettors in bulk. `remaining_accounts` holds
+    /// (bet, bettor token account, user position) triples; bets already
+    /// refunded and frozen token accounts are skipped rather than failing the
+    /// batch.
+    pub fn refund_all<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundAll<'info>>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let market_key = market.key();
+
+        // Validate authority
+        require!(
+            ctx.accounts.authority.key() == market.authority,
+            ErrorCode::UnauthorizedResolver
+        );
+
+        // Validate market state
+        market.require_payable()?;
+        market.require_currency(Currency::Spl)?;
+        let triples = ctx.remaining_accounts.chunks_exact(3);
+        require!(market.is_settled() && market.refund_only, ErrorCode::MarketNotVoided);
+        require!(triples.remainder().is_empty(), ErrorCode::InvalidRemainingAccounts);
+
+        let mut refunded: u32 = 0;
+        let mut skipped: u32 = 0;
+        for triple in triples {
+            let (bet_info, token_info, position_info) = (&triple[0], &triple[1], &triple[2]);
+            let mut bet = Account::<Bet>::try_from(bet_info)?;
+            let bettor_token_account = InterfaceAccount::<TokenAccount>::try_from(token_info)?;
+
+            // Only the canonical bet PDA for this market, paid to its bettor
+            let (expected, _) = Pubkey::find_program_address(
+                &[
+                    b"bet",
+                    market_key.as_ref(),
+                    bet.bettor.as_ref(),
+                    &bet.bet_index.to_le_bytes(),
+                ],
+                ctx.program_id,
+            );
+            require!(
+                bet_info.key() == expected && bet.market_id == market_id,
+                ErrorCode::InvalidMarketId
+            );
+            require!(
+                bettor_token_account.owner == bet.bettor,
+                ErrorCode::UnauthorizedClaimer
+            );
+
+            if bet.is_claimed || bettor_token_account.is_frozen() {
+                skipped += 1;
+                continue;
+            }
+
+            let refund = market.payout_for(&bet)?;
+            bet.is_claimed = true;
+            transfer_from_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.market_vault,
+                &bettor_token_account,
+                &ctx.accounts.mint,
+                market_key,
+                market.vault_bump,
+                refund,
+            )?;
+
+            bet.exit(ctx.program_id)?;
+
+            // Bets placed before positions existed may have none to update
+            let (expected_position, _) = Pubkey::find_program_address(
+                &[b"user_position", market_key.as_ref(), bet.bettor.as_ref()],
+                ctx.program_id,
+            );
+            require!(
+                position_info.key() == expected_position,
+                ErrorCode::InvalidRemainingAccounts
+            );
+            if !position_info.data_is_empty() {
+                let mut position = Account::<UserPosition>::try_from(position_info)?;
+                position.record_payout(refund)?;
+                position.exit(ctx.program_id)?;
+            }
+            if let Some(report) = market.record_payout(refund)? {
+                emit!(report);
+            }
+            refunded += 1;
+        }
+
+        emit!(BetsRefunded {
+            market_id,
+            refunded,
+            skipped,
+        });
+
+        Ok(())
+    }
+
+    /// Crank that stores each winning bet's payout on the bet account once the
+    /// market resolves, so claims transfer the stored value instead of pricing
+    /// it. `remaining_accounts` holds writable bet accounts; bets already
+    /// claimed, already priced or owed nothing are skipped.
+    pub fn precompute_payouts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PrecomputePayouts<'info>>,
+        market_id: u64,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let market_key = market.key();
+        market.require_settled(Clock::get()?.unix_timestamp)?;
+
+        let mut stored: u32 = 0;
+        let mut skipped: u32 = 0;
+        for bet_info in ctx.remaining_accounts {
+            let mut bet = Account::<Bet>::try_from(bet_info)?;
+
+            // Only the canonical bet PDAs for this market may be priced
+            let (expected, _) = Pubkey::find_program_address(
+                &[
+                    b"bet",
+                    market_key.as_ref(),
+                    bet.bettor.as_ref(),
+                    &bet.bet_index.to_le_bytes(),
+                ],
+                ctx.program_id,
+            );
+            require!(
+                bet_info.key() == expected && bet.market_id == market_id,
+                ErrorCode::InvalidMarketId
+            );
+
+            if bet.is_claimed || bet.payout_amount.is_some() {
+                skipped += 1;
+                continue;
+            }
+            match market.payout_for(&bet) {
+                Ok(payout) => bet.payout_amount = Some(payout),
+                Err(_) => {
+                    skipped += 1;
+                    continue;
+                }
+            }
+            bet.exit(ctx.program_id)?;
+            stored += 1;
+        }
+
+        emit!(PayoutsPrecomputed {
+            market_id,
+            stored,
+            skipped,
+        });
+
+        Ok(())
+    }
+
+    /// Reports whether `bettor`'s bet on this market won, `Pending` while the
+    /// market is unresolved, or `Voided` when stakes are being refunded.
+    pub fn did_win(
+        ctx: Context<ViewBet>,
+        _market_id: u64,
+        _bettor: Pubkey,
+        _bet_index: u64,
+    ) -> Result<BetResult> {
+        let market = &ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+
+        Ok(match (market.is_settled(), market.winning_outcome) {
+            (true, _) if market.refund_only => BetResult::Voided,
+            (true, Some(outcome)) if bet.outcome == outcome => BetResult::Won,
+            (true, Some(_)) => BetResult::Lost,
+            _ => BetResult::Pending,
+        })
+    }
+
+    /// Runs place_bet's checks for `bettor` without moving funds and returns
+    /// the error code the bet would fail with, or 0 if it would pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn dry_run_bet(
+        ctx: Context<DryRunBet>,
+        market_id: u64,
+        bettor: Pubkey,
+        outcome: u8,
+        amount: u64,
+        min_expected_ratio: Option<u64>,
+        whitelist_proof: Option<Vec<[u8; 32]>>,
+    ) -> Result<u32> {
+        match check_spl_bet(
+            &ctx.accounts.config,
+            &ctx.accounts.market,
+            market_id,
+            bettor,
+            outcome,
+            amount,
+            min_expected_ratio,
+            whitelist_proof.as_deref().unwrap_or_default(),
+        ) {
+            Ok(()) => Ok(0),
+            Err(Error::AnchorError(err)) => Ok(err.error_code_number),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the market's running audit hash; see `Market::chain_audit`.
+    pub fn get_audit_hash(
+        ctx: Context<ViewMarket>,
+        _market_id: u64,
+    ) -> Result<[u8; 32]> {
+        Ok(ctx.accounts.market.audit_hash)
+    }
+
+    /// Returns the implied probability of `outcome`, scaled by 10^odds_decimals.
+    pub fn implied_probability(
+        ctx: Context<ViewMarket>,
+        _market_id: u64,
+        outcome: u8,
+    ) -> Result<u64> {
+        Ok(ctx.accounts.market.implied_probability(outcome))
+    }
+
+    /// Returns what a new bet of `amount` on `outcome` by `bettor` would be
+    /// paid, net of the market fee and charity cut, if that outcome won at the
+    /// current pools. Uses the same pool and fee math as the claim
+    /// instructions, fee exemption included.
+    pub fn preview_winnings(
+        ctx: Context<ViewMarket>,
+        _market_id: u64,
+        outcome: u8,
+        amount: u64,
+        bettor: Pubkey,
+    ) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(outcome < market.outcome_count, ErrorCode::InvalidOutcome);
+        require!(amount > 0, ErrorCode::InvalidBetAmount);
+
+        let gross = market.projected_winnings(outcome, amount)?;
+        market.net_of_cuts(gross, Clock::get()?.unix_timestamp, bettor)
+    }
+
+    /// Returns the probability, scaled by 10^odds_decimals, that `outcome` must
+    /// exceed for a new bet of `amount` on it by `bettor` to be +EV at current
+    /// pools.
+    pub fn break_even(
+        ctx: Context<ViewMarket>,
+        _market_id: u64,
+        outcome: u8,
+        amount: u64,
+        bettor: Pubkey,
+    ) -> Result<u64> {
+        require!(amount > 0, ErrorCode::InvalidBetAmount);
+        ctx.accounts
+            .market
+            .break_even_probability(outcome, amount, Clock::get()?.unix_timestamp, bettor)
+    }
+
+    /// Returns the current payout multiplier (total pool / outcome pool) for
+    /// `outcome`, scaled by 10^odds_decimals.
+    pub fn odds(
+        ctx: Context<ViewMarket>,
+        _market_id: u64,
+        outcome: u8,
+    ) -> Result<u64> {
+        Ok(ctx.accounts.market.payout_multiplier(outcome))
+    }
+
+    /// Returns the current payout multiplier (total pool / outcome pool) of
+    /// every outcome in basis points, YES then NO for binary markets. An
+    /// empty side reports `u64::MAX`.
+    pub fn current_odds(ctx: Context<ViewMarket>, _market_id: u64) -> Result<Vec<u64>> {
+        let market = &ctx.accounts.market;
+        Ok((0..market.outcome_count)
+            .map(|outcome| market.payout_multiplier_at(outcome, 10_000))
+            .collect())
+    }
+}
+
+/// The accounts `create_market` and `create_market_auto` share.
+struct NewMarket<'a, 'info> {
+    market: &'a mut Account<'info, Market>,
+    market_vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    mint: &'a InterfaceAccount<'info, Mint>,
+    authority: &'a Signer<'info>,
+    authority_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    bond_vault: Option<&'a mut InterfaceAccount<'info, TokenAccount>>,
+    creator_fee_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    sol_vault: Option<&'a mut Account<'info, SolVault>>,
+    config: &'a Account<'info, Config>,
+    authority_stats: &'a mut Account<'info, AuthorityStats>,
+    vault_bump: u8,
+    payer: &'a Signer<'info>,
+    token_program: &'a Interface<'info, TokenInterface>,
+}
+
+/// Validates the market parameters and initializes a freshly created market
+/// under `market_id`, taking the seed, LMSR subsidy and resolver bond from
+/// the authority.
+fn init_market(
+    mut accounts: NewMarket,
+    market_id: u64,
+    description: String,
+    end_time: i64,
+    min_bet_amount: u64,
+    options: MarketOptions,
+) -> Result<()> {
+    let market = &mut *accounts.market;
+    let clock = Clock::get()?;
+
+    // Validate inputs
+    require!(end_time > clock.unix_timestamp, ErrorCode::InvalidEndTime);
+    require!(
+        end_time <= clock.unix_timestamp.saturating_add(MAX_MARKET_DURATION),
+        ErrorCode::EndTimeTooFar
+    );
+    require!(description.len() <= MAX_DESCRIPTION_LEN, ErrorCode::DescriptionTooLong);
+    require!(
+        options.resolution_criteria.len() <= MAX_RESOLUTION_CRITERIA_LEN,
+        ErrorCode::ResolutionCriteriaTooLong
+    );
+    require!(
+        options.metadata_uri.len() <= MAX_METADATA_URI_LEN,
+        ErrorCode::MetadataUriTooLong
+    );
+    require!(min_bet_amount > 0, ErrorCode::InvalidBetAmount);
+    require!(options.odds_decimals <= MAX_ODDS_DECIMALS, ErrorCode::InvalidOddsPrecision);
+    require!(
+        (2..=MAX_OUTCOMES).contains(&options.outcome_count),
+        ErrorCode::InvalidOutcomeCount
+    );
+    require!(
+        options.outcome_caps.is_empty()
+            || options.outcome_caps.len() == options.outcome_count as usize,
+        ErrorCode::InvalidOutcomeCount
+    );
+    require!(options.vesting_period >= 0, ErrorCode::InvalidVestingPeriod);
+    require!(options.min_bet_pool_bps <= 10_000, ErrorCode::InvalidBasisPoints);
+    require!(options.majority_margin_bps <= 10_000, ErrorCode::InvalidBasisPoints);
+    require!(
+        options.resolution_source.is_supported(),
+        ErrorCode::UnsupportedResolutionSource
+    );
+    require!(
+        options.max_payout_multiplier_bps == 0 || options.max_payout_multiplier_bps >= 10_000,
+        ErrorCode::InvalidPayoutCap
+    );
+    require!(options.claim_cooldown >= 0, ErrorCode::InvalidClaimCooldown);
+    require!(
+        options.prior_yes_bps.unwrap_or(DEFAULT_PRIOR_YES_BPS) <= 10_000,
+        ErrorCode::InvalidBasisPoints
+    );
+    let seed_total = options
+        .seed_amount
+        .checked_add(options.initial_yes)
+        .and_then(|total| total.checked_add(options.initial_no))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(options.early_bird_bps <= 10_000, ErrorCode::InvalidBasisPoints);
+    require!(
+        options.resolution_deadline > end_time,
+        ErrorCode::InvalidResolutionDeadline
+    );
+    // A fee schedule peaks at one of its ends
+    let peak_fee_bps = options.fee_bps.max(options.closing_fee_bps.unwrap_or(0));
+    require!(
+        peak_fee_bps as u32 + options.creator_fee_bps as u32 <= MAX_FEE_BPS as u32,
+        ErrorCode::FeeTooHigh
+    );
+    require!(
+        options.charity.is_some() == (options.charity_bps > 0)
+            && peak_fee_bps as u32 + options.creator_fee_bps as u32 + options.charity_bps as u32
+                <= 10_000,
+        ErrorCode::InvalidCharityConfig
+    );
+    require!(
+        options.max_bet_amount.unwrap_or(u64::MAX) >= min_bet_amount,
+        ErrorCode::InvalidBetAmount
+    );
+    require!(options.challenge_period >= 0, ErrorCode::InvalidChallengePeriod);
+    require!(options.dispute_bond_bps <= 10_000, ErrorCode::InvalidDisputeBond);
+    require!(
+        options.challenge_period == 0 || options.dispute_bond > 0 || options.dispute_bond_bps > 0,
+        ErrorCode::DisputeBondRequired
+    );
+    require!(
+        options.betting_cutoff_offset >= 0
+            && options.betting_cutoff_offset < end_time - clock.unix_timestamp,
+        ErrorCode::InvalidBettingCutoff
+    );
+    require!(
+        options.resolvers.len() <= MAX_RESOLVERS,
+        ErrorCode::TooManyResolvers
+    );
+    require!(
+        options.resolver_threshold as usize <= options.resolvers.len(),
+        ErrorCode::InvalidResolverThreshold
+    );
+    require!(
+        options.resolution_source != ResolutionSource::Oracle
+            || ((options.oracle_feed.is_some() && options.oracle_feed_id != [0; 32]
+                || !options.oracle_feeds.is_empty())
+                && options.outcome_count == 2
+                && options.max_oracle_staleness > 0),
+        ErrorCode::InvalidOracleConfig
+    );
+    require!(
+        options.oracle_feeds.is_empty()
+            || (options.oracle_feeds.len() <= MAX_ORACLE_FEEDS
+                && options.min_oracle_feeds > 0
+                && options.min_oracle_feeds as usize <= options.oracle_feeds.len()
+                && options.oracle_feeds.iter().enumerate().all(|(i, feed)| {
+                    feed.feed_id != [0; 32]
+                        && options.oracle_feeds[..i].iter().all(|other| other.account != feed.account)
+                })),
+        ErrorCode::InvalidOracleConfig
+    );
+    require!(
+        options.resolution_source != ResolutionSource::CommitReveal
+            || options.reveal_window > 0,
+        ErrorCode::InvalidRevealWindow
+    );
+    if options.currency == Currency::NativeSol {
+        require!(
+            options.market_type == MarketType::Parimutuel
+                && seed_total == 0
+                && options.resolver_bond == 0
+                && options.fee_bps == 0
+                && options.creator_fee_bps == 0
+                && options.charity_bps == 0
+                && options.vesting_period == 0
+                && options.high_value_threshold == 0,
+            ErrorCode::InvalidNativeConfig
+        );
+    }
+    if let MarketType::Lmsr { liquidity } = options.market_type {
+        require!(
+            liquidity > 0
+                && options.outcome_count == 2
+                && seed_total == 0
+                && options.outcome_caps.is_empty()
+                && options.max_payout_multiplier_bps == 0
+                && options.early_bird_bps == 0,
+            ErrorCode::InvalidLmsrConfig
+        );
+    }
+
+    // Count the market against its authority's cap
+    let stats = &mut accounts.authority_stats;
+    let max_open = accounts.config.max_open_markets_per_authority;
+    require!(
+        max_open == 0 || stats.open_market_count < max_open,
+        ErrorCode::TooManyOpenMarkets
+    );
+    stats.authority = accounts.authority.key();
+    stats.open_market_count = stats.open_market_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+    // Initialize market
+    market.authority = accounts.authority.key();
+    market.market_id = market_id;
+    market.version = MARKET_VERSION;
+    market.vault_bump = accounts.vault_bump;
+    market.description = description;
+    market.end_time = end_time;
+    market.min_bet_amount = min_bet_amount;
+    market.outcome_count = options.outcome_count;
+    market.outcome_pools = vec![0; options.outcome_count as usize];
+    market.outcome_weighted_pools = vec![0; options.outcome_count as usize];
+    market.market_type = options.market_type;
+    market.outcome_shares = vec![0; options.outcome_count as usize];
+    market.lmsr_subsidy = 0;
+    market.status = MarketStatus::Open;
+    market.winning_outcome = None;
+    market.created_at = clock.unix_timestamp;
+    market.odds_decimals = options.odds_decimals;
+    market.vesting_period = options.vesting_period;
+    market.resolved_at = 0;
+    market.mint = accounts.mint.key();
+    market.merged_into = None;
+    market.outcome_caps = if options.outcome_caps.is_empty() {
+        vec![0; options.outcome_count as usize]
+    } else {
+        options.outcome_caps
+    };
+    market.high_value_threshold = options.high_value_threshold;
+    market.min_bet_pool_bps = options.min_bet_pool_bps;
+    market.majority_margin_bps = options.majority_margin_bps;
+    market.resolution_source = options.resolution_source;
+    market.rent_sponsor = accounts.payer.key();
+    market.max_payout_multiplier_bps = options.max_payout_multiplier_bps;
+    market.outcome_bet_counts = vec![0; options.outcome_count as usize];
+    market.settled_bets = 0;
+    market.total_paid = 0;
+    market.claim_cooldown = options.claim_cooldown;
+    market.log_level = options.log_level;
+    market.min_side_pool = options.min_side_pool;
+    market.min_pool_to_settle = options.min_pool_to_settle;
+    market.resolvers = options.resolvers;
+    market.resolver_threshold = options.resolver_threshold;
+    market.refund_only = false;
+    market.seed_pools = vec![0; options.outcome_count as usize];
+    market.seed_claimed = false;
+    market.reveal_window = options.reveal_window;
+    market.outcome_commitment = None;
+    market.resolution_deadline = options.resolution_deadline;
+    market.fee_bps = options.fee_bps;
+    market.total_fees = 0;
+    market.creator_fee_bps = options.creator_fee_bps;
+    market.creator_fees_accrued = 0;
+    market.creator_fee_exempt = options.creator_fee_exempt;
+    market.closing_fee_bps = options.closing_fee_bps;
+    market.dust_swept = false;
+    market.betting_cutoff_offset = options.betting_cutoff_offset;
+    market.resolution_criteria = options.resolution_criteria;
+    market.currency = options.currency;
+    market.authority_cannot_bet = options.authority_cannot_bet;
+    market.whitelist_root = options.whitelist_root;
+    market.early_bird_bps = options.early_bird_bps;
+    market.bettor_count = 0;
+    market.open_p2p_offers = 0;
+    market.max_bettors = options.max_bettors;
+    market.charity = options.charity;
+    market.charity_bps = options.charity_bps;
+    if options.currency == Currency::NativeSol {
+        let sol_vault = accounts.sol_vault.as_mut().ok_or(ErrorCode::SolVaultMissing)?;
+        sol_vault.market = market.key();
+    }
+    require!(
+        options.creator_fee_bps == 0 || accounts.creator_fee_vault.is_some(),
+        ErrorCode::CreatorFeeVaultMissing
+    );
+    market.max_bet_amount = options.max_bet_amount;
+    market.challenge_period = options.challenge_period;
+    market.dispute_bond = options.dispute_bond;
+    market.dispute_bond_bps = options.dispute_bond_bps;
+    market.disputed_until = 0;
+    market.oracle_feed = options.oracle_feed;
+    market.oracle_feed_id = options.oracle_feed_id;
+    market.oracle_feeds = options.oracle_feeds.clone();
+    market.min_oracle_feeds = options.min_oracle_feeds;
+    market.resolution_threshold = options.resolution_threshold;
+    market.max_oracle_staleness = options.max_oracle_staleness;
+    market.metadata_uri = options.metadata_uri;
+    market.resolver_bond = 0;
+    market.resolver_defaulted = false;
+    market.disputer = None;
+    market.dispute_pending = false;
+    market.audit_hash = [0; 32];
+    market.chain_audit(
+        b"create",
+        &[
+            &market_id.to_le_bytes(),
+            accounts.authority.key.as_ref(),
+            &end_time.to_le_bytes(),
+            &seed_total.to_le_bytes(),
+        ],
+    );
+
+    // Seed the pools from the creator: initial_yes and initial_no as given,
+    // seed_amount split by the prior probability
+    if seed_total > 0 {
+        require!(options.outcome_count == 2, ErrorCode::SeedRequiresBinary);
+        let creator_account = accounts
+            .authority_token_account
+            .ok_or(ErrorCode::SeedAccountMissing)?;
+        let seeded = deposit_to_vault(
+            accounts.token_program,
+            creator_account,
+            accounts.market_vault,
+            accounts.mint,
+            accounts.authority.to_account_info(),
+            seed_total,
+        )?;
+        let prior_yes_bps = options.prior_yes_bps.unwrap_or(DEFAULT_PRIOR_YES_BPS);
+        let wanted_yes = options.initial_yes as u128
+            + options.seed_amount as u128 * prior_yes_bps as u128 / 10_000;
+        // A transfer fee comes out of both sides in proportion
+        let seed_yes = (seeded as u128 * wanted_yes / seed_total as u128) as u64;
+        let seed_no = seeded - seed_yes;
+
+        market.seed_pools = vec![seed_yes, seed_no];
+        market.outcome_pools = vec![seed_yes, seed_no];
+        market.outcome_weighted_pools = vec![seed_yes, seed_no];
+
+        emit!(LiquiditySeeded {
+            market_id,
+            authority: accounts.authority.key(),
+            seed_pools: market.seed_pools.clone(),
+        });
+    }
+
+    // Fund the LMSR market maker's worst-case loss from the creator
+    if let MarketType::Lmsr { liquidity } = options.market_type {
+        let creator_account = accounts
+            .authority_token_account
+            .ok_or(ErrorCode::SeedAccountMissing)?;
+        let subsidy = lmsr_subsidy(liquidity)?;
+        let received = deposit_to_vault(
+            accounts.token_program,
+            creator_account,
+            accounts.market_vault,
+            accounts.mint,
+            accounts.authority.to_account_info(),
+            subsidy,
+        )?;
+        require!(received == subsidy, ErrorCode::TransferFeeUnsupported);
+        market.lmsr_subsidy = subsidy;
+    }
+
+    // Lock the resolver bond until the resolution is beyond dispute
+    if options.resolver_bond > 0 {
+        let (Some(creator_account), Some(bond_vault)) = (
+            accounts.authority_token_account.as_ref(),
+            accounts.bond_vault.as_mut(),
+        ) else {
+            return err!(ErrorCode::BondAccountsMissing);
+        };
+        market.resolver_bond = deposit_to_vault(
+            accounts.token_program,
+            creator_account,
+            bond_vault,
+            accounts.mint,
+            accounts.authority.to_account_info(),
+            options.resolver_bond,
+        )?;
+    }
+
+    emit!(MarketCreated {
+        market_id,
+        authority: accounts.authority.key(),
+        description: market.description.clone(),
+        end_time,
+    });
+
+    Ok(())
+}
+
+/// Closes a claimed bet once `settled` says nothing more is owed on it,
+/// returning its rent to `rent_receiver` and booking the close on `market`,
+/// the market the bet was placed on. Every claim path ends here.
+fn close_settled_bet<'info>(
+    market: &mut Market,
+    bet: &Account<'info, Bet>,
+    settled: bool,
+    rent_receiver: AccountInfo<'info>,
+) -> Result<()> {
+    if settled {
+        market.release_bet()?;
+        bet.close(rent_receiver)?;
+    }
+    Ok(())
+}
+
+/// The market whose resolution settles `market`'s bets: the market itself, or
+/// the destination a merged market was folded into, which must then be passed.
+fn settling_market<'a>(
+    market: &'a Market,
+    dest_market: Option<&'a Account<Market>>,
+) -> Result<&'a Market> {
+    match market.merged_into {
+        None => Ok(market),
+        Some(dest_market_id) => {
+            let dest = dest_market.ok_or(ErrorCode::MarketNotMerged)?;
+            require!(dest.market_id == dest_market_id, ErrorCode::MarketNotMerged);
+            Ok(dest)
+        }
+    }
+}
+
+/// Every check place_bet makes before moving funds, shared with dry_run_bet
+/// so the dry run fails exactly where the bet would.
+#[allow(clippy::too_many_arguments)]
+fn check_spl_bet(
+    config: &Config,
+    market: &Market,
+    market_id: u64,
+    bettor: Pubkey,
+    outcome: u8,
+    amount: u64,
+    min_expected_ratio: Option<u64>,
+    whitelist_proof: &[[u8; 32]],
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(!config.paused, ErrorCode::ProgramPaused);
+    market.require_currency(Currency::Spl)?;
+    market.validate_bet(market_id, outcome, amount, clock.unix_timestamp)?;
+    market.require_bettor_allowed(bettor)?;
+    market.require_whitelisted(bettor, whitelist_proof)?;
+    market.require_room_for_bettor()?;
+    if let Some(min_ratio) = min_expected_ratio {
+        require!(
+            market.expected_ratio_bps(outcome, amount)? >= min_ratio,
+            ErrorCode::SlippageExceeded
+        );
+    }
+    Ok(())
+}
+
+/// The counter account of the campaign a bet is tagged with. It must be
+/// passed exactly when the bet is tagged, so every stake it counted can be
+/// taken back off when the bet is cancelled or reduced.
+fn tagged_campaign<'a, 'info>(
+    campaign_id: Option<u64>,
+    campaign: Option<&'a mut Account<'info, Campaign>>,
+) -> Result<Option<&'a mut Account<'info, Campaign>>> {
+    match (campaign_id, campaign) {
+        (None, None) => Ok(None),
+        (Some(id), Some(campaign)) if campaign.campaign_id == id => Ok(Some(campaign)),
+        _ => err!(ErrorCode::CampaignMismatch),
+    }
+}
+
+/// Books a validated, funded bet: pool totals, the Bet account, the
+/// bettor's position, campaign volume and the BetPlaced event.
+#[allow(clippy::too_many_arguments)]
+fn record_bet(
+    market: &mut Market,
+    bet: &mut Bet,
+    bettor: Pubkey,
+    bet_index: u64,
+    bet_outcome: u8,
+    amount: u64,
+    campaign_id: Option<u64>,
+    campaign: Option<&mut Account<Campaign>>,
+    position: &mut UserPosition,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    market.require_room_for_bettor()?;
+
+    // Update market totals
+    let outcome = bet_outcome as usize;
+    market.bettor_count = market.bettor_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    market.live_bets = market.live_bets.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    market.outcome_pools[outcome] = market.outcome_pools[outcome]
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    market.outcome_bet_counts[outcome] = market.outcome_bet_counts[outcome]
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    bet.weighted_amount = market.early_bird_weight(amount, clock.unix_timestamp)?;
+    market.outcome_weighted_pools[outcome] = market.outcome_weighted_pools[outcome]
+        .checked_add(bet.weighted_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    bet.side_pool_at_bet = market.outcome_pools[outcome];
+    bet.total_pool_at_bet = market.checked_total_pool()?;
+    market.chain_audit(b"bet", &[bettor.as_ref(), &[bet_outcome], &amount.to_le_bytes()]);
+
+    // Initialize bet account
+    bet.bettor = bettor;
+    bet.market_id = market.market_id;
+    bet.bet_index = bet_index;
+    bet.outcome = bet_outcome;
+    bet.amount = amount;
+    bet.timestamp = clock.unix_timestamp;
+    bet.is_claimed = false;
+    bet.claim_pending = false;
+    bet.campaign_id = campaign_id;
+    bet.payout_amount = None;
+    bet.shares = 0;
+    bet.fee_bps = Some(market.fee_bps_at(clock.unix_timestamp));
+    bet.version = BET_VERSION;
+    position.record_stake(market, bettor, bet_outcome, amount, true)?;
+
+    // Attribute volume to the campaign the bet is tagged with
+    if let Some(campaign) = tagged_campaign(campaign_id, campaign)? {
+        campaign.record_volume(amount, true)?;
+    }
+
+    if market.logs(LogLevel::Full) {
+        emit!(BetPlaced {
+            market_id: market.market_id,
+            bettor,
+            bet_index,
+            outcome: bet_outcome,
+            amount,
+            campaign_id,
+            outcome_pools: market.outcome_pools.clone(),
+            implied_probabilities_bps: (0..market.outcome_count)
+                .map(|outcome| market.implied_probability_at(outcome, 10_000) as u16)
+                .collect(),
+        });
+    }
+
+    Ok(())
+}
+
+/// e^-x at `LMSR_SCALE`: 2^-k e^-r with x = k ln 2 + r, the remainder by
+/// Taylor series.
+fn lmsr_exp_neg(x: u128) -> u128 {
+    let halvings = x / LMSR_LN_2;
+    if halvings >= 64 {
+        return 0;
+    }
+    let r = x % LMSR_LN_2;
+
+    let (mut positive, mut negative, mut term) = (LMSR_SCALE, 0, LMSR_SCALE);
+    for n in 1..=24 {
+        term = term * r / LMSR_SCALE / n;
+        if term == 0 {
+            break;
+        }
+        if n % 2 == 1 {
+            negative += term;
+        } else {
+            positive += term;
+        }
+    }
+    (positive - negative) >> halvings
+}
+
+/// ln(1 + y) at `LMSR_SCALE` for y in [0, 1], as 2 atanh(y / (2 + y)).
+fn lmsr_ln_1p(y: u128) -> u128 {
+    let t = y * LMSR_SCALE / (2 * LMSR_SCALE + y);
+    let t_squared = t * t / LMSR_SCALE;
+
+    let (mut sum, mut power, mut n) = (0, t, 1);
+    while power > 0 {
+        sum += power / n;
+        power = power * t_squared / LMSR_SCALE;
+        n += 2;
+    }
+    2 * sum
+}
+
+/// The LMSR cost function b ln(e^(q0/b) + e^(q1/b)) of a binary market, in
+/// token units at `LMSR_SCALE`. Evaluated as max(q) + b ln(1 + e^-(|q0 - q1|/b))
+/// so nothing overflows.
+fn lmsr_cost(liquidity: u64, shares: &[u64]) -> Result<u128> {
+    let [first, second] = shares else {
+        return err!(ErrorCode::InvalidLmsrConfig);
+    };
+    let (high, low) = ((*first).max(*second) as u128, (*first).min(*second) as u128);
+    let spread = (high - low) * LMSR_SCALE / liquidity as u128;
+
+    Ok(high * LMSR_SCALE + liquidity as u128 * lmsr_ln_1p(lmsr_exp_neg(spread)))
+}
+
+/// The LMSR price of `outcome` in a binary market at `LMSR_SCALE`:
+/// 1 / (1 + e^-((q_outcome - q_other)/b)).
+fn lmsr_price(liquidity: u64, shares: &[u64], outcome: u8) -> u128 {
+    let own = shares.get(outcome as usize).copied().unwrap_or(0) as u128;
+    let other = shares.get(1 - outcome.min(1) as usize).copied().unwrap_or(0) as u128;
+    let spread = own.abs_diff(other) * LMSR_SCALE / liquidity as u128;
+    let weight = lmsr_exp_neg(spread);
+
+    let favoured = own >= other;
+    let numerator = if favoured { LMSR_SCALE } else { weight };
+    numerator * LMSR_SCALE / (LMSR_SCALE + weight)
+}
+
+/// The creator deposit covering an LMSR market's worst-case loss, b ln 2,
+/// rounded up with a unit to spare for rounding in later trades.
+fn lmsr_subsidy(liquidity: u64) -> Result<u64> {
+    let cost = lmsr_cost(liquidity, &[0, 0])?.div_ceil(LMSR_SCALE) + 1;
+    u64::try_from(cost).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Hash a CommitReveal authority commits to; binding the market id stops a
+/// commitment from being replayed on another market.
+pub fn outcome_commitment(market_id: u64, outcome: u8, salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&market_id.to_le_bytes(), &[outcome], salt]).to_bytes()
+}
+
+/// Seed that breaks a tie for the lead in `resolve_by_majority`:
+/// `hash("tiebreak" || market || slot)`, with the slot little-endian.
+pub fn tiebreak_seed(market: &Pubkey, slot: u64) -> [u8; 32] {
+    hashv(&[b"tiebreak", market.as_ref(), &slot.to_le_bytes()]).to_bytes()
+}
+
+/// Index among the tied outcomes picked by a tiebreak seed: its first eight
+/// bytes, little-endian, modulo the number tied.
+pub fn tiebreak_draw(seed: &[u8; 32], tied: usize) -> usize {
+    let mut draw = [0u8; 8];
+    draw.copy_from_slice(&seed[..8]);
+    (u64::from_le_bytes(draw) % tied as u64) as usize
+}
+
+/// Leaf of a whitelisted market's Merkle tree for `bettor`; the domain prefix
+/// keeps leaves from being passed off as inner nodes.
+pub fn whitelist_leaf(bettor: &Pubkey) -> [u8; 32] {
+    hashv(&[b"whitelist", bettor.as_ref()]).to_bytes()
+}
+
+/// Transfers `amount` out of a market vault, signing as the vault PDA.
+fn transfer_from_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    market_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[
+        b"market_vault",
+        market_key.as_ref(),
+        &[vault_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: market_vault.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)
+}
+
+/// Empties the dispute bond vault, whose authority is the market vault, into
+/// `to` and closes it to the disputer who paid its rent. Returns the bond.
+#[allow(clippy::too_many_arguments)]
+fn pay_out_dispute_bond<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    dispute_bond_vault: &InterfaceAccount<'info, TokenAccount>,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    disputer: AccountInfo<'info>,
+    market_key: Pubkey,
+    vault_bump: u8,
+) -> Result<u64> {
+    let seeds = &[
+        b"market_vault",
+        market_key.as_ref(),
+        &[vault_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let bond = dispute_bond_vault.amount;
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: dispute_bond_vault.to_account_info(),
+            mint: mint.to_account_info(),
+            to,
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::transfer_checked(transfer_ctx, bond, mint.decimals)?;
+
+    let close_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: dispute_bond_vault.to_account_info(),
+            destination: disputer,
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::close_account(close_ctx)?;
+
+    Ok(bond)
+}
+
+/// Moves `amount` into a market vault from an account `authority` can spend
+/// and returns what the vault actually received, which is less than `amount`
+/// for mints that charge a transfer fee.
+fn deposit_to_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    market_vault: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let transfer_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: market_vault.to_account_info(),
+            authority,
+        },
+    );
+    let vault_before = market_vault.amount;
+    token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)?;
+
+    market_vault.reload()?;
+    market_vault
+        .amount
+        .checked_sub(vault_before)
+        .ok_or(error!(ErrorCode::MathUnderflow))
+}
+
+/// Limits a payout to the vault balance so rounding across claimants cannot
+/// strand the last one; an empty vault is an error.
+fn cap_to_vault(payout: u64, market_vault: &InterfaceAccount<TokenAccount>) -> Result<u64> {
+    require!(
+        payout == 0 || market_vault.amount > 0,
+        ErrorCode::InsufficientVaultBalance
+    );
+    Ok(payout.min(market_vault.amount))
+}
+
+/// Sends the market's fees and charity cut on `bet`'s `payout` from its
+/// vault to the treasury vault, the creator fee vault and the charity,
+/// returning what is left for the recipient. The protocol fee is the one
+/// the bet recorded when placed.
+#[allow(clippy::too_many_arguments)]
+fn collect_fee<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    market: &mut Market,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    treasury: Option<&mut Account<'info, Treasury>>,
+    treasury_vault: Option<&InterfaceAccount<'info, TokenAccount>>,
+    creator_fee_vault: Option<&InterfaceAccount<'info, TokenAccount>>,
+    charity_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    mint: &InterfaceAccount<'info, Mint>,
+    market_key: Pubkey,
+    bet: &Bet,
+    payout: u64,
+) -> Result<u64> {
+    let creator_fee = market.creator_fee_for(payout);
+    let fee = creator_fee + market.protocol_fee_for(payout, bet);
+    let charity_cut = market.charity_cut_for(payout);
+    if charity_cut > 0 {
+        let Some(charity_token_account) = charity_token_account else {
+            return err!(ErrorCode::CharityAccountMissing);
+        };
+        transfer_from_vault(
+            token_program,
+            market_vault,
+            charity_token_account,
+            mint,
+            market_key,
+            market.vault_bump,
+            charity_cut,
+        )?;
+
+        emit!(CharityDonation {
+            market_id: market.market_id,
+            charity: charity_token_account.owner,
+            amount: charity_cut,
+        });
+    }
+    if fee == 0 {
+        return Ok(payout - charity_cut);
+    }
+    let protocol_fee = fee - creator_fee;
+
+    if creator_fee > 0 {
+        let Some(creator_fee_vault) = creator_fee_vault else {
+            return err!(ErrorCode::CreatorFeeVaultMissing);
+        };
+        transfer_from_vault(
+            token_program,
+            market_vault,
+            creator_fee_vault,
+            mint,
+            market_key,
+            market.vault_bump,
+            creator_fee,
+        )?;
+        market.creator_fees_accrued = market
+            .creator_fees_accrued
+            .checked_add(creator_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    if protocol_fee > 0 {
+        let (Some(treasury), Some(treasury_vault)) = (treasury, treasury_vault) else {
+            return err!(ErrorCode::FeeVaultMissing);
+        };
+        transfer_from_vault(
+            token_program,
+            market_vault,
+            treasury_vault,
+            mint,
+            market_key,
+            market.vault_bump,
+            protocol_fee,
+        )?;
+        treasury.record_collected(protocol_fee)?;
+
+        if market.logs(LogLevel::Minimal) {
+            emit!(FeeCollected {
+                market_id: market.market_id,
+                amount: protocol_fee,
+                treasury_total: treasury.total_collected,
+            });
+        }
+    }
+    market.total_fees = market.total_fees.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(payout - fee - charity_cut)
+}
+
+/// Grows a program account to `len` bytes for a newer layout, topping up its
+/// rent from `payer` and zero-filling the new tail. Never shrinks. Returns
+/// the lamports `payer` put in.
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    len: usize,
+) -> Result<u64> {
+    let old_len = account.data_len();
+    if len <= old_len {
+        return Ok(0);
+    }
+
+    let shortfall = Rent::get()?.minimum_balance(len).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.resize(len)?;
+    account.try_borrow_mut_data()?[old_len..].fill(0);
+    Ok(shortfall)
+}
+
+/// Shrinks a program-owned account to `len` bytes and returns the rent it no
+/// longer needs, which the caller moves out. Does nothing unless it gets
+/// smaller.
+fn shrink_account(account: &AccountInfo, len: usize) -> Result<u64> {
+    if len >= account.data_len() {
+        return Ok(0);
+    }
+
+    account.resize(len)?;
+    Ok(account.lamports().saturating_sub(Rent::get()?.minimum_balance(len)))
+}
+
+/// Hands the authority back the rent it paid to grow a market's description
+/// before the market account is closed to its sponsor.
+fn refund_authority_rent(market: &Account<Market>, authority: &AccountInfo) -> Result<()> {
+    if market.authority_rent > 0 {
+        market.to_account_info().sub_lamports(market.authority_rent)?;
+        authority.add_lamports(market.authority_rent)?;
+    }
+    Ok(())
+}
+
+/// Lamports in a NativeSol market's escrow above its rent-exempt minimum.
+fn sol_vault_balance(sol_vault: &Account<SolVault>) -> Result<u64> {
+    let info = sol_vault.to_account_info();
+    let rent = Rent::get()?.minimum_balance(info.data_len());
+    Ok(info.lamports().saturating_sub(rent))
+}
+
+/// Closes an emptied market vault, signing as the vault PDA.
+fn close_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    destination: &UncheckedAccount<'info>,
+    market_key: Pubkey,
+    vault_bump: u8,
+) -> Result<()> {
+    let seeds = &[
+        b"market_vault",
+        market_key.as_ref(),
+        &[vault_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let close_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: market_vault.to_account_info(),
+            destination: destination.to_account_info(),
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::close_account(close_ctx)
+}
+
+/// Pays `amount` out of a P2P escrow, which the market vault signs for.
+#[allow(clippy::too_many_arguments)]
+fn release_p2p_escrow<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    market_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[
+        b"market_vault",
+        market_key.as_ref(),
+        &[vault_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: escrow.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)
+}
+
+/// Closes an emptied P2P escrow, returning its rent to the maker.
+fn close_p2p_escrow<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    market_vault: &InterfaceAccount<'info, TokenAccount>,
+    market_key: Pubkey,
+    vault_bump: u8,
+) -> Result<()> {
+    let seeds = &[
+        b"market_vault",
+        market_key.as_ref(),
+        &[vault_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let close_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow.to_account_info(),
+            destination,
+            authority: market_vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::close_account(close_ctx)
+}
 
 // Account structures
 #[derive(Accounts)]
 #[instruction(market_id: u64)]
-pub struct CreateMarket<'info> {
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    /// Counts the authority's open markets against the configured cap.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AuthorityStats::INIT_SPACE,
+        seeds = [b"authority_stats", authority.key().as_ref()],
+        bump
+    )]
+    pub authority_stats: Account<'info, AuthorityStats>,
+    
+    /// Source of the seed liquidity; required when `seed_amount` is non-zero.
+    #[account(mut)]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Holds the resolver bond until `release_bond`; required when
+    /// `resolver_bond` is non-zero. The market vault is its authority.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub bond_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Collects the creator fee until `withdraw_creator_fees`; required when
+    /// `creator_fee_bps` is non-zero. The market vault is its authority.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"creator_fee_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Lamport escrow; required for NativeSol markets.
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SolVault::INIT_SPACE,
+        seeds = [b"sol_vault", market.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Option<Account<'info, SolVault>>,
+    
+    /// Funds the market and vault rent and gets it back on close_market. Pass
+    /// the authority again when creation is not sponsored.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMarketAuto<'info> {
+    #[account(mut, seeds = [b"market_registry"], bump)]
+    pub registry: Account<'info, MarketRegistry>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", registry.next_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    /// Counts the authority's open markets against the configured cap.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + AuthorityStats::INIT_SPACE,
+        seeds = [b"authority_stats", authority.key().as_ref()],
+        bump
+    )]
+    pub authority_stats: Account<'info, AuthorityStats>,
+    
+    /// Source of the seed liquidity; required when `seed_amount` is non-zero.
+    #[account(mut)]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Holds the resolver bond until `release_bond`; required when
+    /// `resolver_bond` is non-zero. The market vault is its authority.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub bond_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Collects the creator fee until `withdraw_creator_fees`; required when
+    /// `creator_fee_bps` is non-zero. The market vault is its authority.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"creator_fee_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Lamport escrow; required for NativeSol markets.
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + SolVault::INIT_SPACE,
+        seeds = [b"sol_vault", market.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Option<Account<'info, SolVault>>,
+    
+    /// Funds the market and vault rent and gets it back on close_market. Pass
+    /// the authority again when creation is not sponsored.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MarketRegistry::INIT_SPACE,
+        seeds = [b"market_registry"],
+        bump
+    )]
+    pub registry: Account<'info, MarketRegistry>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// The upgrade authority recorded in the program's data account, so
+    /// only whoever deployed the program can claim this singleton.
+    pub upgrade_authority: Signer<'info>,
+    
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PredictionMarket>,
+    
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct PlaceBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; bets are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    #[account(mut)]
+    pub campaign: Option<Account<'info, Campaign>>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct PlaceBetNative<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault", market.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+    
+    /// Program-wide settings; bets are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct IncreaseBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; bets are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    /// The counter of the campaign the bet is tagged with; required when it
+    /// is tagged.
+    #[account(mut)]
+    pub campaign: Option<Account<'info, Campaign>>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct PlaceBetDelegated<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; bets are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    /// Co-signs the relayer's transaction, binding the outcome, amount and
+    /// bet index to the bettor; their token account must delegate to the
+    /// vault.
+    pub bettor: Signer<'info>,
+    
+    /// Pays the transaction fee and the bet account rent.
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    #[account(mut)]
+    pub campaign: Option<Account<'info, Campaign>>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct CancelBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    /// The counter of the campaign the bet is tagged with; required when it
+    /// is tagged.
+    #[account(mut)]
+    pub campaign: Option<Account<'info, Campaign>>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ReduceBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    /// The counter of the campaign the bet is tagged with; required when it
+    /// is tagged.
+    #[account(mut)]
+    pub campaign: Option<Account<'info, Campaign>>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(first_market_id: u64, second_market_id: u64, first: HedgeLeg, second: HedgeLeg)]
+pub struct HedgeBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", first_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub first_market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", second_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub second_market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [
+            b"bet",
+            first_market.key().as_ref(),
+            bettor.key().as_ref(),
+            first.bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub first_bet: Account<'info, Bet>,
+    
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [
+            b"bet",
+            second_market.key().as_ref(),
+            bettor.key().as_ref(),
+            second.bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub second_bet: Account<'info, Bet>,
+    
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Hedge::INIT_SPACE,
+        seeds = [b"hedge", first_bet.key().as_ref(), second_bet.key().as_ref()],
+        bump
+    )]
+    pub hedge: Account<'info, Hedge>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", first_market.key().as_ref()],
+        bump = first_market.vault_bump
+    )]
+    pub first_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", second_market.key().as_ref()],
+        bump = second_market.vault_bump
+    )]
+    pub second_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = first_market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; bets are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    /// Funds both legs, so the two markets must share a mint.
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", first_market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub first_position: Account<'info, UserPosition>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", second_market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub second_position: Account<'info, UserPosition>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u64)]
+pub struct CreateCampaign<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Campaign::INIT_SPACE,
+        seeds = [b"campaign", campaign_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// The upgrade authority recorded in the program's data account, so
+    /// only whoever deployed the program can claim this singleton.
+    pub upgrade_authority: Signer<'info>,
+    
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PredictionMarket>,
+    
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxOpenMarkets<'info> {
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct MigrateMarket<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: a Market of any layout version, which `Account` could not
+    /// load; its discriminator is checked when it is read back.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bettor: Pubkey, bet_index: u64)]
+pub struct MigrateBet<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: only its address seeds the bet; it may itself await migration.
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market: UncheckedAccount<'info>,
+    
+    /// CHECK: a Bet of any layout version; its discriminator is checked when
+    /// it is read back.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// The upgrade authority recorded in the program's data account, so
+    /// only whoever deployed the program can claim this singleton.
+    pub upgrade_authority: Signer<'info>,
+    
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PredictionMarket>,
+    
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTreasuryVault<'info> {
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"treasury_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury,
+        token::token_program = token_program,
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeSplits<'info> {
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeTreasury<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct RequestEmergencyWithdraw<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + EmergencyWithdrawal::INIT_SPACE,
+        seeds = [b"emergency_withdrawal", market.key().as_ref()],
+        bump
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"emergency_withdrawal", market.key().as_ref()],
+        bump
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut, address = emergency_withdrawal.destination)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelEmergencyWithdraw<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"emergency_withdrawal", market.key().as_ref()],
+        bump
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct EscapeToMint<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The elapsed emergency request; the escape consumes it in place of
+    /// the drain.
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"emergency_withdrawal", market.key().as_ref()],
+        bump
+    )]
+    pub emergency_withdrawal: Account<'info, EmergencyWithdrawal>,
+    
+    #[account(constraint = new_mint.key() != market.mint @ ErrorCode::EscapeNotAllowed)]
+    pub new_mint: InterfaceAccount<'info, Mint>,
+    
+    /// Pays out escaped claims in the new mint. The market vault is its
+    /// authority.
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"escape_vault", market.key().as_ref()],
+        bump,
+        token::mint = new_mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub escape_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Source of the converted funding, in the new mint.
+    #[account(mut)]
+    pub admin_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimEscaped<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"escape_vault", market.key().as_ref()],
+        bump
+    )]
+    pub escape_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.escape_mint.unwrap_or_default())]
+    pub escape_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CommitOutcome<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct RevealOutcome<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ExtendMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct UpdateMetadataUri<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct UpdateMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SubmitResolutionVote<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + ResolutionVotes::INIT_SPACE,
+        seeds = [b"resolution_votes", market.key().as_ref()],
+        bump
+    )]
+    pub resolution_votes: Account<'info, ResolutionVotes>,
+    
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct DisputeResolution<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// Any of the disputer's bets on the market, proving they have a stake.
+    #[account(
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            disputer.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(mut)]
+    pub disputer_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Holds the dispute bond until `adjudicate_dispute`. The market vault
+    /// is its authority.
+    #[account(
+        init,
+        payer = disputer,
+        seeds = [b"dispute_bond_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub dispute_bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub disputer: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct AdjudicateDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"dispute_bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub dispute_bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Receives a refunded bond when the dispute succeeds.
+    #[account(mut)]
+    pub disputer_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The protocol treasury and its vault for this mint, credited with a
+    /// forfeited bond.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: receives the bond vault rent it paid; pinned to the disputer.
+    #[account(mut, address = market.disputer.unwrap_or_default())]
+    pub disputer: UncheckedAccount<'info>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct VoidUnadjudicated<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"dispute_bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub dispute_bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Receives the refunded bond.
+    #[account(mut)]
+    pub disputer_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: receives the bond vault rent it paid; pinned to the disputer.
+    #[account(mut, address = market.disputer.unwrap_or_default())]
+    pub disputer: UncheckedAccount<'info>,
+    
+    pub caller: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ReleaseBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The authority's token account, or the disputer's when a dispute
+    /// overturned the resolution; unused when the bond goes to the treasury.
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// bond is forfeited to the treasury.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// CHECK: receives the bond vault rent; pinned to the recorded sponsor.
+    #[account(mut, address = market.rent_sponsor)]
+    pub rent_sponsor: UncheckedAccount<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct WithdrawCreatorFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"creator_fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub creator_fee_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut, token::authority = authority)]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SweepDust<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveFromOracle<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// CHECK: matched against `market.oracle_feed`; owner and layout are
+    /// checked by `PythPriceUpdate::read`.
+    pub oracle_feed: UncheckedAccount<'info>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveFromMultiOracle<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveByMajority<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloseBetting<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct VoidUnrevealed<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct VoidUnresolved<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimWinnings<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Receives the payout instead of `bettor_token_account` when passed,
+    /// e.g. a hardware wallet; the bettor still signs the claim.
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// market charges a fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the market charges a creator fee.
+    #[account(mut, seeds = [b"creator_fee_vault", market.key().as_ref()], bump)]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The charity's token account; required when the market donates a cut.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = market.charity.unwrap_or_default(),
+    )]
+    pub charity_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimWinningsNative<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"sol_vault", market.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimTimeoutRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    /// CHECK: only derives the bet PDA and receives its rent.
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct CloseLosingBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    /// The market this one was merged into; required once it has been merged.
+    pub dest_market: Option<Account<'info, Market>>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimToAlternate<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// market charges a fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the market charges a creator fee.
+    #[account(mut, seeds = [b"creator_fee_vault", market.key().as_ref()], bump)]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The charity's token account; required when the market donates a cut.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = market.charity.unwrap_or_default(),
+    )]
+    pub charity_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    /// CHECK: derives the bet and profile PDAs and receives the settled
+    /// bet's rent; the bet records its owner.
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ReclaimSeed<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CreateP2pOffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// Signs for the escrow; holds none of its funds.
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + P2pOffer::INIT_SPACE,
+        seeds = [b"p2p_offer", market.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, P2pOffer>,
+    
+    /// Holds both stakes of the offer, kept apart from the market vault.
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"p2p_escrow", offer.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market_vault,
+        token::token_program = token_program,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; offers are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub maker_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct AcceptP2pOffer<'info> {
+    #[account(
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"p2p_offer", market.key().as_ref(), offer.maker.as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, P2pOffer>,
+    
+    #[account(
+        mut,
+        seeds = [b"p2p_escrow", offer.key().as_ref()],
+        bump
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Program-wide settings; offers are refused while paused.
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub taker_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub taker: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelP2pOffer<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        close = maker,
+        seeds = [b"p2p_offer", market.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, P2pOffer>,
+    
+    #[account(
+        mut,
+        seeds = [b"p2p_escrow", offer.key().as_ref()],
+        bump
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut, token::authority = maker)]
+    pub maker_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SettleP2p<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        close = maker,
+        seeds = [b"p2p_offer", market.key().as_ref(), offer.maker.as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, P2pOffer>,
+    
+    #[account(
+        mut,
+        seeds = [b"p2p_escrow", offer.key().as_ref()],
+        bump
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut, token::authority = offer.maker)]
+    pub maker_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut, token::authority = offer.taker.unwrap_or_default())]
+    pub taker_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: receives the offer and escrow rent; pinned to the offer's maker.
+    #[account(mut, address = offer.maker)]
+    pub maker: UncheckedAccount<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ApproveLargeClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// market charges a fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the market charges a creator fee.
+    #[account(mut, seeds = [b"creator_fee_vault", market.key().as_ref()], bump)]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The charity's token account; required when the market donates a cut.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = market.charity.unwrap_or_default(),
+    )]
+    pub charity_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The bettor's profile, created by their held claim.
+    #[account(
+        mut,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    /// CHECK: derives the bet PDA and receives the settled bet's rent; the
+    /// bet records its owner.
+    #[account(mut)]
+    pub bettor: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bet_index: u64)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Vesting::INIT_SPACE,
+        seeds = [b"vesting", bet.key().as_ref()],
+        bump
+    )]
+    pub vesting: Account<'info, Vesting>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// market charges a fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the market charges a creator fee.
+    #[account(mut, seeds = [b"creator_fee_vault", market.key().as_ref()], bump)]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// The charity's token account; required when the market donates a cut.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = market.charity.unwrap_or_default(),
+    )]
+    pub charity_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_market_id: u64, dest_market_id: u64)]
+pub struct MergeMarkets<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", source_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", source_market.key().as_ref()],
+        bump = source_market.vault_bump
+    )]
+    pub source_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", dest_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub dest_market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", dest_market.key().as_ref()],
+        bump = dest_market.vault_bump
+    )]
+    pub dest_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = dest_market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// Frees the source authority's open-market slot. Markets from before the
+    /// count existed have no stats account to pass.
+    #[account(mut, seeds = [b"authority_stats", source_market.authority.as_ref()], bump)]
+    pub source_authority_stats: Option<Account<'info, AuthorityStats>>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_market_id: u64, dest_market_id: u64, bet_index: u64)]
+pub struct ClaimMergedWinnings<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", source_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub source_market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"bet",
+            source_market.key().as_ref(),
+            bettor.key().as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    #[account(
+        mut,
+        seeds = [b"market", dest_market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub dest_market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", dest_market.key().as_ref()],
+        bump = dest_market.vault_bump
+    )]
+    pub dest_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = dest_market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The protocol treasury and its vault for this mint; required when the
+    /// market charges a fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    
+    #[account(mut, seeds = [b"treasury_vault", dest_market.mint.as_ref()], bump)]
+    pub treasury_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the destination market charges a creator fee.
+    #[account(mut, seeds = [b"creator_fee_vault", dest_market.key().as_ref()], bump)]
+    pub creator_fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Required when the destination market donates a cut to charity.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = dest_market.charity.unwrap_or_default(),
+    )]
+    pub charity_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", bettor.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", source_market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ViewMarket<'info> {
+    #[account(
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct DryRunBet<'info> {
+    #[account(
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct UpdateMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// CHECK: receives rent freed by a shorter description beyond what the
+    /// authority paid; pinned to the recorded sponsor.
+    #[account(mut, address = market.rent_sponsor)]
+    pub rent_sponsor: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloseMarket<'info> {
+    #[account(
+        mut,
+        close = rent_sponsor,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        close = rent_sponsor,
+        seeds = [b"sol_vault", market.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Option<Account<'info, SolVault>>,
+    
+    /// CHECK: receives the reclaimed rent; pinned to the recorded sponsor.
+    #[account(mut, address = market.rent_sponsor)]
+    pub rent_sponsor: UncheckedAccount<'info>,
+    
+    /// Refunded the rent it paid to grow the description.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// Frees a slot in the authority's open-market count. Markets from before
+    /// the count existed have no stats account to pass.
+    #[account(mut, seeds = [b"authority_stats", market.authority.as_ref()], bump)]
+    pub authority_stats: Option<Account<'info, AuthorityStats>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ArchiveMarket<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + Market::INIT_SPACE,
+        mut,
+        close = rent_sponsor,
         seeds = [b"market", market_id.to_le_bytes().as_ref()],
         bump
     )]
     pub market: Account<'info, Market>,
     
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
     #[account(
         init,
         payer = authority,
-        seeds = [b"market_vault", market.key().as_ref()],
-        bump,
-        token::mint = mint,
-        token::authority = market_vault,
+        space = 8 + Archive::INIT_SPACE,
+        seeds = [b"archive", market_id.to_le_bytes().as_ref()],
+        bump
     )]
-    pub market_vault: Account<'info, TokenAccount>,
+    pub archive: Account<'info, Archive>,
     
-    pub mint: Account<'info, anchor_spl::token::Mint>,
+    /// CHECK: receives the reclaimed rent; pinned to the recorded sponsor.
+    #[account(mut, address = market.rent_sponsor)]
+    pub rent_sponsor: UncheckedAccount<'info>,
     
     #[account(mut)]
     pub authority: Signer<'info>,
     
+    /// Frees a slot in the authority's open-market count. Markets from before
+    /// the count existed have no stats account to pass.
+    #[account(mut, seeds = [b"authority_stats", market.authority.as_ref()], bump)]
+    pub authority_stats: Option<Account<'info, AuthorityStats>>,
+    
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct RefundAll<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump
+    )]
+    pub market_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = market.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct PrecomputePayouts<'info> {
+    #[account(
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloseBetsBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// The market this one was merged into; required once it has been merged.
+    pub dest_market: Option<Account<'info, Market>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, bettor: Pubkey, bet_index: u64)]
+pub struct ViewBet<'info> {
+    #[account(
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        seeds = [
+            b"bet",
+            market.key().as_ref(),
+            bettor.as_ref(),
+            bet_index.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+}
+
+// Data structures
+/// Optional market settings supplied to `create_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketOptions {
+    /// Decimal places used by the odds helpers (4 = basis points).
+    pub odds_decimals: u8,
+    /// Seconds over which winnings vest after resolution; 0 pays out at once.
+    pub vesting_period: i64,
+    /// Number of outcomes, 2..=MAX_OUTCOMES.
+    pub outcome_count: u8,
+    /// Maximum pool per outcome, one entry per outcome or empty for none; 0
+    /// leaves an outcome uncapped.
+    pub outcome_caps: Vec<u64>,
+    /// Payouts above this wait for `approve_large_claim`; 0 disables the hold.
+    /// Vesting markets release gradually instead and are not held.
+    pub high_value_threshold: u64,
+    /// Raises the minimum bet to this fraction of the current pool; 0 keeps
+    /// the flat `min_bet_amount`.
+    pub min_bet_pool_bps: u16,
+    /// How the market will be resolved; only the matching instruction can.
+    pub resolution_source: ResolutionSource,
+    /// For Community markets, the lead the majority needs over the pool in
+    /// `resolve_by_majority`; a narrower lead voids the market.
+    pub majority_margin_bps: u16,
+    /// Caps each payout at this multiple of the stake (10000 = 1x); 0 disables.
+    pub max_payout_multiplier_bps: u32,
+    /// Minimum seconds between two claims by the same wallet; 0 disables.
+    pub claim_cooldown: i64,
+    pub log_level: LogLevel,
+    /// Stake each side needs (beyond being non-zero) for the market to
+    /// resolve; otherwise resolution voids it.
+    pub min_side_pool: u64,
+    /// Creator liquidity moved into the pools at creation, split between them
+    /// by `prior_yes_bps`; 0 starts them empty. Only binary markets can be
+    /// seeded.
+    pub seed_amount: u64,
+    /// Prior probability of YES used to split `seed_amount` between the
+    /// pools; None means even odds.
+    pub prior_yes_bps: Option<u16>,
+    /// For CommitReveal markets, seconds after end_time to reveal the outcome
+    /// before the market can be voided.
+    pub reveal_window: i64,
+    /// Last moment the market can be resolved; after it the market can only
+    /// be voided. Must be after end_time.
+    pub resolution_deadline: i64,
+    /// Cut of each winning payout sent to the protocol treasury; refunds are
+    /// free. With `closing_fee_bps`, the rate for bets at creation.
+    pub fee_bps: u16,
+    /// Largest single bet accepted; None leaves bets unlimited.
+    pub max_bet_amount: Option<u64>,
+    /// Seconds after resolution during which any bettor can dispute it and
+    /// claims are held back; 0 makes resolution final immediately.
+    pub challenge_period: i64,
+    /// Escrowed by a disputer; forfeited to the treasury if the resolution
+    /// is upheld. The least bond when `dispute_bond_bps` is set.
+    pub dispute_bond: u64,
+    /// For Oracle markets, the Pyth price feed that settles the market.
+    /// Oracle markets must be binary.
+    pub oracle_feed: Option<Pubkey>,
+    /// YES wins if the feed's price is at least this, in the feed's own
+    /// exponent; otherwise NO.
+    pub resolution_threshold: i64,
+    /// Oldest feed price, in seconds, `resolve_market_from_oracle` accepts.
+    pub max_oracle_staleness: i64,
+    /// Off-chain metadata for front-ends (image, source link, category).
+    pub metadata_uri: String,
+    /// Stake the authority locks at creation as resolver. It is returned by
+    /// `release_bond` once an undisputed resolution's challenge window
+    /// passes; it is forfeited to the disputer if a dispute overturns the
+    /// resolution, and to the treasury if the market is cancelled or nobody
+    /// resolves it by the resolution deadline.
+    pub resolver_bond: u64,
+    /// Total stake the market needs to resolve to a payout; below it,
+    /// resolution voids the market and refunds everyone.
+    pub min_pool_to_settle: u64,
+    /// Pari-mutuel pools, or an LMSR market maker subsidised by the creator.
+    pub market_type: MarketType,
+    /// Keys allowed to resolve a ManualAuthority market, up to MAX_RESOLVERS.
+    /// Empty means only the authority may resolve.
+    pub resolvers: Vec<Pubkey>,
+    /// Matching `submit_resolution_vote` votes needed to resolve; 0 lets a
+    /// single resolver call `resolve_market`. At most `resolvers.len()`.
+    pub resolver_threshold: u8,
+    /// Cut of each winning payout kept for the market authority in the
+    /// creator fee vault, on top of `fee_bps`. The two together are held to
+    /// MAX_FEE_BPS.
+    pub creator_fee_bps: u16,
+    /// Seconds before end_time at which betting stops, so late bets cannot
+    /// front-run the event; must be shorter than the market's lifetime.
+    pub betting_cutoff_offset: i64,
+    /// Detailed rules for how the market resolves, up to
+    /// MAX_RESOLUTION_CRITERIA_LEN bytes.
+    pub resolution_criteria: String,
+    /// NativeSol markets take lamports through `place_bet_native` and pay
+    /// out through `claim_winnings_native`; they must be plain pari-mutuel
+    /// markets with no fees, seed, bond, vesting or claim approval.
+    pub currency: Currency,
+    /// Bars the authority and resolver set from betting, so whoever decides
+    /// the outcome cannot profit from it.
+    pub authority_cannot_bet: bool,
+    /// Merkle root of the `whitelist_leaf`s allowed to bet; None keeps the
+    /// market public. Whitelisted markets only take bets through `place_bet`.
+    pub whitelist_root: Option<[u8; 32]>,
+    /// Extra payout weight for early bets, in basis points, tapering to zero
+    /// at end_time; see `Market::early_bird_weight`. Pari-mutuel only.
+    pub early_bird_bps: u16,
+    /// Most bets the market holds at once, keeping batch claims and refunds
+    /// within compute limits; None leaves it unlimited.
+    pub max_bettors: Option<u64>,
+    /// Token account owner that receives `charity_bps` of every winning
+    /// payout; both are set or neither.
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+    /// For Oracle markets, the Pyth price feed id the `oracle_feed` account
+    /// must carry. An account can be re-posted for another feed, so the id
+    /// is checked as well as the address.
+    pub oracle_feed_id: [u8; 32],
+    /// Waives the protocol fee on the authority's own winning bets. Opt-in,
+    /// since it rewards the party who also settles the market.
+    pub creator_fee_exempt: bool,
+    /// Protocol fee at end_time; bets pay a rate moving linearly from
+    /// `fee_bps` at creation to this, recorded when placed. Below `fee_bps`
+    /// it rewards late liquidity, above it early. None keeps `fee_bps` flat.
+    pub closing_fee_bps: Option<u16>,
+    /// For Oracle markets resolved by `resolve_from_multi_oracle`, the feeds
+    /// whose median settles the market, up to MAX_ORACLE_FEEDS.
+    pub oracle_feeds: Vec<OracleFeed>,
+    /// Fresh feeds needed to resolve from `oracle_feeds`.
+    pub min_oracle_feeds: u8,
+    /// Dispute bond as a share of the total pool, so larger markets cost
+    /// more to dispute; the bond is the larger of this and `dispute_bond`.
+    pub dispute_bond_bps: u16,
+    /// Creator seed placed on YES and NO as given, on top of `seed_amount`.
+    /// The seed is reclaimable only if nobody else backed a losing side.
+    pub initial_yes: u64,
+    pub initial_no: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub authority: Pubkey,
+    pub market_id: u64,
+    #[max_len(280)]
+    pub description: String,
+    pub end_time: i64,
+    pub min_bet_amount: u64,
+    pub outcome_count: u8,
+    /// Stake per outcome; for binary markets 0 is YES and 1 is NO.
+    #[max_len(8)]
+    pub outcome_pools: Vec<u64>,
+    pub status: MarketStatus,
+    pub winning_outcome: Option<u8>,
+    pub created_at: i64,
+    pub odds_decimals: u8,
+    pub vesting_period: i64,
+    pub resolved_at: i64,
+    pub mint: Pubkey,
+    pub merged_into: Option<u64>,
+    #[max_len(8)]
+    pub outcome_caps: Vec<u64>,
+    pub high_value_threshold: u64,
+    pub min_bet_pool_bps: u16,
+    pub majority_margin_bps: u16,
+    pub resolution_source: ResolutionSource,
+    pub rent_sponsor: Pubkey,
+    pub max_payout_multiplier_bps: u32,
+    /// Bets placed per outcome.
+    #[max_len(8)]
+    pub outcome_bet_counts: Vec<u64>,
+    /// Bets paid out (or locked into vesting) and the amount committed to them.
+    pub settled_bets: u64,
+    pub total_paid: u64,
+    pub claim_cooldown: i64,
+    pub log_level: LogLevel,
+    pub min_side_pool: u64,
+    /// Set when a resolved market was voided; every bet is refunded its stake.
+    pub refund_only: bool,
+    /// Creator seed liquidity per outcome.
+    #[max_len(8)]
+    pub seed_pools: Vec<u64>,
+    pub seed_claimed: bool,
+    pub reveal_window: i64,
+    pub outcome_commitment: Option<[u8; 32]>,
+    pub resolution_deadline: i64,
+    /// Hash chain over every state transition; see `chain_audit`.
+    pub audit_hash: [u8; 32],
+    pub fee_bps: u16,
+    pub total_fees: u64,
+    pub max_bet_amount: Option<u64>,
+    pub challenge_period: i64,
+    pub dispute_bond: u64,
+    /// End of the resolution's challenge window; claims wait until then.
+    pub disputed_until: i64,
+    pub oracle_feed: Option<Pubkey>,
+    pub resolution_threshold: i64,
+    pub max_oracle_staleness: i64,
+    #[max_len(200)]
+    pub metadata_uri: String,
+    pub min_pool_to_settle: u64,
+    /// Resolver bond still held in the bond vault; 0 once released.
+    pub resolver_bond: u64,
+    /// The bettor with a pending dispute, or whose dispute cancelled the
+    /// market; cleared when a dispute is rejected.
+    pub disputer: Option<Pubkey>,
+    pub market_type: MarketType,
+    /// LMSR shares sold per outcome; each winning share pays one token unit.
+    /// Unused by pari-mutuel markets, whose `outcome_pools` set the payout.
+    #[max_len(8)]
+    pub outcome_shares: Vec<u64>,
+    /// What the creator deposited to cover the LMSR market maker's
+    /// worst-case loss; paid back with any surplus through `reclaim_seed`.
+    pub lmsr_subsidy: u64,
+    #[max_len(5)]
+    pub resolvers: Vec<Pubkey>,
+    pub resolver_threshold: u8,
+    pub creator_fee_bps: u16,
+    /// Creator fees sitting in the creator fee vault, awaiting
+    /// `withdraw_creator_fees`.
+    pub creator_fees_accrued: u64,
+    pub betting_cutoff_offset: i64,
+    #[max_len(512)]
+    pub resolution_criteria: String,
+    pub currency: Currency,
+    pub authority_cannot_bet: bool,
+    pub whitelist_root: Option<[u8; 32]>,
+    pub early_bird_bps: u16,
+    /// Sum of `Bet::weighted_amount` per outcome; winners split the pool in
+    /// proportion to these weights.
+    #[max_len(8)]
+    pub outcome_weighted_pools: Vec<u64>,
+    /// Set once `sweep_dust` has moved the vault's rounding residual to the
+    /// treasury.
+    pub dust_swept: bool,
+    /// Bets placed and not cancelled.
+    pub bettor_count: u64,
+    pub max_bettors: Option<u64>,
+    /// P2P offers not yet cancelled or settled; the market cannot be closed
+    /// or merged away while any remain.
+    pub open_p2p_offers: u64,
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+    /// Canonical bump of `[b"market_vault", market]`, recorded at creation.
+    /// Every vault constraint and signer seed uses it rather than deriving
+    /// the bump again.
+    pub vault_bump: u8,
+    /// Layout version the account was written with; see `MARKET_VERSION`.
+    pub version: u8,
+    /// Bet accounts created on this market and not yet closed. The market
+    /// PDA is seeded by its id alone, so it may only be closed once this is
+    /// 0; otherwise stale bets would validate against a recreated market.
+    pub live_bets: u64,
+    pub oracle_feed_id: [u8; 32],
+    /// A dispute awaits `adjudicate_dispute`; claims and bond releases wait.
+    pub dispute_pending: bool,
+    /// Set by `request_emergency_withdraw`: the market takes no new bets
+    /// until `emergency_withdraw` drains and cancels it, or
+    /// `cancel_emergency_withdraw` lifts the halt. Claims and cancels go on.
+    pub emergency_halted: bool,
+    pub creator_fee_exempt: bool,
+    /// Mint the market settles in after `escape_to_mint`, paid from the
+    /// `[b"escape_vault", market]` vault; None for a live mint.
+    pub escape_mint: Option<Pubkey>,
+    /// New-mint units per old-mint unit, as `escape_rate_num / escape_rate_den`.
+    pub escape_rate_num: u64,
+    pub escape_rate_den: u64,
+    pub closing_fee_bps: Option<u16>,
+    #[max_len(5)]
+    pub oracle_feeds: Vec<OracleFeed>,
+    pub min_oracle_feeds: u8,
+    pub dispute_bond_bps: u16,
+    /// Set when `resolve_by_majority` drew the winner from a tie for the
+    /// lead; `tiebreak_seed` recomputes it.
+    pub tiebreak_seed: Option<[u8; 32]>,
+    /// Rent the authority paid to grow the description in `update_metadata`;
+    /// refunded to it, not the sponsor, on shrinking or closing.
+    pub authority_rent: u64,
+    /// Set when the market was voided because its resolver missed a
+    /// deadline: nobody resolved it by `resolution_deadline`, or the
+    /// committed outcome was never revealed. Forfeits the resolver bond.
+    pub resolver_defaulted: bool,
+}
+
+/// A Pyth price update account and the feed id it must carry.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleFeed {
+    pub account: Pubkey,
+    pub feed_id: [u8; 32],
+}
+
+impl Market {
+    /// Brings a market read under an older layout up to `MARKET_VERSION`.
+    /// Appended fields read as zero, which is right for most; weighted pools
+    /// and the bet counts are rebuilt from the pools and per-outcome counts.
+    /// Which bets were already closed is unknown, so every bet is assumed
+    /// live, keeping the market open rather than risking a replay.
+    pub fn upgrade(&mut self, vault_bump: u8) {
+        if self.outcome_weighted_pools.is_empty() {
+            self.outcome_weighted_pools = self.outcome_pools.clone();
+        }
+        if self.bettor_count == 0 {
+            self.bettor_count = self.outcome_bet_counts.iter().sum();
+        }
+        if self.live_bets == 0 {
+            self.live_bets = self.bettor_count;
+        }
+        self.vault_bump = vault_bump;
+        self.version = MARKET_VERSION;
+    }
+
+    /// The authority resolves unless a resolver set was given at creation,
+    /// in which case any member of the set may.
+    pub fn require_resolver(&self, signer: Pubkey) -> Result<()> {
+        if self.resolvers.is_empty() {
+            require!(signer == self.authority, ErrorCode::UnauthorizedResolver);
+        } else {
+            require!(self.resolvers.contains(&signer), ErrorCode::NotInResolverSet);
+        }
+        Ok(())
+    }
+
+    /// Whether events at `level` are emitted by this market's bet and claim
+    /// instructions.
+    pub fn logs(&self, level: LogLevel) -> bool {
+        self.log_level >= level
+    }
+
+    /// Rejects LMSR markets in instructions built on pari-mutuel stakes.
+    pub fn require_currency(&self, currency: Currency) -> Result<()> {
+        require!(self.currency == currency, ErrorCode::CurrencyMismatch);
+        Ok(())
+    }
+
+    pub fn require_parimutuel(&self) -> Result<()> {
+        require!(
+            self.market_type == MarketType::Parimutuel,
+            ErrorCode::UnsupportedMarketType
+        );
+        Ok(())
+    }
+
+    /// What a bet of `amount` on `outcome` costs: the amount itself, or on
+    /// LMSR markets the price of `amount` shares at the current quantities.
+    pub fn stake_for(&self, outcome: u8, amount: u64) -> Result<u64> {
+        let MarketType::Lmsr { liquidity } = self.market_type else {
+            return Ok(amount);
+        };
+        let mut after = self.outcome_shares.clone();
+        let shares = after
+            .get_mut(outcome as usize)
+            .ok_or(ErrorCode::InvalidOutcome)?;
+        *shares = shares.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        let cost = lmsr_cost(liquidity, &after)?
+            .checked_sub(lmsr_cost(liquidity, &self.outcome_shares)?)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        u64::try_from(cost.div_ceil(LMSR_SCALE)).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Rejects anything but an Open market, e.g. for new bets.
+    pub fn require_open(&self) -> Result<()> {
+        match self.status {
+            MarketStatus::Open => Ok(()),
+            MarketStatus::Closed => err!(ErrorCode::BettingClosed),
+            MarketStatus::Resolved => err!(ErrorCode::MarketAlreadyResolved),
+            MarketStatus::Cancelled => err!(ErrorCode::MarketCancelled),
+        }
+    }
+
+    /// Rejects a market that has already been resolved or cancelled, e.g.
+    /// before resolving it.
+    pub fn require_unsettled(&self) -> Result<()> {
+        match self.status {
+            MarketStatus::Open | MarketStatus::Closed => Ok(()),
+            MarketStatus::Resolved => err!(ErrorCode::MarketAlreadyResolved),
+            MarketStatus::Cancelled => err!(ErrorCode::MarketCancelled),
+        }
+    }
+
+    /// Rejects a market whose bets cannot be settled yet, e.g. before claims:
+    /// unresolved, or resolved but still inside its challenge window.
+    pub fn require_settled(&self, now: i64) -> Result<()> {
+        match self.status {
+            MarketStatus::Resolved | MarketStatus::Cancelled => {}
+            MarketStatus::Open | MarketStatus::Closed => return err!(ErrorCode::MarketNotResolved),
+        }
+        self.require_payable()?;
+        require!(
+            self.refund_only || now >= self.disputed_until,
+            ErrorCode::ChallengeWindowActive
+        );
+        Ok(())
+    }
+
+    /// Rejects payouts from the vault while it is frozen: escaped to another
+    /// mint, or under dispute.
+    pub fn require_payable(&self) -> Result<()> {
+        require!(self.escape_mint.is_none(), ErrorCode::MintEscaped);
+        require!(!self.dispute_pending, ErrorCode::DisputePending);
+        Ok(())
+    }
+
+    /// Whether a settled market's resolver bond is forfeited rather than
+    /// returned: the market was cancelled, whether by its authority, an
+    /// overturned dispute or the admin, or its resolver missed a deadline.
+    pub fn resolver_bond_forfeited(&self) -> bool {
+        self.status == MarketStatus::Cancelled || self.resolver_defaulted
+    }
+
+    /// Last moment the admin may rule on a pending dispute: the end of the
+    /// challenge window plus `ADJUDICATION_PERIOD`.
+    pub fn adjudication_deadline(&self) -> i64 {
+        self.disputed_until.saturating_add(ADJUDICATION_PERIOD)
+    }
+
+    /// The bond a dispute must escrow now: `dispute_bond_bps` of the live
+    /// total pool, but never less than the flat `dispute_bond`.
+    pub fn required_dispute_bond(&self) -> Result<u64> {
+        let scaled = self.total_pool() * self.dispute_bond_bps as u128 / 10_000;
+        let scaled = u64::try_from(scaled).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        Ok(scaled.max(self.dispute_bond))
+    }
+
+    /// Whether an oracle price can settle the market: published after
+    /// end_time and no older than `max_oracle_staleness`.
+    pub fn is_fresh_price(&self, price: &PythPriceUpdate, now: i64) -> Result<bool> {
+        let age = now
+            .checked_sub(price.publish_time)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        Ok(price.publish_time >= self.end_time && age <= self.max_oracle_staleness)
+    }
+
+    /// Whether the market is resolved or cancelled, i.e. its bets settle.
+    pub fn is_settled(&self) -> bool {
+        matches!(self.status, MarketStatus::Resolved | MarketStatus::Cancelled)
+    }
+
+    /// Checks that a P2P offer can still be made or taken: the market must
+    /// be an open SPL market, not merged, before its betting cutoff.
+    /// Whitelisted markets take no P2P offers.
+    pub fn require_p2p_open(&self, market_id: u64, now: i64) -> Result<()> {
+        self.require_open()?;
+        require!(!self.emergency_halted, ErrorCode::MarketHalted);
+        self.require_currency(Currency::Spl)?;
+        require!(self.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(self.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(self.whitelist_root.is_none(), ErrorCode::NotWhitelisted);
+        require!(
+            now < self.end_time - self.betting_cutoff_offset,
+            ErrorCode::BettingClosed
+        );
+        Ok(())
+    }
+
+    /// Checks a prospective bet against the market's state and limits.
+    /// With `authority_cannot_bet`, the authority and resolver set, who
+    /// decide the outcome, may not stake on it.
+    pub fn require_bettor_allowed(&self, bettor: Pubkey) -> Result<()> {
+        require!(
+            !self.authority_cannot_bet
+                || (bettor != self.authority && !self.resolvers.contains(&bettor)),
+            ErrorCode::AuthorityCannotBet
+        );
+        Ok(())
+    }
+
+    /// Rejects new bets once `max_bettors` bets have been placed.
+    pub fn require_room_for_bettor(&self) -> Result<()> {
+        require!(
+            self.bettor_count < self.max_bettors.unwrap_or(u64::MAX),
+            ErrorCode::MarketFull
+        );
+        Ok(())
+    }
+
+    /// Public markets accept anyone. Whitelisted markets need `proof` to lead
+    /// from the bettor's `whitelist_leaf` to `whitelist_root`, hashing each
+    /// pair of nodes in sorted order.
+    pub fn require_whitelisted(&self, bettor: Pubkey, proof: &[[u8; 32]]) -> Result<()> {
+        let Some(root) = self.whitelist_root else {
+            return Ok(());
+        };
+        let node = proof.iter().fold(whitelist_leaf(&bettor), |node, sibling| {
+            if node <= *sibling {
+                hashv(&[&node, sibling]).to_bytes()
+            } else {
+                hashv(&[sibling, &node]).to_bytes()
+            }
+        });
+        require!(node == root, ErrorCode::NotWhitelisted);
+        Ok(())
+    }
+
+    pub fn validate_bet(&self, market_id: u64, outcome: u8, amount: u64, now: i64) -> Result<()> {
+        self.require_open()?;
+        require!(!self.emergency_halted, ErrorCode::MarketHalted);
+        require!(self.merged_into.is_none(), ErrorCode::MarketMerged);
+        require!(now < self.end_time, ErrorCode::MarketExpired);
+        require!(
+            now < self.end_time - self.betting_cutoff_offset,
+            ErrorCode::BettingClosed
+        );
+        require!(amount >= self.effective_min_bet(), ErrorCode::BetTooSmall);
+        require!(
+            amount <= self.max_bet_amount.unwrap_or(u64::MAX),
+            ErrorCode::BetTooLarge
+        );
+        require!(self.market_id == market_id, ErrorCode::InvalidMarketId);
+        require!(outcome < self.outcome_count, ErrorCode::InvalidOutcome);
+
+        // Enforce the exposure cap on the chosen outcome
+        let cap = self.outcome_caps[outcome as usize];
+        let outcome_pool = self
+            .outcome_pool(outcome)
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(cap == 0 || outcome_pool <= cap, ErrorCode::OutcomeCapExceeded);
+
+        Ok(())
+    }
+
+    /// Account space for a market whose description is `description_len`
+    /// bytes, never less than what creation reserved.
+    pub fn space_with_description(description_len: usize) -> usize {
+        Market::INIT_SPACE - MAX_DESCRIPTION_LEN + description_len.max(MAX_DESCRIPTION_LEN)
+    }
+
+    /// Folds a state transition into the audit hash:
+    /// `audit_hash = hash(audit_hash || transition || fields...)`, starting
+    /// from zeroes. Integers are little-endian and bools a single byte, so
+    /// the chain can be recomputed from the emitted events.
+    pub fn chain_audit(&mut self, transition: &[u8], fields: &[&[u8]]) {
+        let mut parts: Vec<&[u8]> = vec![&self.audit_hash, transition];
+        parts.extend_from_slice(fields);
+        self.audit_hash = hashv(&parts).to_bytes();
+    }
+
+    /// When an unrevealed CommitReveal market can be voided.
+    pub fn reveal_deadline(&self) -> i64 {
+        self.end_time.saturating_add(self.reveal_window)
+    }
+
+    /// Resolution is only allowed from end_time through resolution_deadline.
+    pub fn require_resolution_window(&self, now: i64) -> Result<()> {
+        require!(now >= self.end_time, ErrorCode::MarketNotExpired);
+        require!(now <= self.resolution_deadline, ErrorCode::ResolutionDeadlinePassed);
+        Ok(())
+    }
+
+    pub fn require_resolution_source(&self, source: ResolutionSource) -> Result<()> {
+        require!(self.resolution_source == source, ErrorCode::WrongResolutionSource);
+        Ok(())
+    }
+
+    /// The larger of the flat minimum and `min_bet_pool_bps` of the pool.
+    pub fn effective_min_bet(&self) -> u64 {
+        let pool_min = self.total_pool() * self.min_bet_pool_bps as u128 / 10_000;
+        // A pool past u64 puts the minimum out of reach rather than wrapping
+        self.min_bet_amount.max(u64::try_from(pool_min).unwrap_or(u64::MAX))
+    }
+
+    /// The protocol and creator fees a bet by `bettor` placed at `now` would
+    /// pay on `payout`, each rounded down as `collect_fee` charges them;
+    /// refunds carry no fee.
+    pub fn fee_for(&self, payout: u64, now: i64, bettor: Pubkey) -> u64 {
+        self.creator_fee_for(payout)
+            .saturating_add(self.protocol_fee_at(payout, bettor, self.fee_bps_at(now)))
+    }
+
+    /// The protocol fee rate a bet placed at `now` records: `fee_bps` at
+    /// creation moving linearly to `closing_fee_bps` at end_time, or
+    /// `fee_bps` throughout when no schedule is set.
+    pub fn fee_bps_at(&self, now: i64) -> u16 {
+        let Some(closing_fee_bps) = self.closing_fee_bps else {
+            return self.fee_bps;
+        };
+        let lifetime = (self.end_time - self.created_at).max(1) as i128;
+        let elapsed = (now - self.created_at).clamp(0, lifetime as i64) as i128;
+        let (start, end) = (self.fee_bps as i128, closing_fee_bps as i128);
+        (start + (end - start) * elapsed / lifetime) as u16
+    }
+
+    /// The treasury's part of the fee on `bet`'s `payout`, at the rate the
+    /// bet recorded; bets from before fee schedules pay `fee_bps`.
+    pub fn protocol_fee_for(&self, payout: u64, bet: &Bet) -> u64 {
+        self.protocol_fee_at(payout, bet.bettor, bet.fee_bps.unwrap_or(self.fee_bps))
+    }
+
+    /// The protocol fee at `fee_bps` on `bettor`'s `payout`: none on refunds
+    /// or on winnings exempt under `creator_fee_exempt`.
+    fn protocol_fee_at(&self, payout: u64, bettor: Pubkey, fee_bps: u16) -> u64 {
+        if self.refund_only || self.is_fee_exempt(bettor) {
+            return 0;
+        }
+        (payout as u128 * fee_bps as u128 / 10_000) as u64
+    }
+
+    /// Whether `bettor`'s winnings skip the protocol fee: only the
+    /// authority's own, and only on markets created with `creator_fee_exempt`.
+    pub fn is_fee_exempt(&self, bettor: Pubkey) -> bool {
+        self.creator_fee_exempt && bettor == self.authority
+    }
+
+    /// The creator's part of `fee_for(payout)`; the rest goes to the treasury.
+    pub fn creator_fee_for(&self, payout: u64) -> u64 {
+        if self.refund_only {
+            return 0;
+        }
+        (payout as u128 * self.creator_fee_bps as u128 / 10_000) as u64
+    }
+
+    /// The charity's cut of a winning payout; refunds give nothing.
+    pub fn charity_cut_for(&self, payout: u64) -> u64 {
+        if self.refund_only {
+            return 0;
+        }
+        (payout as u128 * self.charity_bps as u128 / 10_000) as u64
+    }
+
+    pub fn requires_claim_approval(&self, winnings: u64) -> bool {
+        !self.refund_only
+            && self.high_value_threshold > 0
+            && winnings > self.high_value_threshold
+    }
+
+    /// What a bet is owed once the market settles: its stake back on a voided
+    /// market, otherwise its winnings if it backed the winning outcome.
+    pub fn payout_for(&self, bet: &Bet) -> Result<u64> {
+        if let Some(payout) = bet.payout_amount {
+            return Ok(payout);
+        }
+        if self.refund_only {
+            return Ok(bet.amount);
+        }
+        let winning_outcome = self.winning_outcome.ok_or(ErrorCode::MarketNotResolved)?;
+        require!(bet.outcome == winning_outcome, ErrorCode::LosingBet);
+        self.winnings_for(bet)
+    }
+
+    /// Books the closing of one of this market's Bet accounts.
+    pub fn release_bet(&mut self) -> Result<()> {
+        self.live_bets = self.live_bets.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+        Ok(())
+    }
+
+    /// Whether a bet on this resolved market is owed nothing more and its
+    /// account can be closed. Claimed bets on vesting markets stay open until
+    /// `claim_vested` releases the last of their payout, since their Vesting
+    /// schedule is derived from the bet account.
+    pub fn is_bet_settled(&self, bet: &Bet) -> bool {
+        if !self.is_settled() || self.dispute_pending || bet.claim_pending {
+            return false;
+        }
+        if bet.is_claimed {
+            return self.vesting_period == 0 || self.refund_only;
+        }
+        !self.refund_only && self.winning_outcome != Some(bet.outcome)
+    }
+
+    /// Requires stake both on `winning_outcome` and against it, each at least
+    /// `min_side_pool`.
+    pub fn ensure_two_sided(&self, winning_outcome: u8) -> Result<()> {
+        let min_side = self.min_side_pool.max(1) as u128;
+        let winning_pool = self.outcome_pool(winning_outcome) as u128;
+        require!(
+            winning_pool >= min_side && self.total_pool() - winning_pool >= min_side,
+            ErrorCode::OneSidedMarket
+        );
+        Ok(())
+    }
+
+    /// Why resolving on `winning_outcome` would be voided instead, if it
+    /// would: no stake at all, too little stake overall, or too little on
+    /// either side.
+    pub fn unsettleable_reason(&self, winning_outcome: u8) -> Option<VoidReason> {
+        if self.total_pool() == 0 {
+            return Some(VoidReason::NoBets);
+        }
+        if self.total_pool() < self.min_pool_to_settle as u128 {
+            return Some(VoidReason::InsufficientLiquidity);
+        }
+        if let Err(err) = self.ensure_two_sided(winning_outcome) {
+            msg!("Voiding market: {}", err);
+            return Some(VoidReason::OneSided);
+        }
+        None
+    }
+
+    /// The outcomes sharing the most stake, in index order; more than one
+    /// only on a tie.
+    pub fn leading_outcomes(&self) -> Vec<u8> {
+        let top = self.outcome_pools.iter().copied().max().unwrap_or(0);
+        (0..self.outcome_count)
+            .filter(|&i| self.outcome_pool(i) == top)
+            .collect()
+    }
+
+    /// Settles the market on `outcome`. Handlers emit `resolved_event`.
+    pub fn resolve(&mut self, outcome: u8, now: i64) {
+        self.status = MarketStatus::Resolved;
+        self.winning_outcome = Some(outcome);
+        self.resolved_at = now;
+        self.disputed_until = now.saturating_add(self.challenge_period);
+        self.chain_audit(b"resolve", &[&[outcome], &now.to_le_bytes()]);
+    }
+
+    /// The MarketResolved event for a just-resolved market, with the final
+    /// pools so indexers can price payouts from logs alone.
+    pub fn resolved_event(&self, resolver: Pubkey) -> Result<MarketResolved> {
+        Ok(MarketResolved {
+            market_id: self.market_id,
+            winning_outcome: self.winning_outcome.ok_or(ErrorCode::MarketNotResolved)?,
+            resolver,
+            outcome_pools: self.outcome_pools.clone(),
+            total_pool: self.checked_total_pool()?,
+            resolved_at: self.resolved_at,
+        })
+    }
+
+    /// Settles the market as void: every bet is refunded its stake. Voiding
+    /// on `cancel_market` or a dispute leaves the market Cancelled rather
+    /// than Resolved.
+    pub fn void(&mut self, now: i64, reason: VoidReason) {
+        self.status = if matches!(
+            reason,
+            VoidReason::Cancelled
+                | VoidReason::Disputed
+                | VoidReason::Unadjudicated
+                | VoidReason::EmergencyWithdrawn
+                | VoidReason::MintEscaped
+        ) {
+            MarketStatus::Cancelled
+        } else {
+            MarketStatus::Resolved
+        };
+        self.refund_only = true;
+        self.winning_outcome = None;
+        self.resolved_at = now;
+        self.resolver_defaulted = matches!(
+            reason,
+            VoidReason::ResolutionDeadlinePassed | VoidReason::Unrevealed
+        );
+        self.chain_audit(b"void", &[&[reason as u8], &now.to_le_bytes()]);
+
+        emit!(MarketVoided {
+            market_id: self.market_id,
+            reason,
+        });
+    }
+
+    /// Bets owed a payout: every bet on a voided market, otherwise the winners.
+    pub fn claimable_bets(&self) -> Result<u64> {
+        let bets = match self.winning_outcome {
+            Some(outcome) if !self.refund_only => Some(self.outcome_bet_counts[outcome as usize]),
+            _ => self
+                .outcome_bet_counts
+                .iter()
+                .try_fold(0u64, |total, &count| total.checked_add(count)),
+        };
+        bets.and_then(|bets| bets.checked_add(self.seed_owed() as u64))
+            .ok_or(error!(ErrorCode::MathOverflow))
+    }
+
+    /// Whether the creator's seed is owed a payout, counted as one more
+    /// claimant alongside the bets. An LMSR subsidy counts as seed.
+    pub fn seed_owed(&self) -> bool {
+        if self.lmsr_subsidy > 0 {
+            return true;
+        }
+        match self.winning_outcome {
+            Some(outcome) if !self.refund_only => {
+                self.seed_pools[outcome as usize] > 0 && !self.seed_forfeited(outcome)
+            }
+            _ => self.seed_pools.iter().any(|&seed| seed > 0),
+        }
+    }
+
+    /// Whether the winning side's seed joins the pool won by the other
+    /// winners instead of being reclaimable: someone else bet on a losing
+    /// outcome, and someone else backed the winner to collect it.
+    pub fn seed_forfeited(&self, winning_outcome: u8) -> bool {
+        let winner = winning_outcome as usize;
+        let seed = self.seed_pools[winner];
+        seed > 0
+            && self.outcome_weighted_pools[winner] > seed
+            && self
+                .outcome_pools
+                .iter()
+                .zip(&self.seed_pools)
+                .enumerate()
+                .any(|(i, (&pool, &seed))| i != winner && pool > seed)
+    }
+
+    /// The weight sharing the pool when `winning_outcome` wins: its bets,
+    /// plus the seed unless it was forfeited.
+    pub fn winning_weight(&self, winning_outcome: u8) -> u64 {
+        let weight = self.outcome_weighted_pools[winning_outcome as usize];
+        if self.seed_forfeited(winning_outcome) {
+            weight - self.seed_pools[winning_outcome as usize]
+        } else {
+            weight
+        }
+    }
+
+    /// The seed's payout, priced like a bet of the winning-side seed. On LMSR
+    /// markets it is whatever the winning shares leave of the stakes and the
+    /// subsidy.
+    pub fn seed_payout(&self) -> Result<u64> {
+        if self.refund_only {
+            return self
+                .seed_pools
+                .iter()
+                .try_fold(self.lmsr_subsidy, |total, &seed| total.checked_add(seed))
+                .ok_or(error!(ErrorCode::MathOverflow));
+        }
+        let winning_outcome = self.winning_outcome.ok_or(ErrorCode::MarketNotResolved)?;
+        if self.market_type != MarketType::Parimutuel {
+            return self
+                .checked_total_pool()?
+                .checked_add(self.lmsr_subsidy)
+                .and_then(|funds| funds.checked_sub(self.outcome_shares[winning_outcome as usize]))
+                .ok_or(error!(ErrorCode::MathUnderflow));
+        }
+        let total_pool = self.checked_total_pool()?;
+        let winning_weight = self.outcome_weighted_pools[winning_outcome as usize];
+        let seed = self.seed_pools[winning_outcome as usize];
+
+        self.pool_share(seed, seed, total_pool, winning_weight)
+    }
+
+    /// Records a settled bet, returning the close-out report to emit when it
+    /// was the last bet owed a payout and the log level allows it.
+    pub fn record_payout(&mut self, amount: u64) -> Result<Option<SettlementReport>> {
+        self.settled_bets = self.settled_bets.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        self.total_paid = self.total_paid.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.chain_audit(b"payout", &[&amount.to_le_bytes()]);
+        if self.settled_bets != self.claimable_bets()? || !self.logs(LogLevel::Minimal) {
+            return Ok(None);
+        }
+
+        let total_pool = self.checked_total_pool()?;
+        let winning_pool = match self.winning_outcome {
+            Some(outcome) if !self.refund_only => self.outcome_pool(outcome),
+            _ => total_pool,
+        };
+        Ok(Some(SettlementReport {
+            market_id: self.market_id,
+            total_pool,
+            winning_pool,
+            total_paid: self.total_paid,
+            total_fees: self.total_fees,
+            dust: total_pool
+                .checked_add(self.lmsr_subsidy)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_sub(self.total_paid)
+                .ok_or(ErrorCode::MathUnderflow)?,
+            claimant_count: self.settled_bets,
+        }))
+    }
+
+    /// Pari-mutuel payout for a winning bet: its share of the winning side's
+    /// weight applied to the whole pool,
+    /// `total_pool * weighted_amount / outcome_weighted_pools[outcome]`.
+    /// Without an early-bird bonus the weights are the stakes. LMSR bets are
+    /// paid their shares.
+    pub fn winnings_for(&self, bet: &Bet) -> Result<u64> {
+        if self.market_type != MarketType::Parimutuel {
+            return Ok(bet.shares);
+        }
+        let total_pool = self.checked_total_pool()?;
+        let winning_weight = self.winning_weight(bet.outcome);
+
+        self.pool_share(bet.weighted_amount, bet.amount, total_pool, winning_weight)
+    }
+
+    /// Payout weight of `amount` staked at `now`:
+    /// `amount * (10_000 + early_bird_bps * remaining / lifetime) / 10_000`,
+    /// where `lifetime` is `end_time - created_at` and `remaining` is
+    /// `end_time - now`, clamped to `0..=lifetime`. A bet at creation weighs
+    /// `1 + early_bird_bps / 10_000` times its stake; one at end_time, 1.
+    pub fn early_bird_weight(&self, amount: u64, now: i64) -> Result<u64> {
+        if self.early_bird_bps == 0 {
+            return Ok(amount);
+        }
+        let lifetime = (self.end_time - self.created_at).max(1) as u128;
+        let remaining = (self.end_time - now).clamp(0, lifetime as i64) as u128;
+        let bonus_bps = self.early_bird_bps as u128 * remaining / lifetime;
+        u64::try_from(amount as u128 * (10_000 + bonus_bps) / 10_000)
+            .map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    /// What a new bet of `amount` on `outcome` would win if that outcome won
+    /// and no further bets arrived.
+    pub fn projected_winnings(&self, outcome: u8, amount: u64) -> Result<u64> {
+        if self.market_type != MarketType::Parimutuel {
+            return Ok(amount);
+        }
+        let total_pool = self
+            .checked_total_pool()?
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let weight = self.early_bird_weight(amount, Clock::get()?.unix_timestamp)?;
+        let winning_weight = self.outcome_weighted_pools[outcome as usize]
+            .checked_add(weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.pool_share(weight, amount, total_pool, winning_weight)
+    }
+
+    /// Payout per unit staked, in basis points, if a bet of `amount` on
+    /// `outcome` won with no further bets:
+    /// `projected_winnings(outcome, amount) * 10_000 / stake_for(outcome, amount)`.
+    /// For pari-mutuel markets that is `(total_pool + amount) * 10_000 /
+    /// (outcome_pool + amount)`, before fees and limited by the payout cap.
+    pub fn expected_ratio_bps(&self, outcome: u8, amount: u64) -> Result<u64> {
+        let stake = self.stake_for(outcome, amount)?;
+        if stake == 0 {
+            return Ok(u64::MAX);
+        }
+        let winnings = self.projected_winnings(outcome, amount)? as u128;
+        Ok(u64::try_from(winnings * 10_000 / stake as u128).unwrap_or(u64::MAX))
+    }
+
+    /// `weight`'s share of `winning_weight` applied to `total_pool`, limited
+    /// to `max_payout_multiplier_bps` of the `stake`. Anything withheld by the
+    /// cap stays in the vault.
+    fn pool_share(&self, weight: u64, stake: u64, total_pool: u64, winning_weight: u64) -> Result<u64> {
+        // Resolution voids markets with an empty side, so this only guards
+        // against pricing against a pool nobody backed
+        require!(winning_weight > 0, ErrorCode::EmptyWinningPool);
+        let mut winnings = (weight as u128)
+            .checked_mul(total_pool as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / winning_weight as u128;
+
+        if self.max_payout_multiplier_bps > 0 {
+            let cap = stake as u128 * self.max_payout_multiplier_bps as u128 / 10_000;
+            winnings = winnings.min(cap);
+        }
+        u64::try_from(winnings).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Probability, scaled by 10^odds_decimals, above which a new bet of
+    /// `amount` on `outcome` by `bettor` has positive expected value:
+    /// stake / payout.
+    pub fn break_even_probability(
+        &self,
+        outcome: u8,
+        amount: u64,
+        now: i64,
+        bettor: Pubkey,
+    ) -> Result<u64> {
+        let scale = self.odds_scale();
+        let stake = self.stake_for(outcome, amount)?;
+        let gross = self.projected_winnings(outcome, amount)?;
+        let payout = self.net_of_cuts(gross, now, bettor)? as u128;
+        if payout == 0 {
+            return Ok(scale as u64);
+        }
+        Ok((stake as u128 * scale).div_ceil(payout).min(scale) as u64)
+    }
+
+    /// `gross` less the fee `bettor` would pay at `now` and the charity cut.
+    pub fn net_of_cuts(&self, gross: u64, now: i64, bettor: Pubkey) -> Result<u64> {
+        gross
+            .checked_sub(self.fee_for(gross, now, bettor))
+            .and_then(|net| net.checked_sub(self.charity_cut_for(gross)))
+            .ok_or(error!(ErrorCode::MathUnderflow))
+    }
+
+    pub fn odds_scale(&self) -> u128 {
+        10u128.pow(self.odds_decimals as u32)
+    }
+
+    /// Stake on `outcome`; 0 for an index past `outcome_count`.
+    pub fn outcome_pool(&self, outcome: u8) -> u64 {
+        self.outcome_pools.get(outcome as usize).copied().unwrap_or(0)
+    }
+
+    pub fn total_pool(&self) -> u128 {
+        self.outcome_pools.iter().map(|&pool| pool as u128).sum()
+    }
+
+    /// The pool as a token amount; MathOverflow if it no longer fits a u64.
+    pub fn checked_total_pool(&self) -> Result<u64> {
+        u64::try_from(self.total_pool()).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Share of the pool backing `outcome`. An empty market reports even odds
+    /// across its outcomes. LMSR markets report the market maker's price.
+    pub fn implied_probability(&self, outcome: u8) -> u64 {
+        self.implied_probability_at(outcome, self.odds_scale())
+    }
+
+    /// `implied_probability` with 1.0 represented as `scale`.
+    pub fn implied_probability_at(&self, outcome: u8, scale: u128) -> u64 {
+        if let MarketType::Lmsr { liquidity } = self.market_type {
+            return (lmsr_price(liquidity, &self.outcome_shares, outcome) * scale / LMSR_SCALE)
+                as u64;
+        }
+        let total_pool = self.total_pool();
+        if total_pool == 0 {
+            return (scale / self.outcome_count as u128) as u64;
+        }
+        (self.outcome_pool(outcome) as u128 * scale / total_pool) as u64
+    }
+
+    /// Total pool divided by the pool backing `outcome`. An empty side has no
+    /// finite multiplier and reports `u64::MAX`.
+    pub fn payout_multiplier(&self, outcome: u8) -> u64 {
+        self.payout_multiplier_at(outcome, self.odds_scale())
+    }
+
+    /// `payout_multiplier` at an explicit fixed-point `scale`.
+    pub fn payout_multiplier_at(&self, outcome: u8, scale: u128) -> u64 {
+        let side_pool = self.outcome_pool(outcome) as u128;
+        if side_pool == 0 {
+            return u64::MAX;
+        }
+        u64::try_from(self.total_pool() * scale / side_pool).unwrap_or(u64::MAX)
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct PlaceBet<'info> {
-    #[account(
-        mut,
-        seeds = [b"market", market_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
-    
-    #[account(
-        init,
-        payer = bettor,
-        space = 8 + Bet::INIT_SPACE,
-        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref()],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
-    
-    #[account(
-        mut,
-        seeds = [b"market_vault", market.key().as_ref()],
-        bump
-    )]
-    pub market_vault: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub bettor_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub bettor: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+/// A fixed-odds wager between a maker and one taker, seeded
+/// `[b"p2p_offer", market, maker]`. Its stakes sit in a separate escrow
+/// seeded `[b"p2p_escrow", offer]` and never enter the market's pools.
+#[account]
+#[derive(InitSpace)]
+pub struct P2pOffer {
+    pub market: Pubkey,
+    pub maker: Pubkey,
+    /// Outcome the maker backs; the taker wins on any other.
+    pub outcome: u8,
+    pub maker_stake: u64,
+    pub taker_stake: u64,
+    pub taker: Option<Pubkey>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bet {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    /// Distinguishes a wallet's bets on one market; part of the bet seeds.
+    pub bet_index: u64,
+    pub outcome: u8,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub is_claimed: bool,
+    pub claim_pending: bool,
+    pub campaign_id: Option<u64>,
+    /// Payout stored by `precompute_payouts`; claims use it when set.
+    pub payout_amount: Option<u64>,
+    /// LMSR shares bought; `amount` is what they cost. 0 on pari-mutuel markets.
+    pub shares: u64,
+    /// The bet's outcome pool and the total pool just after it was placed,
+    /// which priced its `expected_ratio_bps`.
+    pub side_pool_at_bet: u64,
+    pub total_pool_at_bet: u64,
+    /// `amount` scaled by the market's early-bird bonus at placement; equal
+    /// to `amount` when the market has none.
+    pub weighted_amount: u64,
+    /// Layout version the account was written with; see `BET_VERSION`.
+    pub version: u8,
+    /// Protocol fee rate in force when the bet was placed, charged on its
+    /// winnings; see `Market::fee_bps_at`. None on older bets.
+    pub fee_bps: Option<u16>,
+    /// Set when `approve_large_claim` lifts the hold on a vesting payout,
+    /// letting `claim_vested` lock it in, or once the hold has timed out.
+    pub claim_approved: bool,
+    /// When the payout was held for approval; it may be claimed without one
+    /// `CLAIM_APPROVAL_TIMEOUT` later.
+    pub claim_held_at: i64,
+}
+
+impl Bet {
+    /// Brings a bet read under an older layout up to `BET_VERSION`. Bets
+    /// placed before early-bird weighting weigh their stake, and claims held
+    /// before the hold was timed start their timeout now.
+    pub fn upgrade(&mut self, now: i64) {
+        if self.weighted_amount == 0 {
+            self.weighted_amount = self.amount;
+        }
+        if self.claim_pending && self.claim_held_at == 0 {
+            self.claim_held_at = now;
+        }
+        self.version = BET_VERSION;
+    }
+
+    /// Fails while a held payout awaits `approve_large_claim`, and treats the
+    /// hold as approved once `CLAIM_APPROVAL_TIMEOUT` has passed without one.
+    pub fn require_claim_released(&mut self, now: i64) -> Result<()> {
+        if self.claim_pending {
+            require!(
+                now >= self.claim_held_at.saturating_add(CLAIM_APPROVAL_TIMEOUT),
+                ErrorCode::ClaimPendingApproval
+            );
+            self.claim_pending = false;
+            self.claim_approved = true;
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserProfile {
+    pub wallet: Pubkey,
+    pub last_claim_at: i64,
+    /// Stake placed across all markets, in raw token units of each mint.
+    pub total_wagered: u64,
+    pub bets_placed: u64,
+    /// Winning payouts received, net of fees; refunds do not count.
+    pub total_won: u64,
+    pub bets_won: u64,
+}
+
+impl UserProfile {
+    /// Adds a wager to the wallet's stats; `new_bet` is false for top-ups.
+    pub fn record_wager(&mut self, wallet: Pubkey, amount: u64, new_bet: bool) -> Result<()> {
+        self.wallet = wallet;
+        self.total_wagered = self
+            .total_wagered
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if new_bet {
+            self.bets_placed = self.bets_placed.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Takes a withdrawn stake back off the wallet's stats; `closed_bet` is
+    /// true when the whole bet was cancelled. A profile opened after the bet
+    /// was placed never counted it and is left as it is.
+    pub fn release_wager(&mut self, amount: u64, closed_bet: bool) -> Result<()> {
+        if self.wallet == Pubkey::default() {
+            return Ok(());
+        }
+        self.total_wagered = self
+            .total_wagered
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        if closed_bet {
+            self.bets_placed = self.bets_placed.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a winning payout to the wallet's stats.
+    pub fn record_win(&mut self, amount: u64) -> Result<()> {
+        self.total_won = self.total_won.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.bets_won = self.bets_won.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Stamps a claim at `now`, rejecting it if it lands within `cooldown`
+    /// seconds of the wallet's previous claim.
+    pub fn record_claim(&mut self, wallet: Pubkey, cooldown: i64, now: i64) -> Result<()> {
+        if self.wallet == Pubkey::default() {
+            self.wallet = wallet;
+        } else {
+            let next_claim_at = self
+                .last_claim_at
+                .checked_add(cooldown)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(cooldown == 0 || now >= next_claim_at, ErrorCode::ClaimingTooFast);
+        }
+        self.last_claim_at = now;
+        Ok(())
+    }
+}
+
+/// A wallet's combined bets on one market, seeded
+/// `[b"user_position", market, bettor]`, so front-ends read one account
+/// instead of every Bet.
+#[account]
+#[derive(InitSpace)]
+pub struct UserPosition {
+    pub bettor: Pubkey,
+    pub market_id: u64,
+    /// Live stake per outcome: placed and increased, less what was
+    /// cancelled or reduced.
+    #[max_len(8)]
+    pub outcome_stakes: Vec<u64>,
+    pub total_staked: u64,
+    /// Bets placed and not cancelled.
+    pub bet_count: u64,
+    /// Paid out across claims and refunds, net of fees.
+    pub total_claimed: u64,
+}
+
+impl UserPosition {
+    /// Adds a stake on `outcome`; `new_bet` is false for top-ups.
+    pub fn record_stake(
+        &mut self,
+        market: &Market,
+        bettor: Pubkey,
+        outcome: u8,
+        amount: u64,
+        new_bet: bool,
+    ) -> Result<()> {
+        if self.outcome_stakes.is_empty() {
+            self.bettor = bettor;
+            self.market_id = market.market_id;
+            self.outcome_stakes = vec![0; market.outcome_count as usize];
+        }
+        let stake = &mut self.outcome_stakes[outcome as usize];
+        *stake = stake.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_staked = self
+            .total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if new_bet {
+            self.bet_count = self.bet_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a withdrawn stake; `closed_bet` is true when the whole bet was
+    /// cancelled. Positions opened after the bet was placed hold none of its
+    /// stake and are left as they are.
+    pub fn release_stake(&mut self, outcome: u8, amount: u64, closed_bet: bool) -> Result<()> {
+        if self.outcome_stakes.is_empty() {
+            return Ok(());
+        }
+        let stake = &mut self.outcome_stakes[outcome as usize];
+        *stake = stake.checked_sub(amount).ok_or(ErrorCode::MathUnderflow)?;
+        self.total_staked = self
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        if closed_bet {
+            self.bet_count = self.bet_count.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+        }
+        Ok(())
+    }
+
+    pub fn record_payout(&mut self, amount: u64) -> Result<()> {
+        self.total_claimed = self
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Votes cast so far on a threshold market, one per resolver.
+#[account]
+#[derive(InitSpace)]
+pub struct ResolutionVotes {
+    pub market: Pubkey,
+    #[max_len(5)]
+    pub votes: Vec<ResolverVote>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ResolverVote {
+    pub resolver: Pubkey,
+    pub outcome: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Campaign {
+    pub campaign_id: u64,
+    pub owner: Pubkey,
+    /// Live stake of the bets tagged with the campaign.
+    pub total_volume: u64,
+    /// Tagged bets placed and not cancelled.
+    pub bet_count: u64,
+}
+
+impl Campaign {
+    /// Adds a tagged stake; `new_bet` is false for top-ups.
+    pub fn record_volume(&mut self, amount: u64, new_bet: bool) -> Result<()> {
+        self.total_volume = self
+            .total_volume
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if new_bet {
+            self.bet_count = self.bet_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a withdrawn stake; `closed_bet` is true when the whole bet was
+    /// cancelled.
+    pub fn release_volume(&mut self, amount: u64, closed_bet: bool) -> Result<()> {
+        self.total_volume = self
+            .total_volume
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathUnderflow)?;
+        if closed_bet {
+            self.bet_count = self.bet_count.checked_sub(1).ok_or(ErrorCode::MathUnderflow)?;
+        }
+        Ok(())
+    }
+}
+
+/// Program-wide settings, seeded `[b"config"]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    /// Kill switch for incident response: refuses new bets on every market.
+    pub paused: bool,
+    /// Most markets one authority may have open at once; 0 is unlimited.
+    pub max_open_markets_per_authority: u32,
+    /// How `distribute_treasury` splits protocol revenue; empty leaves it to
+    /// the treasury admin.
+    #[max_len(5)]
+    pub fee_splits: Vec<FeeSplit>,
+}
+
+/// One recipient's share of protocol revenue, in basis points.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+/// A pending emergency drain of one market's vault, seeded
+/// `[b"emergency_withdrawal", market]`. The destination is fixed when the
+/// timelock starts so it cannot be swapped at execution.
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyWithdrawal {
+    pub market: Pubkey,
+    pub destination: Pubkey,
+    pub requested_at: i64,
+    pub executable_at: i64,
+}
+
+/// Markets an authority has created and not yet closed, seeded
+/// `[b"authority_stats", authority]`.
+#[account]
+#[derive(InitSpace)]
+pub struct AuthorityStats {
+    pub authority: Pubkey,
+    pub open_market_count: u32,
+}
+
+/// Lamport escrow for a NativeSol market, seeded `[b"sol_vault", market]`.
+/// Stakes sit on top of its own rent-exempt minimum, which never leaves it.
+#[account]
+#[derive(InitSpace)]
+pub struct SolVault {
+    pub market: Pubkey,
+}
+
+/// Hands out sequential market ids to `create_market_auto`.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketRegistry {
+    pub next_market_id: u64,
+}
+
+/// Protocol revenue: market fees and dispute bonds land in its per-mint
+/// vaults, seeded `[b"treasury_vault", mint]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub admin: Pubkey,
+    /// Lifetime revenue across all mints, in raw token units.
+    pub total_collected: u64,
+}
+
+impl Treasury {
+    pub fn record_collected(&mut self, amount: u64) -> Result<()> {
+        self.total_collected = self
+            .total_collected
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Compact record of a settled market left behind by `archive_market`.
+#[account]
+#[derive(InitSpace)]
+pub struct Archive {
+    pub market_id: u64,
+    pub authority: Pubkey,
+    pub winning_outcome: Option<u8>,
+    pub refund_only: bool,
+    #[max_len(8)]
+    pub outcome_pools: Vec<u64>,
+    pub total_paid: u64,
+    pub created_at: i64,
+    pub end_time: i64,
+    pub resolved_at: i64,
+    pub archived_at: i64,
+}
+
+/// Links the two bets placed together by `hedge_bet`.
+#[account]
+#[derive(InitSpace)]
+pub struct Hedge {
+    pub bettor: Pubkey,
+    pub first_market_id: u64,
+    pub second_market_id: u64,
+    pub first: HedgeLeg,
+    pub second: HedgeLeg,
+    pub created_at: i64,
+}
+
+/// The leading fields of a Pyth receiver `PriceUpdateV2` account, enough to
+/// read its price without depending on the Pyth SDK.
+#[derive(AnchorDeserialize)]
+pub struct PythPriceUpdate {
+    pub write_authority: Pubkey,
+    pub verification_level: PythVerificationLevel,
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+#[derive(AnchorDeserialize)]
+pub enum PythVerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+impl PythPriceUpdate {
+    /// Decodes a price update owned by the Pyth receiver program.
+    pub fn read(feed: &AccountInfo) -> Result<Self> {
+        require!(
+            feed.owner == &PYTH_RECEIVER_PROGRAM_ID,
+            ErrorCode::OracleFeedMismatch
+        );
+        let data = feed.try_borrow_data()?;
+        let discriminator = &hashv(&[b"account:PriceUpdateV2"]).to_bytes()[..8];
+        require!(
+            data.len() > 8 && &data[..8] == discriminator,
+            ErrorCode::OracleFeedMismatch
+        );
+        let mut body = &data[8..];
+        Self::deserialize(&mut body).map_err(|_| error!(ErrorCode::OracleFeedMismatch))
+    }
+
+    /// Decodes a price update, requiring full Wormhole verification and the
+    /// expected feed id.
+    pub fn read_verified(feed: &AccountInfo, feed_id: [u8; 32]) -> Result<Self> {
+        let price = Self::read(feed)?;
+        require!(
+            matches!(price.verification_level, PythVerificationLevel::Full),
+            ErrorCode::OracleNotFullyVerified
+        );
+        require!(price.feed_id == feed_id, ErrorCode::OracleFeedMismatch);
+        Ok(price)
+    }
+}
+
+/// Return data of `create_market` and `create_market_auto`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CreatedMarket {
+    pub market_id: u64,
+    pub market: Pubkey,
+    pub market_bump: u8,
+    pub market_vault: Pubkey,
+    pub vault_bump: u8,
+}
+
+/// One side of a hedge: the bet placed and the outcome and stake it backs.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug)]
+pub struct HedgeLeg {
+    pub bet_index: u64,
+    pub outcome: u8,
+    pub amount: u64,
+}
+
+/// Where a market is in its lifecycle. Transitions only move forward:
+/// Open -> Closed -> Resolved, or Open/Closed -> Cancelled.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketStatus {
+    /// Accepting bets until end_time.
+    Open,
+    /// Betting is over; awaiting resolution.
+    Closed,
+    /// Resolved on an outcome, or voided with every bet refunded.
+    Resolved,
+    /// Cancelled by the authority; every bet is refunded.
+    Cancelled,
+}
+
+/// What a market is staked in.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Currency {
+    /// The market's SPL mint, held in its token vault.
+    Spl,
+    /// Lamports, held in the market's SolVault escrow. The mint and token
+    /// vault are still created but stay empty.
+    NativeSol,
+}
+
+/// How a market prices bets.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarketType {
+    /// Winners split the whole pool in proportion to their stake.
+    Parimutuel,
+    /// A logarithmic market scoring rule market maker with liquidity `b`,
+    /// in token units, sells shares that each pay one token unit if their
+    /// outcome wins. Binary markets only; the creator funds its worst-case
+    /// loss of b ln 2 at creation.
+    Lmsr { liquidity: u64 },
+}
+
+/// The authority's verdict in `resolve_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Resolution {
+    /// The winning outcome index; for binary markets 0 is YES, 1 is NO.
+    Outcome(u8),
+    /// No outcome won, e.g. a draw or a called-off event; every stake is
+    /// refunded.
+    Void,
+}
+
+/// Who or what resolves a market, fixed at creation.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResolutionSource {
+    /// The market authority, via `resolve_market`.
+    ManualAuthority,
+    Committee,
+    Oracle,
+    Governance,
+    Vrf,
+    /// Stake-weighted majority, via `resolve_by_majority`.
+    Community,
+    /// The authority commits to an outcome before end_time and reveals it
+    /// after, via `commit_outcome` and `reveal_outcome`.
+    CommitReveal,
+}
+
+impl ResolutionSource {
+    /// Sources with a resolve instruction in this program. Markets cannot be
+    /// created with a source that would leave them unresolvable.
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            Self::ManualAuthority | Self::Oracle | Self::Community | Self::CommitReveal
+        )
+    }
+}
+
+/// Event volume from the bet, claim and P2P instructions. Lifecycle events
+/// (creation, resolution, closing) are always emitted.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// No bet or claim events.
+    None,
+    /// Claim payouts, refunds, P2P settlements, held claims and the
+    /// settlement report.
+    Minimal,
+    /// Everything, including one BetPlaced per bet, top-ups, hedges,
+    /// cancellations, reductions and P2P offers.
+    Full,
 }
 
-#[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct ResolveMarket<'info> {
-    #[account(
-        mut,
-        seeds = [b"market", market_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
-    
-    pub authority: Signer<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VoidReason {
+    NarrowMajority,
+    OneSided,
+    /// The revealed outcome did not match the commitment.
+    CommitmentMismatch,
+    /// No matching reveal arrived within the reveal window.
+    Unrevealed,
+    /// Nobody resolved the market before its resolution deadline.
+    ResolutionDeadlinePassed,
+    /// The authority cancelled the market.
+    Cancelled,
+    /// A bettor disputed the resolution within its challenge window.
+    Disputed,
+    /// The pool was under the market's `min_pool_to_settle`.
+    InsufficientLiquidity,
+    /// The authority resolved the market as void, e.g. a draw.
+    ResolvedVoid,
+    /// Nobody bet; nothing is owed and the market can be closed at once.
+    NoBets,
+    /// The admin drained the vault with `emergency_withdraw`.
+    EmergencyWithdrawn,
+    /// The admin moved the market off a dead mint with `escape_to_mint`.
+    MintEscaped,
+    /// The admin did not rule on a dispute by its adjudication deadline.
+    Unadjudicated,
 }
 
-#[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct ClaimWinnings<'info> {
-    #[account(
-        seeds = [b"market", market_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
-    
-    #[account(
-        mut,
-        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref()],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
-    
-    #[account(
-        mut,
-        seeds = [b"market_vault", market.key().as_ref()],
-        bump
-    )]
-    pub market_vault: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub bettor_token_account: Account<'info, TokenAccount>,
-    
-    pub bettor: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum BetResult {
+    Pending,
+    Won,
+    Lost,
+    Voided,
 }
 
-// Data structures
 #[account]
 #[derive(InitSpace)]
-pub struct Market {
-    pub authority: Pubkey,
+pub struct Vesting {
+    pub bettor: Pubkey,
     pub market_id: u64,
-    #[max_len(280)]
-    pub description: String,
+    pub total_amount: u64,
+    pub released_amount: u64,
+    pub start_time: i64,
     pub end_time: i64,
-    pub min_bet_amount: u64,
-    pub total_yes_bets: u64,
-    pub total_no_bets: u64,
-    pub is_resolved: bool,
-    pub winning_outcome: Option<bool>,
-    pub created_at: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Bet {
-    pub bettor: Pubkey,
-    pub market_id: u64,
-    pub outcome: bool,
-    pub amount: u64,
-    pub timestamp: i64,
-    pub is_claimed: bool,
+impl Vesting {
+    /// Amount unlocked at `now`, growing linearly from start_time to end_time.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        if now >= self.end_time {
+            return Ok(self.total_amount);
+        }
+        if now <= self.start_time {
+            return Ok(0);
+        }
+        let elapsed = now.checked_sub(self.start_time).ok_or(ErrorCode::MathUnderflow)? as u128;
+        let duration = self
+            .end_time
+            .checked_sub(self.start_time)
+            .ok_or(ErrorCode::MathUnderflow)? as u128;
+        Ok((self.total_amount as u128 * elapsed / duration) as u64)
+    }
 }
 
 // Events
@@ -342,19 +8988,112 @@ pub struct MarketCreated {
     pub end_time: i64,
 }
 
+#[event]
+pub struct LiquiditySeeded {
+    pub market_id: u64,
+    pub authority: Pubkey,
+    /// What each outcome's pool was credited, after any transfer fee.
+    pub seed_pools: Vec<u64>,
+}
+
 #[event]
 pub struct BetPlaced {
     pub market_id: u64,
     pub bettor: Pubkey,
-    pub outcome: bool,
+    pub bet_index: u64,
+    pub outcome: u8,
     pub amount: u64,
+    pub campaign_id: Option<u64>,
+    /// Stake per outcome after this bet; for binary markets 0 is YES, 1 is NO.
+    pub outcome_pools: Vec<u64>,
+    /// Each outcome's implied probability after this bet, in basis points:
+    /// `outcome_pool * 10_000 / total_pool`, or the LMSR price. An empty
+    /// market reports even odds (5000 each for a binary market).
+    pub implied_probabilities_bps: Vec<u16>,
 }
 
 #[event]
 pub struct MarketResolved {
     pub market_id: u64,
-    pub winning_outcome: bool,
+    pub winning_outcome: u8,
+    pub resolver: Pubkey,
+    /// Stake per outcome at resolution; for binary markets 0 is YES, 1 is NO.
+    pub outcome_pools: Vec<u64>,
+    pub total_pool: u64,
+    pub resolved_at: i64,
+}
+
+#[event]
+pub struct ResolutionVoteSubmitted {
+    pub market_id: u64,
     pub resolver: Pubkey,
+    pub outcome: u8,
+    /// Votes for `outcome` so far, including this one.
+    pub votes_for: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct BettingClosed {
+    pub market_id: u64,
+    /// Final stake per outcome; no bets can change it after closing.
+    pub outcome_pools: Vec<u64>,
+    pub closed_at: i64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market_id: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct MarketExtended {
+    pub market_id: u64,
+    pub old_end_time: i64,
+    pub new_end_time: i64,
+}
+
+#[event]
+pub struct TieBroken {
+    pub market_id: u64,
+    pub tied_outcomes: Vec<u8>,
+    pub slot: u64,
+    pub seed: [u8; 32],
+    pub winning_outcome: u8,
+}
+
+#[event]
+pub struct MarketVoided {
+    pub market_id: u64,
+    pub reason: VoidReason,
+}
+
+#[event]
+pub struct ResolutionDisputed {
+    pub market_id: u64,
+    pub disputer: Pubkey,
+    pub disputed_outcome: u8,
+    pub bond: u64,
+}
+
+#[event]
+pub struct DisputeAdjudicated {
+    pub market_id: u64,
+    pub disputer: Pubkey,
+    /// Whether the resolution stood, forfeiting the bond to the treasury.
+    pub upheld: bool,
+    pub bond: u64,
+}
+
+#[event]
+pub struct BondReleased {
+    pub market_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// Whether the bond went to a disputer or the treasury rather than back
+    /// to the authority.
+    pub forfeited: bool,
 }
 
 #[event]
@@ -364,6 +9103,277 @@ pub struct WinningsClaimed {
     pub amount: u64,
 }
 
+#[event]
+pub struct VestedWinningsReleased {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub amount: u64,
+    pub total_released: u64,
+    pub total_amount: u64,
+}
+
+/// Emitted once, with the payout that settles the last claimable bet.
+#[event]
+pub struct SettlementReport {
+    pub market_id: u64,
+    pub total_pool: u64,
+    pub winning_pool: u64,
+    /// Everything sent out of the vault, fees included.
+    pub total_paid: u64,
+    pub total_fees: u64,
+    /// Left in the vault after every claim: rounding dust plus anything
+    /// withheld by the payout cap.
+    pub dust: u64,
+    pub claimant_count: u64,
+}
+
+#[event]
+pub struct FeeCollected {
+    pub market_id: u64,
+    pub amount: u64,
+    /// The treasury's lifetime revenue after this fee.
+    pub treasury_total: u64,
+}
+
+#[event]
+pub struct CreatorFeesWithdrawn {
+    pub market_id: u64,
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MaxOpenMarketsSet {
+    pub admin: Pubkey,
+    pub max_open_markets: u32,
+}
+
+#[event]
+pub struct PausedSet {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub admin: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeeSplitsSet {
+    pub admin: Pubkey,
+    pub splits: Vec<FeeSplit>,
+}
+
+#[event]
+pub struct TreasuryDistributed {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub splits: Vec<FeeSplit>,
+}
+
+#[event]
+pub struct DustSwept {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawRequested {
+    pub market_id: u64,
+    pub admin: Pubkey,
+    pub destination: Pubkey,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawn {
+    pub market_id: u64,
+    pub admin: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawCancelled {
+    pub market_id: u64,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct MintEscaped {
+    pub market_id: u64,
+    pub admin: Pubkey,
+    pub old_mint: Pubkey,
+    pub new_mint: Pubkey,
+    pub rate_num: u64,
+    pub rate_den: u64,
+    /// New-mint tokens deposited in the escape vault.
+    pub funded: u64,
+}
+
+#[event]
+pub struct MarketsMerged {
+    pub source_market_id: u64,
+    pub dest_market_id: u64,
+    pub amount: u64,
+    pub outcome_pools: Vec<u64>,
+}
+
+#[event]
+pub struct LargeClaimHeld {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MinBetUpdated {
+    pub market_id: u64,
+    pub min_bet_amount: u64,
+}
+
+#[event]
+pub struct MetadataUpdated {
+    pub market_id: u64,
+    pub description: String,
+    pub metadata_uri: String,
+}
+
+#[event]
+pub struct MarketMigrated {
+    pub market_id: u64,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct BetMigrated {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub bet_index: u64,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market_id: u64,
+    pub rent_recipient: Pubkey,
+}
+
+#[event]
+pub struct TimeoutRefund {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub bet_index: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BetCancelled {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub bet_index: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CharityDonation {
+    pub market_id: u64,
+    pub charity: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct P2pOfferCreated {
+    pub market_id: u64,
+    pub maker: Pubkey,
+    pub outcome: u8,
+    pub maker_stake: u64,
+    pub taker_stake: u64,
+}
+
+#[event]
+pub struct P2pOfferAccepted {
+    pub market_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+}
+
+#[event]
+pub struct P2pOfferCancelled {
+    pub market_id: u64,
+    pub maker: Pubkey,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct P2pSettled {
+    pub market_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub maker_payout: u64,
+    pub taker_payout: u64,
+}
+
+#[event]
+pub struct BetReduced {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub bet_index: u64,
+    pub withdrawn: u64,
+    /// Stake left on the bet.
+    pub remaining: u64,
+}
+
+#[event]
+pub struct BetIncreased {
+    pub market_id: u64,
+    pub bettor: Pubkey,
+    pub bet_index: u64,
+    pub amount: u64,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct HedgePlaced {
+    pub bettor: Pubkey,
+    pub first_market_id: u64,
+    pub second_market_id: u64,
+    pub first: HedgeLeg,
+    pub second: HedgeLeg,
+}
+
+#[event]
+pub struct MarketArchived {
+    pub market_id: u64,
+    pub archive: Pubkey,
+}
+
+#[event]
+pub struct BetsRefunded {
+    pub market_id: u64,
+    pub refunded: u32,
+    pub skipped: u32,
+}
+
+#[event]
+pub struct PayoutsPrecomputed {
+    pub market_id: u64,
+    pub stored: u32,
+    pub skipped: u32,
+}
+
+#[event]
+pub struct BetsClosed {
+    pub market_id: u64,
+    pub closed: u32,
+    pub skipped: u32,
+}
+
 // Error codes
 #[error_code]
 pub enum ErrorCode {
@@ -393,4 +9403,250 @@ pub enum ErrorCode {
     UnauthorizedClaimer,
     #[msg("This bet lost")]
     LosingBet,
+    #[msg("Odds precision exceeds the supported number of decimals")]
+    InvalidOddsPrecision,
+    #[msg("Token account is frozen or cannot receive this mint")]
+    TokenAccountUnusable,
+    #[msg("Vesting period cannot be negative")]
+    InvalidVestingPeriod,
+    #[msg("Winnings on this market vest - use claim_vested")]
+    WinningsVest,
+    #[msg("Market does not vest winnings")]
+    MarketNotVesting,
+    #[msg("Nothing has vested since the last release")]
+    NothingVested,
+    #[msg("Only the authority of both markets can merge them")]
+    UnauthorizedMerge,
+    #[msg("Markets must be distinct, share a mint and question, and have no vesting or claim hold")]
+    MarketsNotMergeable,
+    #[msg("Market has been merged into another market")]
+    MarketMerged,
+    #[msg("Market was not merged into the given destination")]
+    MarketNotMerged,
+    #[msg("Bet would push this outcome's pool over its cap")]
+    OutcomeCapExceeded,
+    #[msg("Claim exceeds the high-value threshold and awaits approval")]
+    ClaimPendingApproval,
+    #[msg("Claim is not awaiting approval")]
+    ClaimNotPending,
+    #[msg("Basis points must not exceed 10000")]
+    InvalidBasisPoints,
+    #[msg("Market is resolved by a different resolution source")]
+    WrongResolutionSource,
+    #[msg("Resolution source has no resolve instruction yet")]
+    UnsupportedResolutionSource,
+    #[msg("Campaign account does not match the bet's campaign id")]
+    CampaignMismatch,
+    #[msg("Remaining accounts are not in the expected groups")]
+    InvalidRemainingAccounts,
+    #[msg("Market vault still holds funds")]
+    VaultNotEmpty,
+    #[msg("Market already has bets")]
+    MarketHasBets,
+    #[msg("Payout cap must be 0 or at least 1x the stake")]
+    InvalidPayoutCap,
+    #[msg("Claim cooldown cannot be negative")]
+    InvalidClaimCooldown,
+    #[msg("Claiming too fast - wait for the cooldown to pass")]
+    ClaimingTooFast,
+    #[msg("Token account has not delegated exactly this amount to the vault")]
+    InvalidDelegation,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Arithmetic underflow")]
+    MathUnderflow,
+    #[msg("Market does not have enough stake on both sides")]
+    OneSidedMarket,
+    #[msg("Seeding a market requires the creator's token account")]
+    SeedAccountMissing,
+    #[msg("Market has no seed liquidity owed to the creator")]
+    NoSeedToReclaim,
+    #[msg("A hedge needs two different markets")]
+    InvalidHedge,
+    #[msg("CommitReveal markets need a positive reveal window")]
+    InvalidRevealWindow,
+    #[msg("Outcome already committed")]
+    OutcomeAlreadyCommitted,
+    #[msg("No outcome was committed")]
+    NoOutcomeCommitment,
+    #[msg("Reveal window has closed")]
+    RevealWindowClosed,
+    #[msg("Reveal window is still open")]
+    RevealWindowOpen,
+    #[msg("Resolution deadline must be after the end time")]
+    InvalidResolutionDeadline,
+    #[msg("Resolution deadline has passed; the market can only be voided")]
+    ResolutionDeadlinePassed,
+    #[msg("Market can still be resolved")]
+    ResolutionWindowOpen,
+    #[msg("Market has not been voided")]
+    MarketNotVoided,
+    #[msg("Market still has payouts owed")]
+    MarketNotFullySettled,
+    #[msg("No stake backs the winning outcome")]
+    EmptyWinningPool,
+    #[msg("Fee exceeds the maximum of 1000 basis points")]
+    FeeTooHigh,
+    #[msg("This market charges a fee; pass the treasury and its vault for the market's mint")]
+    FeeVaultMissing,
+    #[msg("Bet did not lose; claim it instead")]
+    BetNotLosing,
+    #[msg("Markets need between 2 and 8 outcomes, with caps for each or none")]
+    InvalidOutcomeCount,
+    #[msg("Outcome index is out of range for this market")]
+    InvalidOutcome,
+    #[msg("Seeded liquidity is only supported on binary markets")]
+    SeedRequiresBinary,
+    #[msg("Market has been cancelled")]
+    MarketCancelled,
+    #[msg("Resolution deadline has not passed yet")]
+    ResolutionDeadlineNotReached,
+    #[msg("Market vault has no balance left to pay out")]
+    InsufficientVaultBalance,
+    #[msg("Bet amount exceeds the market maximum")]
+    BetTooLarge,
+    #[msg("Betting on this market is closed")]
+    BettingClosed,
+    #[msg("Challenge period cannot be negative")]
+    InvalidChallengePeriod,
+    #[msg("Resolution can still be disputed; claims open when the challenge window ends")]
+    ChallengeWindowActive,
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("Oracle markets need a feed, a positive staleness limit and two outcomes")]
+    InvalidOracleConfig,
+    #[msg("Oracle account is not the market's price feed")]
+    OracleFeedMismatch,
+    #[msg("Oracle price is stale or predates the market's end")]
+    StaleOracleData,
+    #[msg("New end time must be later than the current one and before the resolution deadline")]
+    InvalidExtension,
+    #[msg("Bet backs a different outcome")]
+    OutcomeMismatch,
+    #[msg("Only the treasury admin can do this")]
+    UnauthorizedAdmin,
+    #[msg("Metadata URI is longer than 200 bytes")]
+    MetadataUriTooLong,
+    #[msg("A resolver bond requires the bond vault and the creator's token account")]
+    BondAccountsMissing,
+    #[msg("Resolver bond cannot be released yet")]
+    BondNotReleasable,
+    #[msg("Release the resolver bond first")]
+    BondOutstanding,
+    #[msg("Betting is paused")]
+    ProgramPaused,
+    #[msg("Token account mint does not match the market's mint")]
+    MintMismatch,
+    #[msg("LMSR markets need positive liquidity, two outcomes, and no seed, caps or payout cap")]
+    InvalidLmsrConfig,
+    #[msg("Instruction is not supported for this market type")]
+    UnsupportedMarketType,
+    #[msg("LMSR markets do not support mints with transfer fees")]
+    TransferFeeUnsupported,
+    #[msg("Close betting before resolving the market")]
+    BettingNotClosed,
+    #[msg("Expected payout ratio is below the bettor's minimum")]
+    SlippageExceeded,
+    #[msg("Too many resolvers for one market")]
+    TooManyResolvers,
+    #[msg("Signer is not in the market's resolver set")]
+    NotInResolverSet,
+    #[msg("Resolver threshold exceeds the resolver set")]
+    InvalidResolverThreshold,
+    #[msg("This market resolves by resolver votes")]
+    VoteThresholdRequired,
+    #[msg("This market does not resolve by resolver votes")]
+    VotingNotEnabled,
+    #[msg("Resolver has already voted on this market")]
+    DuplicateVote,
+    #[msg("Creator fee vault is required when the market charges a creator fee")]
+    CreatorFeeVaultMissing,
+    #[msg("No creator fees to withdraw")]
+    NoFeesToWithdraw,
+    #[msg("Withdraw the accrued creator fees first")]
+    CreatorFeesOutstanding,
+    #[msg("Betting cutoff must be non-negative and shorter than the market's lifetime")]
+    InvalidBettingCutoff,
+    #[msg("Reduction would leave the bet below the market minimum")]
+    WouldGoBelowMinimum,
+    #[msg("Resolution criteria too long")]
+    ResolutionCriteriaTooLong,
+    #[msg("Native SOL markets must be plain pari-mutuel markets without fees, seed, bond, vesting or claim approval")]
+    InvalidNativeConfig,
+    #[msg("SOL vault is required for native SOL markets")]
+    SolVaultMissing,
+    #[msg("Instruction does not support this market's currency")]
+    CurrencyMismatch,
+    #[msg("The market's authority and resolvers cannot bet on it")]
+    AuthorityCannotBet,
+    #[msg("Bettor is not on the market's whitelist")]
+    NotWhitelisted,
+    #[msg("Authority already has the maximum number of open markets")]
+    TooManyOpenMarkets,
+    #[msg("Emergency withdrawal timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Dust has already been swept from this market")]
+    DustAlreadySwept,
+    #[msg("Vault holds no dust to sweep")]
+    NoDustToSweep,
+    #[msg("Vested payouts are still held in the vault")]
+    VestingOutstanding,
+    #[msg("Market has reached its maximum number of bets")]
+    MarketFull,
+    #[msg("Odds leave the taker nothing to stake")]
+    InvalidOdds,
+    #[msg("Offer has already been accepted")]
+    OfferAlreadyAccepted,
+    #[msg("Offer has not been accepted")]
+    OfferNotAccepted,
+    #[msg("Makers cannot accept their own offer")]
+    CannotAcceptOwnOffer,
+    #[msg("Cancel or settle the market's P2P offers first")]
+    P2pOffersOutstanding,
+    #[msg("End time is further out than the maximum market duration")]
+    EndTimeTooFar,
+    #[msg("Charity and charity_bps must be set together, and with fees cannot exceed the payout")]
+    InvalidCharityConfig,
+    #[msg("Charity token account is required when the market donates a cut")]
+    CharityAccountMissing,
+    #[msg("Account is already at the current layout version")]
+    AlreadyMigrated,
+    #[msg("Close the market's remaining bet accounts first")]
+    BetsOutstanding,
+    #[msg("Oracle price update is not fully verified")]
+    OracleNotFullyVerified,
+    #[msg("A challenge period needs a non-zero dispute bond")]
+    DisputeBondRequired,
+    #[msg("Resolution is under dispute until the admin adjudicates it")]
+    DisputePending,
+    #[msg("Market has no pending dispute")]
+    NoDisputePending,
+    #[msg("Market is halted pending an emergency withdrawal")]
+    MarketHalted,
+    #[msg("Fee splits must be non-zero, at most five, and sum to 10000 bps")]
+    InvalidFeeSplit,
+    #[msg("Treasury revenue is paid out through the configured fee splits")]
+    FeeSplitConfigured,
+    #[msg("Market must be halted, unpaid and undisputed to escape its mint")]
+    EscapeNotAllowed,
+    #[msg("Conversion rate must be non-zero")]
+    InvalidConversionRate,
+    #[msg("Market settles in its escape mint; use claim_escaped")]
+    MintEscaped,
+    #[msg("Disputer cannot cover the dispute bond")]
+    InsufficientDisputeBond,
+    #[msg("Dispute bond share cannot exceed the pool")]
+    InvalidDisputeBond,
+    #[msg("Offer has been matched and can no longer be cancelled")]
+    OfferAlreadyMatched,
+    #[msg("Fee rate has moved since the bet was placed; place a new bet")]
+    FeeRateChanged,
+    #[msg("The adjudication deadline has passed; the dispute settles by default")]
+    AdjudicationDeadlinePassed,
+    #[msg("The dispute can still be adjudicated")]
+    AdjudicationWindowOpen,
+    #[msg("Pass the account the bond is paid to: the authority's, the disputer's or the treasury's")]
+    BondRecipientMissing,
+    #[msg("Only the program's upgrade authority can do this")]
+    NotUpgradeAuthority,
 }
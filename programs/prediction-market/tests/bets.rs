@@ -0,0 +1,1347 @@
+mod common;
+
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, whitelist_leaf, Bet, BetCancelled, BetIncreased, BetReduced,
+    Campaign, ErrorCode, Hedge, LogLevel, MarketExtended, MarketOptions, MinBetUpdated,
+    TimeoutRefund, UserPosition, UserProfile, MAX_MARKET_DURATION,
+};
+
+#[test]
+fn a_capped_outcome_fills_while_others_stay_open() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            outcome_count: 3,
+            outcome_caps: vec![500, 0, 200],
+            ..options()
+        },
+    );
+    let bettor = w.bettor(10_000);
+    w.place_bet(1, bettor, 0, 0, 300).unwrap();
+    w.place_bet(1, bettor, 1, 0, 200).unwrap();
+
+    // Outcome 0 is full; a single unit more is refused
+    assert_eq!(
+        w.place_bet(1, bettor, 2, 0, 1).unwrap_err(),
+        err(ErrorCode::OutcomeCapExceeded)
+    );
+    assert_eq!(
+        w.place_bet(1, bettor, 2, 2, 201).unwrap_err(),
+        err(ErrorCode::OutcomeCapExceeded)
+    );
+
+    // The uncapped outcome and the rest of the capped one still take bets
+    w.place_bet(1, bettor, 2, 1, 5_000).unwrap();
+    w.place_bet(1, bettor, 3, 2, 200).unwrap();
+    assert_eq!(w.market(1).outcome_pools, vec![500, 5_000, 200]);
+}
+
+#[test]
+fn caps_cover_every_outcome() {
+    let mut w = World::new();
+    assert_eq!(
+        w.try_create_market(
+            1,
+            3_600,
+            MarketOptions {
+                outcome_count: 3,
+                outcome_caps: vec![500, 0],
+                ..options()
+            },
+        )
+        .unwrap_err(),
+        err(ErrorCode::InvalidOutcomeCount)
+    );
+}
+
+#[test]
+fn the_minimum_bet_rises_with_the_pool() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            min_bet_pool_bps: 1_000,
+            ..options()
+        },
+    );
+    let bettor = w.bettor(10_000);
+
+    // An empty pool leaves the flat minimum
+    w.place_bet(1, bettor, 0, 0, 1).unwrap();
+    w.place_bet(1, bettor, 1, 1, 999).unwrap();
+
+    // 10% of a 1_000 pool
+    assert_eq!(
+        w.place_bet(1, bettor, 2, 0, 99).unwrap_err(),
+        err(ErrorCode::BetTooSmall)
+    );
+    w.place_bet(1, bettor, 2, 0, 100).unwrap();
+
+    // and of the 1_100 pool after it
+    assert_eq!(
+        w.place_bet(1, bettor, 3, 1, 109).unwrap_err(),
+        err(ErrorCode::BetTooSmall)
+    );
+    w.place_bet(1, bettor, 3, 1, 110).unwrap();
+}
+
+fn create_campaign(w: &mut World, campaign_id: u64) -> Pubkey {
+    let owner = w.h.wallet();
+    w.h.send(
+        accounts::CreateCampaign {
+            campaign: campaign_pda(campaign_id),
+            owner,
+            system_program: system_program::ID,
+        },
+        ix::CreateCampaign { campaign_id },
+    )
+    .unwrap();
+    owner
+}
+
+#[test]
+fn campaign_volume_accumulates_across_bets() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let owner = create_campaign(&mut w, 7);
+    let first = w.bettor(1_000);
+    let second = w.bettor(1_000);
+
+    w.place_campaign_bet(1, first, 0, 0, 100, Some(7)).unwrap();
+    w.place_campaign_bet(1, second, 0, 1, 250, Some(7)).unwrap();
+    w.place_campaign_bet(2, first, 0, 0, 50, Some(7)).unwrap();
+    // Unattributed bets leave the counter alone
+    w.place_bet(2, second, 0, 0, 400).unwrap();
+
+    let campaign = w.h.get::<Campaign>(&campaign_pda(7));
+    assert_eq!(campaign.owner, owner);
+    assert_eq!(campaign.total_volume, 400);
+    assert_eq!(campaign.bet_count, 3);
+    let bet = w.h.get::<Bet>(&bet_pda(&market_pda(1), &second.0, 0));
+    assert_eq!(bet.campaign_id, Some(7));
+}
+
+#[test]
+fn the_campaign_account_must_match_the_id() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    create_campaign(&mut w, 7);
+    create_campaign(&mut w, 8);
+    let bettor = w.bettor(1_000);
+
+    let market = market_pda(1);
+    let mut place = |campaign: Option<Pubkey>| {
+        w.h.send(
+            accounts::PlaceBet {
+                market,
+                bet: bet_pda(&market, &bettor.0, 0),
+                market_vault: vault_pda(&market),
+                mint: w.mint,
+                config: config_pda(),
+                bettor_token_account: bettor.1,
+                user_profile: profile_pda(&bettor.0),
+                user_position: position_pda(&market, &bettor.0),
+                bettor: bettor.0,
+                campaign,
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+            },
+            ix::PlaceBet {
+                market_id: 1,
+                bet_index: 0,
+                bet_outcome: 0,
+                amount: 100,
+                campaign_id: Some(7),
+                min_expected_ratio: None,
+                whitelist_proof: None,
+            },
+        )
+    };
+    assert_eq!(
+        place(Some(campaign_pda(8))).unwrap_err(),
+        err(ErrorCode::CampaignMismatch)
+    );
+    // A tagged bet is always counted, so it can be taken off again
+    assert_eq!(place(None).unwrap_err(), err(ErrorCode::CampaignMismatch));
+    place(Some(campaign_pda(7))).unwrap();
+}
+
+#[test]
+fn the_profile_counts_bets_and_wins() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let other = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    w.place_bet(1, bettor, 1, 1, 50).unwrap();
+    w.place_bet(2, bettor, 0, 0, 200).unwrap();
+    w.place_bet(1, other, 0, 1, 150).unwrap();
+    w.place_bet(2, other, 0, 1, 200).unwrap();
+
+    w.h.warp(3_600);
+    for market_id in [1, 2] {
+        w.close_betting(market_id).unwrap();
+        w.resolve(market_id, 0).unwrap();
+    }
+    w.claim(1, bettor, 0).unwrap();
+    w.claim(2, bettor, 0).unwrap();
+
+    let profile: UserProfile = w.h.get(&profile_pda(&bettor.0));
+    assert_eq!(profile.bets_placed, 3);
+    assert_eq!(profile.total_wagered, 350);
+    assert_eq!(profile.bets_won, 2);
+    // 100 of 100 takes the 300 market; 200 of 200 takes the 400 one
+    assert_eq!(profile.total_won, 300 + 400);
+}
+
+#[test]
+fn withdrawn_stake_comes_off_every_counter() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    create_campaign(&mut w, 7);
+    let bettor = w.bettor(1_000);
+    w.place_campaign_bet(1, bettor, 0, 0, 300, Some(7)).unwrap();
+    w.place_campaign_bet(1, bettor, 1, 1, 100, Some(7)).unwrap();
+    w.increase_bet(1, bettor, 0, 50).unwrap();
+
+    w.reduce_bet(1, bettor, 0, 150).unwrap();
+    w.cancel_bet(1, bettor, 1).unwrap();
+    assert_eq!(w.h.token_balance(&bettor.1), 800);
+
+    let profile: UserProfile = w.h.get(&profile_pda(&bettor.0));
+    assert_eq!((profile.total_wagered, profile.bets_placed), (200, 1));
+    let campaign = w.h.get::<Campaign>(&campaign_pda(7));
+    assert_eq!((campaign.total_volume, campaign.bet_count), (200, 1));
+    let position: UserPosition = w.h.get(&position_pda(&market_pda(1), &bettor.0));
+    assert_eq!(position.outcome_stakes, vec![200, 0]);
+    assert_eq!((position.total_staked, position.bet_count), (200, 1));
+    assert_eq!(w.market(1).outcome_pools, vec![200, 0]);
+
+    // More than is staked cannot come off
+    assert_eq!(
+        w.reduce_bet(1, bettor, 0, 200).unwrap_err(),
+        err(ErrorCode::InvalidBetAmount)
+    );
+}
+
+fn place_delegated(
+    w: &mut World,
+    bettor: (Pubkey, Pubkey),
+    relayer: Pubkey,
+    amount: u64,
+) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::PlaceBetDelegated {
+            market,
+            bet: bet_pda(&market, &bettor.0, 0),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            config: config_pda(),
+            bettor_token_account: bettor.1,
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            relayer,
+            campaign: None,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::PlaceBetDelegated {
+            market_id: 1,
+            bet_index: 0,
+            bet_outcome: 0,
+            amount,
+            campaign_id: None,
+        },
+    )
+}
+
+#[test]
+fn a_relayer_submits_a_delegated_bet() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let relayer = w.h.wallet();
+    let vault = vault_pda(&market_pda(1));
+    w.h.approve(bettor.1, vault, 250);
+
+    let bettor_lamports = w.h.lamports(&bettor.0);
+    let relayer_lamports = w.h.lamports(&relayer);
+    place_delegated(&mut w, bettor, relayer, 250).unwrap();
+    assert_eq!(w.h.token_balance(&bettor.1), 750);
+    assert_eq!(w.h.token_balance(&vault), 250);
+    assert_eq!(w.market(1).outcome_pools, vec![250, 0]);
+
+    // The relayer pays the rent
+    assert_eq!(w.h.lamports(&bettor.0), bettor_lamports);
+    assert!(w.h.lamports(&relayer) < relayer_lamports);
+    let market = market_pda(1);
+    assert_eq!(w.h.get::<Bet>(&bet_pda(&market, &bettor.0, 0)).amount, 250);
+}
+
+#[test]
+fn the_delegation_must_be_to_the_vault_for_the_exact_amount() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let relayer = w.h.wallet();
+    let vault = vault_pda(&market_pda(1));
+
+    assert_eq!(
+        place_delegated(&mut w, bettor, relayer, 250).unwrap_err(),
+        err(ErrorCode::InvalidDelegation)
+    );
+    w.h.approve(bettor.1, relayer, 250);
+    assert_eq!(
+        place_delegated(&mut w, bettor, relayer, 250).unwrap_err(),
+        err(ErrorCode::InvalidDelegation)
+    );
+    w.h.approve(bettor.1, vault, 300);
+    assert_eq!(
+        place_delegated(&mut w, bettor, relayer, 250).unwrap_err(),
+        err(ErrorCode::InvalidDelegation)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+}
+
+/// Places a bet on market 1 with the optional arguments `World::place_bet`
+/// leaves out.
+fn place_with(
+    w: &mut World,
+    bettor: (Pubkey, Pubkey),
+    bet_index: u64,
+    outcome: u8,
+    amount: u64,
+    min_expected_ratio: Option<u64>,
+    whitelist_proof: Option<Vec<[u8; 32]>>,
+) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::PlaceBet {
+            market,
+            bet: bet_pda(&market, &bettor.0, bet_index),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            config: config_pda(),
+            bettor_token_account: bettor.1,
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            campaign: None,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::PlaceBet {
+            market_id: 1,
+            bet_index,
+            bet_outcome: outcome,
+            amount,
+            campaign_id: None,
+            min_expected_ratio,
+            whitelist_proof,
+        },
+    )
+}
+
+#[test]
+fn bets_are_refused_below_their_minimum_ratio() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let (long, crowd) = (w.bettor(100), w.bettor(900));
+    w.place_bet(1, long, 0, 0, 100).unwrap();
+    w.place_bet(1, crowd, 0, 1, 900).unwrap();
+    let bettor = w.bettor(1_000);
+
+    // 100 more on the crowded side would win (1_000 + 100) / (900 + 100)
+    assert_eq!(w.market(1).expected_ratio_bps(1, 100).unwrap(), 11_000);
+    assert_eq!(
+        place_with(&mut w, bettor, 0, 1, 100, Some(11_001), None).unwrap_err(),
+        err(ErrorCode::SlippageExceeded)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+    place_with(&mut w, bettor, 0, 1, 100, Some(11_000), None).unwrap();
+
+    // The long side pays (1_100 + 100) / (100 + 100)
+    place_with(&mut w, bettor, 1, 0, 100, Some(60_000), None).unwrap();
+    let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &bettor.0, 1));
+    assert_eq!((bet.side_pool_at_bet, bet.total_pool_at_bet), (200, 1_200));
+}
+
+fn dry_run(
+    w: &mut World,
+    market_id: u64,
+    bettor: Pubkey,
+    outcome: u8,
+    amount: u64,
+    min_expected_ratio: Option<u64>,
+) -> u32 {
+    let data =
+        w.h.send(
+            accounts::DryRunBet {
+                market: market_pda(market_id),
+                config: config_pda(),
+            },
+            ix::DryRunBet {
+                market_id,
+                bettor,
+                outcome,
+                amount,
+                min_expected_ratio,
+                whitelist_proof: None,
+            },
+        )
+        .unwrap();
+    u32::from_le_bytes(data.try_into().unwrap())
+}
+
+#[test]
+fn dry_run_reports_the_error_a_bet_would_hit() {
+    let mut w = World::new();
+    let authority = w.authority;
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            outcome_caps: vec![500, 0],
+            max_bet_amount: Some(1_000),
+            authority_cannot_bet: true,
+            betting_cutoff_offset: 600,
+            ..options()
+        },
+    );
+    let bettor = Pubkey::new_unique();
+    let code = |code: ErrorCode| u32::from(code);
+
+    assert_eq!(dry_run(&mut w, 1, bettor, 0, 100, None), 0);
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 0, None),
+        code(ErrorCode::BetTooSmall)
+    );
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 1, 1_001, None),
+        code(ErrorCode::BetTooLarge)
+    );
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 2, 100, None),
+        code(ErrorCode::InvalidOutcome)
+    );
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 501, None),
+        code(ErrorCode::OutcomeCapExceeded)
+    );
+    assert_eq!(
+        dry_run(&mut w, 1, authority, 0, 100, None),
+        code(ErrorCode::AuthorityCannotBet)
+    );
+    // Alone in the pool a bet gets its stake back: 100%, short of 150%
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 100, Some(15_000)),
+        code(ErrorCode::SlippageExceeded)
+    );
+
+    // Nothing was staked by any of the dry runs
+    assert_eq!(w.market(1).total_pool(), 0);
+
+    // Pausing the program stops every bet
+    let admin = w.admin;
+    let set_paused = |w: &mut World, paused: bool| {
+        w.h.send(
+            accounts::SetPaused {
+                config: config_pda(),
+                admin,
+            },
+            ix::SetPaused { paused },
+        )
+        .unwrap();
+    };
+    set_paused(&mut w, true);
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 100, None),
+        code(ErrorCode::ProgramPaused)
+    );
+    set_paused(&mut w, false);
+
+    // Inside the cutoff, then past the end
+    w.h.warp(3_000);
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 100, None),
+        code(ErrorCode::BettingClosed)
+    );
+    w.h.warp(600);
+    assert_eq!(
+        dry_run(&mut w, 1, bettor, 0, 100, None),
+        code(ErrorCode::MarketExpired)
+    );
+}
+
+#[test]
+fn a_hedge_places_both_legs_and_links_them() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let bettor = w.bettor(1_000);
+    w.hedge(bettor, leg(0, 100), leg(1, 300)).unwrap();
+
+    let first_bet = bet_pda(&market_pda(1), &bettor.0, 0);
+    let second_bet = bet_pda(&market_pda(2), &bettor.0, 0);
+    assert_eq!(w.h.get::<Bet>(&first_bet).amount, 100);
+    assert_eq!(w.h.get::<Bet>(&second_bet).amount, 300);
+    assert_eq!(w.market(1).outcome_pools, vec![100, 0]);
+    assert_eq!(w.market(2).outcome_pools, vec![0, 300]);
+    assert_eq!(w.h.token_balance(&bettor.1), 600);
+
+    let hedge: Hedge =
+        w.h.get(&pda(&[b"hedge", first_bet.as_ref(), second_bet.as_ref()]));
+    assert_eq!(hedge.bettor, bettor.0);
+    assert_eq!((hedge.first_market_id, hedge.second_market_id), (1, 2));
+    assert_eq!((hedge.first.outcome, hedge.second.outcome), (0, 1));
+}
+
+#[test]
+fn a_failing_leg_rolls_back_the_hedge() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+
+    // The first leg is funded, the second is not
+    let bettor = w.bettor(150);
+    assert!(w.hedge(bettor, leg(0, 100), leg(1, 100)).is_err());
+    assert_eq!(w.h.token_balance(&bettor.1), 150);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &bettor.0, 0)));
+    assert_eq!(w.market(1).total_pool(), 0);
+
+    // A leg that fails validation stops the hedge before any transfer
+    let bettor = w.bettor(1_000);
+    assert_eq!(
+        w.hedge(bettor, leg(0, 100), leg(2, 100)).unwrap_err(),
+        err(ErrorCode::InvalidOutcome)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+}
+
+/// Market 1 whose fee rises from 1% at creation to 5% at the close.
+fn rising_fee(w: &mut World) {
+    w.create_market(
+        1,
+        4_000,
+        MarketOptions {
+            fee_bps: 100,
+            closing_fee_bps: Some(500),
+            ..options()
+        },
+    );
+}
+
+#[test]
+fn bets_record_the_fee_rate_when_placed() {
+    let mut w = World::new();
+    rising_fee(&mut w);
+    let early = w.bettor(1_000);
+    let late = w.bettor(1_000);
+    w.place_bet(1, early, 0, 0, 100).unwrap();
+    w.h.warp(3_000);
+    w.place_bet(1, late, 0, 0, 100).unwrap();
+
+    // Three quarters of the way to the close the rate is three quarters up
+    let market = market_pda(1);
+    assert_eq!(
+        w.h.get::<Bet>(&bet_pda(&market, &early.0, 0)).fee_bps,
+        Some(100)
+    );
+    assert_eq!(
+        w.h.get::<Bet>(&bet_pda(&market, &late.0, 0)).fee_bps,
+        Some(400)
+    );
+}
+
+#[test]
+fn a_top_up_keeps_the_recorded_fee_or_is_refused() {
+    let mut w = World::new();
+    w.init_treasury();
+    rising_fee(&mut w);
+    let early = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, early, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    // Still at the rate the bet recorded
+    w.increase_bet(1, early, 0, 50).unwrap();
+
+    // Topped up late, the stake would pay the early 1% on late money
+    w.h.warp(3_000);
+    assert_eq!(
+        w.increase_bet(1, early, 0, 50).unwrap_err(),
+        err(ErrorCode::FeeRateChanged)
+    );
+    let bet = w.h.get::<Bet>(&bet_pda(&market_pda(1), &early.0, 0));
+    assert_eq!((bet.amount, bet.fee_bps), (150, Some(100)));
+
+    // The claim pays the recorded 1% on the 250 payout
+    w.h.warp(1_000);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, early, 0).unwrap();
+    assert_eq!(w.h.token_balance(&early.1), 850 + 250 - 2);
+}
+
+#[test]
+fn one_wallet_holds_and_claims_separate_bets() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    for (index, amount) in [(0, 100), (1, 200), (2, 300)] {
+        w.place_bet(1, bettor, index, 0, amount).unwrap();
+    }
+    w.place_bet(1, no, 0, 1, 600).unwrap();
+
+    let market = market_pda(1);
+    for (index, amount) in [(0, 100), (1, 200), (2, 300)] {
+        let bet: Bet = w.h.get(&bet_pda(&market, &bettor.0, index));
+        assert_eq!((bet.bet_index, bet.amount), (index, amount));
+    }
+    assert_eq!(w.market(1).outcome_pools, vec![600, 600]);
+
+    // Each bet is claimed by its own index and pays its share of the pool
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    let mut balance = 400;
+    for (index, amount) in [(0, 100), (1, 200), (2, 300)] {
+        w.claim(1, bettor, index).unwrap();
+        balance += amount * 2;
+        assert_eq!(w.h.token_balance(&bettor.1), balance);
+        assert!(!w.h.exists(&bet_pda(&market, &bettor.0, index)));
+    }
+    assert_eq!(w.claim(1, bettor, 0).unwrap_err(), err_missing());
+}
+
+#[test]
+fn cancel_returns_the_stake_and_closes_the_bet() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 250).unwrap();
+    w.place_bet(1, bettor, 1, 1, 50).unwrap();
+
+    let bet = bet_pda(&market_pda(1), &bettor.0, 0);
+    let rent = w.h.lamports(&bet);
+    let lamports = w.h.lamports(&bettor.0);
+    w.cancel_bet(1, bettor, 0).unwrap();
+    assert_eq!(w.h.token_balance(&bettor.1), 950);
+    assert!(!w.h.exists(&bet));
+    assert_eq!(w.h.lamports(&bettor.0), lamports + rent);
+    let cancelled = &w.h.events::<BetCancelled>()[0];
+    assert_eq!((cancelled.bet_index, cancelled.amount), (0, 250));
+
+    let market = w.market(1);
+    assert_eq!(market.outcome_pools, vec![0, 50]);
+    assert_eq!(market.outcome_bet_counts, vec![0, 1]);
+    assert_eq!(market.bettor_count, 1);
+
+    w.h.warp(3_600);
+    assert_eq!(
+        w.cancel_bet(1, bettor, 1).unwrap_err(),
+        err(ErrorCode::MarketExpired)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 950);
+}
+
+#[test]
+fn a_three_way_market_pays_the_winning_outcome() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            outcome_count: 3,
+            ..options()
+        },
+    );
+    let first = w.bettor(1_000);
+    let second = w.bettor(1_000);
+    let third = w.bettor(1_000);
+    w.place_bet(1, first, 0, 0, 100).unwrap();
+    w.place_bet(1, second, 0, 1, 200).unwrap();
+    w.place_bet(1, third, 0, 2, 300).unwrap();
+    assert_eq!(
+        w.place_bet(1, first, 1, 3, 100).unwrap_err(),
+        err(ErrorCode::InvalidOutcome)
+    );
+    assert_eq!(w.market(1).outcome_pools, vec![100, 200, 300]);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    assert_eq!(w.resolve(1, 3).unwrap_err(), err(ErrorCode::InvalidOutcome));
+    w.resolve(1, 1).unwrap();
+
+    // The single winner takes the whole 600 pool
+    w.claim(1, second, 0).unwrap();
+    assert_eq!(w.h.token_balance(&second.1), 1_400);
+    assert_eq!(w.claim(1, first, 0).unwrap_err(), err(ErrorCode::LosingBet));
+    assert_eq!(w.claim(1, third, 0).unwrap_err(), err(ErrorCode::LosingBet));
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn outcome_counts_run_from_two_to_eight() {
+    let mut w = World::new();
+    for (market_id, outcome_count) in [(1, 1), (2, 9)] {
+        assert_eq!(
+            w.try_create_market(
+                market_id,
+                3_600,
+                MarketOptions {
+                    outcome_count,
+                    ..options()
+                },
+            )
+            .unwrap_err(),
+            err(ErrorCode::InvalidOutcomeCount)
+        );
+    }
+    w.create_market(
+        3,
+        3_600,
+        MarketOptions {
+            outcome_count: 8,
+            ..options()
+        },
+    );
+    assert_eq!(w.market(3).outcome_pools, vec![0; 8]);
+}
+
+fn timeout_refund(
+    w: &mut World,
+    market_id: u64,
+    bettor: (Pubkey, Pubkey),
+    bet_index: u64,
+) -> Result {
+    let market = market_pda(market_id);
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::ClaimTimeoutRefund {
+            market,
+            bet: bet_pda(&market, &bettor.0, bet_index),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            bettor_token_account: bettor.1,
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            caller,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimTimeoutRefund {
+            market_id,
+            bet_index,
+        },
+    )
+}
+
+#[test]
+fn timeout_refund_voids_an_unresolved_market() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 400).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    // Refused while the authority still has time to resolve
+    w.h.warp(3_600);
+    assert_eq!(
+        timeout_refund(&mut w, 1, yes, 0).unwrap_err(),
+        err(ErrorCode::ResolutionDeadlineNotReached)
+    );
+    let deadline = w.market(1).resolution_deadline;
+    w.h.set_time(deadline);
+    assert_eq!(
+        timeout_refund(&mut w, 1, yes, 0).unwrap_err(),
+        err(ErrorCode::ResolutionDeadlineNotReached)
+    );
+    assert_eq!(w.h.token_balance(&yes.1), 600);
+
+    w.h.set_time(deadline + 1);
+    timeout_refund(&mut w, 1, yes, 0).unwrap();
+    assert!(w.market(1).refund_only);
+    assert_eq!(w.h.token_balance(&yes.1), 1_000);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &yes.0, 0)));
+    let refund = &w.h.events::<TimeoutRefund>()[0];
+    assert_eq!((refund.bettor, refund.amount), (yes.0, 400));
+    let position: UserPosition = w.h.get(&position_pda(&market_pda(1), &yes.0));
+    assert_eq!(position.total_claimed, 400);
+
+    // Once voided, the rest refund too
+    timeout_refund(&mut w, 1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 1_000);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn bets_are_capped_at_the_market_maximum() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            max_bet_amount: Some(500),
+            ..options()
+        },
+    );
+    w.create_market(2, 3_600, options());
+    let bettor = w.bettor(100_000);
+
+    w.place_bet(1, bettor, 0, 0, 500).unwrap();
+    assert_eq!(
+        w.place_bet(1, bettor, 1, 0, 501).unwrap_err(),
+        err(ErrorCode::BetTooLarge)
+    );
+    assert_eq!(w.market(1).outcome_pools, vec![500, 0]);
+
+    // Without a cap any amount goes
+    w.place_bet(2, bettor, 0, 1, 99_000).unwrap();
+    assert_eq!(w.market(2).outcome_pools, vec![0, 99_000]);
+
+    // A cap below the minimum bet is refused at creation
+    assert_eq!(
+        w.try_create_market(
+            3,
+            3_600,
+            MarketOptions {
+                max_bet_amount: Some(0),
+                ..options()
+            },
+        )
+        .unwrap_err(),
+        err(ErrorCode::InvalidBetAmount)
+    );
+}
+
+#[test]
+fn pools_book_what_reaches_the_vault() {
+    // 1% of every transfer is withheld by the fee mint
+    for (mut w, received, paid) in [
+        (World::new(), 1_000, 2_000),
+        (World::with_transfer_fee(100), 990, 1_960),
+    ] {
+        w.create_market(1, 3_600, options());
+        let yes = w.bettor(10_000);
+        let no = w.bettor(10_000);
+        w.place_bet(1, yes, 0, 0, 1_000).unwrap();
+        w.place_bet(1, no, 0, 1, 1_000).unwrap();
+
+        let vault = vault_pda(&market_pda(1));
+        let market = w.market(1);
+        assert_eq!(market.outcome_pools, vec![received, received]);
+        assert_eq!(w.h.token_balance(&vault), 2 * received);
+        let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &yes.0, 0));
+        assert_eq!(bet.amount, received);
+
+        // The winner is paid the whole vault, less the fee on the way out:
+        // 1% of 1_980 rounds up to 20
+        w.h.warp(3_600);
+        w.close_betting(1).unwrap();
+        w.resolve(1, 0).unwrap();
+        w.claim(1, yes, 0).unwrap();
+        assert_eq!(w.h.token_balance(&vault), 0);
+        assert_eq!(w.h.token_balance(&yes.1), 9_000 + paid);
+    }
+}
+
+fn extend(w: &mut World, authority: Pubkey, new_end_time: i64) -> Result {
+    w.h.send(
+        accounts::ExtendMarket {
+            market: market_pda(1),
+            authority,
+        },
+        ix::ExtendMarket {
+            market_id: 1,
+            new_end_time,
+        },
+    )
+}
+
+#[test]
+fn end_times_are_held_within_the_maximum_duration() {
+    let mut w = World::new();
+    assert_eq!(
+        w.try_create_market(2, MAX_MARKET_DURATION + 1, options())
+            .unwrap_err(),
+        err(ErrorCode::EndTimeTooFar)
+    );
+    w.create_market(2, MAX_MARKET_DURATION, options());
+
+    // Extensions are bounded from the moment they are made
+    let now = w.h.now;
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            resolution_deadline: now + 2 * MAX_MARKET_DURATION,
+            ..options()
+        },
+    );
+    let authority = w.authority;
+    w.h.warp(60);
+    let limit = w.h.now + MAX_MARKET_DURATION;
+    assert_eq!(
+        extend(&mut w, authority, limit + 1).unwrap_err(),
+        err(ErrorCode::EndTimeTooFar)
+    );
+    extend(&mut w, authority, limit).unwrap();
+    assert_eq!(w.market(1).end_time, limit);
+}
+
+#[test]
+fn extending_an_open_market_keeps_betting_open() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let end_time = w.market(1).end_time;
+    let authority = w.authority;
+
+    let stranger = w.h.wallet();
+    assert_eq!(
+        extend(&mut w, stranger, end_time + 3_600).unwrap_err(),
+        err(ErrorCode::UnauthorizedResolver)
+    );
+    assert_eq!(
+        extend(&mut w, authority, end_time).unwrap_err(),
+        err(ErrorCode::InvalidExtension)
+    );
+    extend(&mut w, authority, end_time + 3_600).unwrap();
+    let extended = &w.h.events::<MarketExtended>()[0];
+    assert_eq!(
+        (extended.old_end_time, extended.new_end_time),
+        (end_time, end_time + 3_600)
+    );
+
+    // Past the old end, bets are still taken until the new one
+    w.h.set_time(end_time + 60);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    assert_eq!(
+        w.close_betting(1).unwrap_err(),
+        err(ErrorCode::MarketNotExpired)
+    );
+
+    // Once expired and closed, no further extension
+    w.h.set_time(end_time + 3_600);
+    assert_eq!(
+        extend(&mut w, authority, end_time + 7_200).unwrap_err(),
+        err(ErrorCode::MarketExpired)
+    );
+    w.close_betting(1).unwrap();
+    assert_eq!(
+        extend(&mut w, authority, end_time + 7_200).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+}
+
+#[test]
+fn a_topped_up_bet_claims_as_one_bet() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let topped_up = w.bettor(1_000);
+    let single = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, topped_up, 0, 0, 100).unwrap();
+    w.place_bet(1, single, 0, 0, 150).unwrap();
+    w.place_bet(1, no, 0, 1, 300).unwrap();
+
+    assert_eq!(
+        w.increase_bet(1, topped_up, 1, 50).unwrap_err(),
+        err(ErrorCode::OutcomeMismatch)
+    );
+    w.increase_bet(1, topped_up, 0, 50).unwrap();
+    let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &topped_up.0, 0));
+    assert_eq!(bet.amount, 150);
+    assert_eq!(w.market(1).outcome_pools, vec![300, 300]);
+
+    w.h.warp(3_600);
+    assert_eq!(
+        w.increase_bet(1, topped_up, 0, 50).unwrap_err(),
+        err(ErrorCode::MarketExpired)
+    );
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, topped_up, 0).unwrap();
+    w.claim(1, single, 0).unwrap();
+    assert_eq!(w.h.token_balance(&topped_up.1), 1_150);
+    assert_eq!(w.h.token_balance(&single.1), 1_150);
+}
+
+#[test]
+fn stakes_are_fixed_from_the_betting_cutoff() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            betting_cutoff_offset: 600,
+            ..options()
+        },
+    );
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 200).unwrap();
+    w.place_bet(1, bettor, 1, 1, 200).unwrap();
+
+    // One second inside the cutoff everything still moves
+    w.h.warp(2_999);
+    w.place_bet(1, bettor, 2, 0, 100).unwrap();
+    w.reduce_bet(1, bettor, 0, 50).unwrap();
+    w.cancel_bet(1, bettor, 2).unwrap();
+
+    // From the cutoff on, nothing does
+    w.h.warp(1);
+    assert_eq!(
+        w.place_bet(1, bettor, 2, 0, 100).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(
+        w.reduce_bet(1, bettor, 0, 50).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(
+        w.cancel_bet(1, bettor, 1).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(w.market(1).outcome_pools, vec![150, 200]);
+    assert_eq!(w.h.token_balance(&bettor.1), 650);
+}
+
+#[test]
+fn the_cutoff_must_fall_inside_the_market() {
+    let mut w = World::new();
+    for betting_cutoff_offset in [-1, 3_600] {
+        let result = w.try_create_market(
+            1,
+            3_600,
+            MarketOptions {
+                betting_cutoff_offset,
+                ..options()
+            },
+        );
+        assert_eq!(result.unwrap_err(), err(ErrorCode::InvalidBettingCutoff));
+    }
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            betting_cutoff_offset: 3_599,
+            ..options()
+        },
+    );
+}
+
+fn update_min_bet(w: &mut World, authority: Pubkey, min_bet_amount: u64) -> Result {
+    w.h.send(
+        accounts::UpdateMinBet {
+            market: market_pda(1),
+            authority,
+        },
+        ix::UpdateMinBet {
+            market_id: 1,
+            min_bet_amount,
+        },
+    )
+}
+
+#[test]
+fn the_minimum_bet_is_fixed_once_betting_starts() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let authority = w.authority;
+    let stranger = w.h.wallet();
+    assert_eq!(
+        update_min_bet(&mut w, stranger, 100).unwrap_err(),
+        err(ErrorCode::UnauthorizedResolver)
+    );
+    assert_eq!(
+        update_min_bet(&mut w, authority, 0).unwrap_err(),
+        err(ErrorCode::InvalidBetAmount)
+    );
+
+    update_min_bet(&mut w, authority, 100).unwrap();
+    assert_eq!(w.market(1).min_bet_amount, 100);
+    assert_eq!(w.h.events::<MinBetUpdated>()[0].min_bet_amount, 100);
+    let bettor = w.bettor(1_000);
+    assert_eq!(
+        w.place_bet(1, bettor, 0, 0, 99).unwrap_err(),
+        err(ErrorCode::BetTooSmall)
+    );
+
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    assert_eq!(
+        update_min_bet(&mut w, authority, 50).unwrap_err(),
+        err(ErrorCode::MarketHasBets)
+    );
+    assert_eq!(w.market(1).min_bet_amount, 100);
+}
+
+#[test]
+fn a_bet_can_be_reduced_down_to_the_minimum() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let authority = w.authority;
+    update_min_bet(&mut w, authority, 100).unwrap();
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 200).unwrap();
+
+    w.reduce_bet(1, bettor, 0, 50).unwrap();
+    let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &bettor.0, 0));
+    assert_eq!(bet.amount, 150);
+    assert_eq!(w.market(1).outcome_pools, vec![150, 0]);
+    assert_eq!(w.h.token_balance(&bettor.1), 850);
+    let reduced = &w.h.events::<BetReduced>()[0];
+    assert_eq!((reduced.withdrawn, reduced.remaining), (50, 150));
+
+    // 51 more would leave 99 under the 100 minimum; all of it is a cancel
+    assert_eq!(
+        w.reduce_bet(1, bettor, 0, 51).unwrap_err(),
+        err(ErrorCode::WouldGoBelowMinimum)
+    );
+    for amount in [0, 150] {
+        assert_eq!(
+            w.reduce_bet(1, bettor, 0, amount).unwrap_err(),
+            err(ErrorCode::InvalidBetAmount)
+        );
+    }
+    w.reduce_bet(1, bettor, 0, 50).unwrap();
+
+    w.h.warp(3_600);
+    assert_eq!(
+        w.reduce_bet(1, bettor, 0, 1).unwrap_err(),
+        err(ErrorCode::MarketExpired)
+    );
+}
+
+#[test]
+fn the_authority_can_be_barred_from_its_own_market() {
+    let mut w = World::new();
+    let resolver = w.h.wallet();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            authority_cannot_bet: true,
+            resolvers: vec![resolver],
+            resolver_threshold: 1,
+            ..options()
+        },
+    );
+    w.create_market(2, 3_600, options());
+    let authority = w.authority;
+    let authority = (
+        authority,
+        w.h.create_token_account(w.mint, authority, 1_000),
+    );
+    let resolver = (resolver, w.h.create_token_account(w.mint, resolver, 1_000));
+    let bettor = w.bettor(1_000);
+
+    for insider in [authority, resolver] {
+        assert_eq!(
+            w.place_bet(1, insider, 0, 0, 100).unwrap_err(),
+            err(ErrorCode::AuthorityCannotBet)
+        );
+    }
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+
+    // Without the flag the authority bets like anyone else
+    w.place_bet(2, authority, 0, 0, 100).unwrap();
+    assert_eq!(w.h.token_balance(&authority.1), 900);
+}
+
+/// The parent of two Merkle nodes, hashed in sorted order.
+fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&low, &high]).to_bytes()
+}
+
+#[test]
+fn a_whitelisted_market_takes_only_proven_members() {
+    let mut w = World::new();
+    let members = [w.bettor(1_000), w.bettor(1_000), w.bettor(1_000)];
+    let outsider = w.bettor(1_000);
+    let leaves = members.map(|member| whitelist_leaf(&member.0));
+    let left = merkle_parent(leaves[0], leaves[1]);
+    let root = merkle_parent(left, leaves[2]);
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            whitelist_root: Some(root),
+            ..options()
+        },
+    );
+
+    place_with(
+        &mut w,
+        members[0],
+        0,
+        0,
+        100,
+        None,
+        Some(vec![leaves[1], leaves[2]]),
+    )
+    .unwrap();
+    place_with(&mut w, members[2], 0, 1, 100, None, Some(vec![left])).unwrap();
+
+    // A proof only fits the leaf it was built for
+    let refused = [
+        (members[1], None),
+        (members[1], Some(vec![leaves[0]])),
+        (outsider, Some(vec![leaves[1], leaves[2]])),
+        (outsider, Some(vec![left])),
+    ];
+    for (bettor, proof) in refused {
+        assert_eq!(
+            place_with(&mut w, bettor, 0, 0, 100, None, proof).unwrap_err(),
+            err(ErrorCode::NotWhitelisted)
+        );
+    }
+    assert_eq!(w.market(1).outcome_pools, vec![100, 100]);
+
+    // A public market needs no proof
+    w.create_market(2, 3_600, options());
+    w.place_bet(2, outsider, 0, 0, 100).unwrap();
+}
+
+#[test]
+fn a_full_market_refuses_the_next_bet() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            max_bettors: Some(2),
+            ..options()
+        },
+    );
+    let first = w.bettor(1_000);
+    let second = w.bettor(1_000);
+    let third = w.bettor(1_000);
+    w.place_bet(1, first, 0, 0, 100).unwrap();
+    w.place_bet(1, second, 0, 1, 100).unwrap();
+    assert_eq!(w.market(1).bettor_count, 2);
+
+    // The cap counts bets, so a second bet from a wallet takes a slot too
+    for (bettor, bet_index) in [(third, 0), (first, 1)] {
+        assert_eq!(
+            w.place_bet(1, bettor, bet_index, 0, 100).unwrap_err(),
+            err(ErrorCode::MarketFull)
+        );
+    }
+    // Topping up an existing bet takes no new slot
+    w.increase_bet(1, first, 0, 50).unwrap();
+
+    // A cancellation frees its slot
+    w.cancel_bet(1, second, 0).unwrap();
+    assert_eq!(w.market(1).bettor_count, 1);
+    w.place_bet(1, third, 0, 1, 100).unwrap();
+    assert_eq!(w.market(1).bettor_count, 2);
+}
+
+#[test]
+fn a_position_aggregates_separate_bets_on_both_sides() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    let other = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 200).unwrap();
+    w.place_bet(1, bettor, 1, 1, 100).unwrap();
+    w.place_bet(1, bettor, 2, 0, 50).unwrap();
+    w.place_bet(1, other, 0, 1, 150).unwrap();
+
+    // Each bet has its own account under its index
+    let market = market_pda(1);
+    for (bet_index, amount) in [(0, 200), (1, 100), (2, 50)] {
+        let bet: Bet = w.h.get(&bet_pda(&market, &bettor.0, bet_index));
+        assert_eq!((bet.bet_index, bet.amount), (bet_index, amount));
+    }
+    // An index cannot be placed twice
+    assert_eq!(w.place_bet(1, bettor, 1, 0, 10).unwrap_err(), err_in_use());
+
+    let key = position_pda(&market, &bettor.0);
+    let position: UserPosition = w.h.get(&key);
+    assert_eq!(position.bettor, bettor.0);
+    assert_eq!(position.market_id, 1);
+    assert_eq!(position.outcome_stakes, vec![250, 100]);
+    assert_eq!(position.total_staked, 350);
+    assert_eq!(position.bet_count, 3);
+
+    w.cancel_bet(1, bettor, 2).unwrap();
+    let position: UserPosition = w.h.get(&key);
+    assert_eq!(position.outcome_stakes, vec![200, 100]);
+    assert_eq!(position.total_staked, 300);
+    assert_eq!(position.bet_count, 2);
+
+    // 200 of the 200 YES stake takes the whole 450 pool
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, bettor, 0).unwrap();
+    assert_eq!(w.h.get::<UserPosition>(&key).total_claimed, 450);
+}
+
+#[test]
+fn a_top_up_adds_to_the_bet_in_place() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            max_bet_amount: Some(300),
+            log_level: LogLevel::Full,
+            ..options()
+        },
+    );
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+
+    w.increase_bet(1, bettor, 0, 150).unwrap();
+    let increased = &w.h.events::<BetIncreased>()[0];
+    assert_eq!(
+        (
+            increased.bettor,
+            increased.bet_index,
+            increased.amount,
+            increased.total_amount
+        ),
+        (bettor.0, 0, 150, 250)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 750);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 250);
+
+    // Still one bet, on one position
+    let market = w.market(1);
+    assert_eq!((market.bettor_count, market.outcome_pools[0]), (1, 250));
+    let position: UserPosition = w.h.get(&position_pda(&market_pda(1), &bettor.0));
+    assert_eq!((position.bet_count, position.total_staked), (1, 250));
+    let profile: UserProfile = w.h.get(&profile_pda(&bettor.0));
+    assert_eq!((profile.bets_placed, profile.total_wagered), (1, 250));
+
+    // The combined stake is held to the maximum bet
+    assert_eq!(
+        w.increase_bet(1, bettor, 0, 51).unwrap_err(),
+        err(ErrorCode::BetTooLarge)
+    );
+    w.increase_bet(1, bettor, 0, 50).unwrap();
+
+    // A wallet without a bet has nothing to top up
+    let stranger = w.bettor(1_000);
+    assert_eq!(
+        w.increase_bet(1, stranger, 0, 50).unwrap_err(),
+        err_missing()
+    );
+}
@@ -0,0 +1,1376 @@
+mod common;
+
+use anchor_lang::prelude::Rent;
+use anchor_lang::{system_program, AccountSerialize, Space};
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, Bet, BetsRefunded, CharityDonation, DustSwept, ErrorCode,
+    FeeCollected, FeeSplit, LiquiditySeeded, MarketCancelled, MarketOptions, PayoutsPrecomputed,
+    SettlementReport, Treasury, WinningsClaimed, CLAIM_APPROVAL_TIMEOUT,
+};
+
+fn claim_to_alternate(
+    w: &mut World,
+    market_id: u64,
+    bettor: Pubkey,
+    recipient: Pubkey,
+    claimer: Pubkey,
+) -> Result {
+    let market = market_pda(market_id);
+    w.h.send(
+        accounts::ClaimToAlternate {
+            market,
+            bet: bet_pda(&market, &bettor, 0),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            recipient_token_account: recipient,
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: None,
+            user_profile: profile_pda(&bettor),
+            user_position: position_pda(&market, &bettor),
+            bettor,
+            claimer,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimToAlternate {
+            market_id,
+            _bet_index: 0,
+        },
+    )
+}
+
+/// A resolved market where `yes` won 300 + 100 and `no` lost.
+fn resolved(w: &mut World) -> ((Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    (yes, no)
+}
+
+#[test]
+fn frozen_account_payout_is_redirected() {
+    let mut w = World::new();
+    let (yes, _) = resolved(&mut w);
+    w.h.freeze(yes.1);
+    assert!(w.claim(1, yes, 0).is_err());
+
+    let alternate = w.h.create_token_account(w.mint, yes.0, 0);
+    claim_to_alternate(&mut w, 1, yes.0, alternate, yes.0).unwrap();
+    assert_eq!(w.h.token_balance(&alternate), 400);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &yes.0, 0)));
+}
+
+#[test]
+fn authority_can_redirect_on_the_bettors_behalf() {
+    let mut w = World::new();
+    let (yes, _) = resolved(&mut w);
+    let alternate = w.h.create_token_account(w.mint, yes.0, 0);
+
+    let authority = w.authority;
+    claim_to_alternate(&mut w, 1, yes.0, alternate, authority).unwrap();
+    assert_eq!(w.h.token_balance(&alternate), 400);
+}
+
+#[test]
+fn alternate_must_be_a_usable_account_of_the_bettor() {
+    let mut w = World::new();
+    let (yes, no) = resolved(&mut w);
+
+    // Someone else's account, even signed by the bettor
+    let theirs = w.h.create_token_account(w.mint, no.0, 0);
+    assert_eq!(
+        claim_to_alternate(&mut w, 1, yes.0, theirs, yes.0).unwrap_err(),
+        err(ErrorCode::UnauthorizedClaimer)
+    );
+
+    // A frozen one, or one in another mint
+    let frozen = w.h.create_token_account(w.mint, yes.0, 0);
+    w.h.freeze(frozen);
+    assert_eq!(
+        claim_to_alternate(&mut w, 1, yes.0, frozen, yes.0).unwrap_err(),
+        err(ErrorCode::TokenAccountUnusable)
+    );
+    let other_mint = w.h.create_mint(6);
+    let wrong_mint = w.h.create_token_account(other_mint, yes.0, 0);
+    assert_eq!(
+        claim_to_alternate(&mut w, 1, yes.0, wrong_mint, yes.0).unwrap_err(),
+        err(ErrorCode::TokenAccountUnusable)
+    );
+
+    // A stranger cannot submit the claim
+    let stranger = w.h.wallet();
+    let alternate = w.h.create_token_account(w.mint, yes.0, 0);
+    assert_eq!(
+        claim_to_alternate(&mut w, 1, yes.0, alternate, stranger).unwrap_err(),
+        err(ErrorCode::UnauthorizedClaimer)
+    );
+}
+
+/// Claims `yes`'s winning bet, signed by `signer`, into `recipient`.
+fn claim_into(w: &mut World, yes: (Pubkey, Pubkey), recipient: Pubkey, signer: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::ClaimWinnings {
+            market,
+            bet: bet_pda(&market, &yes.0, 0),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            bettor_token_account: yes.1,
+            recipient_token_account: Some(recipient),
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: None,
+            user_profile: profile_pda(&signer),
+            user_position: position_pda(&market, &signer),
+            bettor: signer,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimWinnings {
+            market_id: 1,
+            _bet_index: 0,
+        },
+    )
+}
+
+#[test]
+fn winnings_can_be_paid_into_another_account() {
+    let mut w = World::new();
+    let (yes, _) = resolved(&mut w);
+    let cold_wallet = w.h.wallet();
+    let recipient = w.h.create_token_account(w.mint, cold_wallet, 0);
+
+    // The bettor's signature is what authorizes the payout
+    let stranger = w.h.wallet();
+    assert_eq!(
+        claim_into(&mut w, yes, recipient, stranger).unwrap_err(),
+        err_anchor(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let other_mint = w.h.create_mint(6);
+    let wrong_mint = w.h.create_token_account(other_mint, cold_wallet, 0);
+    assert_eq!(
+        claim_into(&mut w, yes, wrong_mint, yes.0).unwrap_err(),
+        err(ErrorCode::MintMismatch)
+    );
+    assert_eq!(w.h.token_balance(&recipient), 0);
+
+    claim_into(&mut w, yes, recipient, yes.0).unwrap();
+    assert_eq!(w.h.token_balance(&recipient), 400);
+    assert_eq!(w.h.token_balance(&yes.1), 700);
+}
+
+fn claim_vested(w: &mut World, market_id: u64, bettor: (Pubkey, Pubkey)) -> Result {
+    let market = market_pda(market_id);
+    let bet = bet_pda(&market, &bettor.0, 0);
+    w.h.send(
+        accounts::ClaimVested {
+            market,
+            bet,
+            vesting: pda(&[b"vesting", bet.as_ref()]),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            bettor_token_account: bettor.1,
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: None,
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimVested {
+            market_id,
+            _bet_index: 0,
+        },
+    )
+}
+
+#[test]
+fn vested_winnings_release_proportionally() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            vesting_period: 1_000,
+            ..options()
+        },
+    );
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // The lump-sum path is closed on vesting markets
+    assert!(w.claim(1, yes, 0).is_err());
+
+    // A quarter of the way in, a quarter of the 400 payout is out
+    w.h.warp(250);
+    claim_vested(&mut w, 1, yes).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 700 + 100);
+    assert_eq!(
+        claim_vested(&mut w, 1, yes).unwrap_err(),
+        err(ErrorCode::NothingVested)
+    );
+
+    w.h.warp(250);
+    claim_vested(&mut w, 1, yes).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 700 + 200);
+
+    // Past the end the rest is released and the schedule closes
+    w.h.warp(10_000);
+    claim_vested(&mut w, 1, yes).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 700 + 400);
+    let bet = bet_pda(&market_pda(1), &yes.0, 0);
+    assert!(!w.h.exists(&pda(&[b"vesting", bet.as_ref()])));
+    assert!(!w.h.exists(&bet));
+}
+
+fn approve(w: &mut World, bettor: (Pubkey, Pubkey), authority: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::ApproveLargeClaim {
+            market,
+            bet: bet_pda(&market, &bettor.0, 0),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            bettor_token_account: bettor.1,
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: None,
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            authority,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ApproveLargeClaim {
+            market_id: 1,
+            _bet_index: 0,
+        },
+    )
+}
+
+/// A resolved market holding payouts over 500: `big` is owed 900, `small` 300.
+fn with_threshold(w: &mut World) -> ((Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            high_value_threshold: 500,
+            ..options()
+        },
+    );
+    let big = w.bettor(1_000);
+    let small = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, big, 0, 0, 300).unwrap();
+    w.place_bet(1, small, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 800).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    (big, small)
+}
+
+#[test]
+fn claims_under_the_threshold_pay_instantly() {
+    let mut w = World::new();
+    let (_, small) = with_threshold(&mut w);
+    w.claim(1, small, 0).unwrap();
+    assert_eq!(w.h.token_balance(&small.1), 900 + 300);
+}
+
+#[test]
+fn large_claims_wait_for_approval() {
+    let mut w = World::new();
+    let (big, _) = with_threshold(&mut w);
+
+    // The first claim only holds the payout
+    w.claim(1, big, 0).unwrap();
+    assert_eq!(w.h.token_balance(&big.1), 700);
+    let bet = w.h.get::<Bet>(&bet_pda(&market_pda(1), &big.0, 0));
+    assert!(bet.claim_pending);
+    assert_eq!(
+        w.claim(1, big, 0).unwrap_err(),
+        err(ErrorCode::ClaimPendingApproval)
+    );
+
+    // Only the authority releases it
+    assert_eq!(
+        approve(&mut w, big, big.0).unwrap_err(),
+        err(ErrorCode::UnauthorizedResolver)
+    );
+    let authority = w.authority;
+    approve(&mut w, big, authority).unwrap();
+    assert_eq!(w.h.token_balance(&big.1), 700 + 900);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &big.0, 0)));
+}
+
+#[test]
+fn an_unapproved_hold_times_out() {
+    let mut w = World::new();
+    let (big, _) = with_threshold(&mut w);
+    w.claim(1, big, 0).unwrap();
+
+    w.h.warp(CLAIM_APPROVAL_TIMEOUT - 1);
+    assert_eq!(
+        w.claim(1, big, 0).unwrap_err(),
+        err(ErrorCode::ClaimPendingApproval)
+    );
+    w.h.warp(1);
+    w.claim(1, big, 0).unwrap();
+    assert_eq!(w.h.token_balance(&big.1), 700 + 900);
+}
+
+fn sweep_dust(w: &mut World, treasury_vault: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::SweepDust {
+            market,
+            market_vault: vault_pda(&market),
+            treasury: treasury_pda(),
+            treasury_vault,
+            mint: w.mint,
+            token_program: spl_token::ID,
+        },
+        ix::SweepDust { market_id: 1 },
+    )
+}
+
+#[test]
+fn the_payout_cap_engages_on_a_thin_winning_pool() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            max_payout_multiplier_bps: 20_000,
+            ..options()
+        },
+    );
+    let thin = w.bettor(1_000);
+    let thick = w.bettor(1_000);
+    w.place_bet(1, thin, 0, 0, 10).unwrap();
+    w.place_bet(1, thick, 0, 1, 990).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // Uncapped the 10 would take all 1_000; the cap holds it to twice its stake
+    w.claim(1, thin, 0).unwrap();
+    assert_eq!(w.h.token_balance(&thin.1), 990 + 20);
+
+    // The withheld excess goes to the treasury with the dust
+    sweep_dust(&mut w, treasury_vault).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), 980);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn the_settlement_report_fires_once_on_the_last_claim() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let winners = [w.bettor(1_000), w.bettor(1_000), w.bettor(1_000)];
+    let loser = w.bettor(1_000);
+    for winner in winners {
+        w.place_bet(1, winner, 0, 0, 100).unwrap();
+    }
+    w.place_bet(1, loser, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    w.claim(1, winners[0], 0).unwrap();
+    assert!(w.h.events::<SettlementReport>().is_empty());
+    w.claim(1, winners[1], 0).unwrap();
+    assert!(w.h.events::<SettlementReport>().is_empty());
+    w.claim(1, winners[2], 0).unwrap();
+
+    // Each winner took 400 / 3, floored, leaving 1 behind
+    let reports = w.h.events::<SettlementReport>();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report.market_id, 1);
+    assert_eq!(report.total_pool, 400);
+    assert_eq!(report.winning_pool, 300);
+    assert_eq!(report.total_paid, 399);
+    assert_eq!(report.total_fees, 0);
+    assert_eq!(report.dust, 1);
+    assert_eq!(report.claimant_count, 3);
+}
+
+#[test]
+fn floored_payouts_leave_dust_that_is_swept_once() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(1, 3_600, options());
+    let winners = [w.bettor(1_000), w.bettor(1_000), w.bettor(1_000)];
+    let loser = w.bettor(1_000);
+    for winner in winners {
+        w.place_bet(1, winner, 0, 0, 100).unwrap();
+    }
+    w.place_bet(1, loser, 0, 1, 101).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // Nothing is swept while a winner is still owed
+    w.claim(1, winners[0], 0).unwrap();
+    w.claim(1, winners[1], 0).unwrap();
+    assert_eq!(
+        sweep_dust(&mut w, treasury_vault).unwrap_err(),
+        err(ErrorCode::MarketNotFullySettled)
+    );
+    w.claim(1, winners[2], 0).unwrap();
+
+    // Each winner took 401 / 3, floored, leaving 2 behind
+    assert_eq!(w.h.token_balance(&winners[2].1), 900 + 133);
+    sweep_dust(&mut w, treasury_vault).unwrap();
+    assert_eq!(w.h.events::<DustSwept>()[0].amount, 2);
+    assert_eq!(w.h.token_balance(&treasury_vault), 2);
+    assert_eq!(w.h.get::<Treasury>(&treasury_pda()).total_collected, 2);
+    assert!(w.market(1).dust_swept);
+
+    // A second sweep is refused even if more tokens land in the vault
+    w.h.set_token_balance(vault_pda(&market_pda(1)), 5);
+    assert_eq!(
+        sweep_dust(&mut w, treasury_vault).unwrap_err(),
+        err(ErrorCode::DustAlreadySwept)
+    );
+}
+
+/// A resolved market where `yes` holds two winning bets.
+fn two_winning_bets(w: &mut World, claim_cooldown: i64) -> (Pubkey, Pubkey) {
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            claim_cooldown,
+            ..options()
+        },
+    );
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, yes, 1, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    yes
+}
+
+#[test]
+fn claims_from_one_wallet_are_throttled() {
+    let mut w = World::new();
+    let yes = two_winning_bets(&mut w, 60);
+    w.claim(1, yes, 0).unwrap();
+    w.h.warp(59);
+    assert_eq!(
+        w.claim(1, yes, 1).unwrap_err(),
+        err(ErrorCode::ClaimingTooFast)
+    );
+    w.h.warp(1);
+    w.claim(1, yes, 1).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 800 + 300);
+}
+
+#[test]
+fn no_cooldown_leaves_claims_unthrottled() {
+    let mut w = World::new();
+    let yes = two_winning_bets(&mut w, 0);
+    w.claim(1, yes, 0).unwrap();
+    w.claim(1, yes, 1).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 800 + 300);
+}
+
+#[test]
+fn the_creators_winnings_skip_the_protocol_fee_and_preview_agrees() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            fee_bps: 500,
+            creator_fee_exempt: true,
+            ..options()
+        },
+    );
+    let authority = w.authority;
+    let creator = (
+        authority,
+        w.h.create_token_account(w.mint, authority, 1_000),
+    );
+    let other = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, no, 0, 1, 200).unwrap();
+    w.place_bet(1, other, 0, 0, 100).unwrap();
+
+    // A last 100 on YES wins 200 gross: the creator keeps it all
+    let preview = |w: &mut World, bettor| -> u64 {
+        w.view(
+            1,
+            ix::PreviewWinnings {
+                _market_id: 1,
+                outcome: 0,
+                amount: 100,
+                bettor,
+            },
+        )
+    };
+    let creator_preview = preview(&mut w, creator.0);
+    let other_preview = preview(&mut w, other.0);
+    assert_eq!(creator_preview, 200);
+    assert_eq!(other_preview, 190);
+
+    w.place_bet(1, creator, 0, 0, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, creator, 0).unwrap();
+    w.claim(1, other, 0).unwrap();
+    assert_eq!(w.h.token_balance(&creator.1), 900 + creator_preview);
+    assert_eq!(w.h.token_balance(&other.1), 900 + other_preview);
+    assert_eq!(w.h.token_balance(&treasury_vault), 10);
+}
+
+fn reclaim_seed(w: &mut World, market_id: u64, to: Pubkey) -> Result {
+    let market = market_pda(market_id);
+    let authority = w.authority;
+    w.h.send(
+        accounts::ReclaimSeed {
+            market,
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            authority_token_account: to,
+            authority,
+            token_program: spl_token::ID,
+        },
+        ix::ReclaimSeed {
+            _market_id: market_id,
+        },
+    )
+}
+
+#[test]
+fn the_creator_reclaims_the_seed_once() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            seed_amount: 10_000,
+            prior_yes_bps: Some(7_000),
+            ..options()
+        },
+    );
+    let authority = w.authority;
+    let seed_account = w.h.create_token_account(w.mint, authority, 0);
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+
+    // The seed is owed nothing until the market settles
+    assert_eq!(
+        reclaim_seed(&mut w, 1, seed_account).unwrap_err(),
+        err(ErrorCode::MarketNotResolved)
+    );
+
+    // Unopposed, the winning seed takes the whole pool back
+    w.resolve(1, 0).unwrap();
+    reclaim_seed(&mut w, 1, seed_account).unwrap();
+    assert_eq!(w.h.token_balance(&seed_account), 10_000);
+    assert_eq!(
+        reclaim_seed(&mut w, 1, seed_account).unwrap_err(),
+        err(ErrorCode::AlreadyClaimed)
+    );
+}
+
+#[test]
+fn initial_liquidity_sets_the_first_odds_and_is_forfeited_once_contested() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            initial_yes: 300,
+            initial_no: 100,
+            ..options()
+        },
+    );
+    let market = w.market(1);
+    assert_eq!(market.seed_pools, vec![300, 100]);
+    assert_eq!(market.outcome_pools, vec![300, 100]);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 400);
+    let seeded = &w.h.events::<LiquiditySeeded>()[0];
+    assert_eq!(
+        (seeded.authority, &seeded.seed_pools),
+        (w.authority, &vec![300, 100])
+    );
+
+    // The first bettor already faces a 3:1 book instead of an empty one
+    let yes = w.bettor(1_000);
+    let preview: u64 = w.view(
+        1,
+        ix::PreviewWinnings {
+            _market_id: 1,
+            outcome: 1,
+            amount: 100,
+            bettor: yes.0,
+        },
+    );
+    assert_eq!(preview, 100 * 500 / 200);
+
+    // Backers on both sides: the YES seed stays in the pool for the winner
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    let authority = w.authority;
+    let seed_account = w.h.create_token_account(w.mint, authority, 0);
+    assert_eq!(
+        reclaim_seed(&mut w, 1, seed_account).unwrap_err(),
+        err(ErrorCode::NoSeedToReclaim)
+    );
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_500);
+}
+
+fn set_fee_splits(w: &mut World, splits: Vec<FeeSplit>) -> Result {
+    let admin = w.admin;
+    w.h.send(
+        accounts::SetFeeSplits {
+            config: config_pda(),
+            admin,
+        },
+        ix::SetFeeSplits { splits },
+    )
+}
+
+#[test]
+fn treasury_fees_split_across_the_recipients() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            fee_bps: 1_000,
+            ..options()
+        },
+    );
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 900).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // 10% of the 1_000 payout goes to the treasury
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), 100);
+
+    let recipients: Vec<(Pubkey, Pubkey)> = (0..3)
+        .map(|_| {
+            let recipient = Pubkey::new_unique();
+            (recipient, w.h.create_token_account(w.mint, recipient, 0))
+        })
+        .collect();
+    let split = |i: usize, bps| FeeSplit {
+        recipient: recipients[i].0,
+        bps,
+    };
+
+    // Shares must cover exactly the whole fee
+    for splits in [
+        vec![split(0, 5_000), split(1, 3_000), split(2, 1_999)],
+        vec![split(0, 5_000), split(1, 5_000), split(2, 0)],
+    ] {
+        assert_eq!(
+            set_fee_splits(&mut w, splits).unwrap_err(),
+            err(ErrorCode::InvalidFeeSplit)
+        );
+    }
+    set_fee_splits(
+        &mut w,
+        vec![split(0, 5_000), split(1, 3_333), split(2, 1_667)],
+    )
+    .unwrap();
+
+    let mint = w.mint;
+    let remaining: Vec<AccountMeta> = recipients
+        .iter()
+        .map(|&(_, account)| AccountMeta::new(account, false))
+        .collect();
+    w.h.send_with(
+        accounts::DistributeTreasury {
+            config: config_pda(),
+            treasury: treasury_pda(),
+            treasury_vault,
+            mint,
+            token_program: spl_token::ID,
+        },
+        ix::DistributeTreasury { amount: 100 },
+        &remaining,
+    )
+    .unwrap();
+
+    // 50 and 33 by ratio; the last recipient takes the rounding remainder
+    let balances: Vec<u64> = recipients
+        .iter()
+        .map(|(_, account)| w.h.token_balance(account))
+        .collect();
+    assert_eq!(balances, vec![50, 33, 17]);
+    assert_eq!(w.h.token_balance(&treasury_vault), 0);
+}
+
+#[test]
+fn precomputed_payouts_match_and_are_what_claims_pay() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let small = w.bettor(1_000);
+    let large = w.bettor(1_000);
+    let loser = w.bettor(1_000);
+    w.place_bet(1, small, 0, 0, 100).unwrap();
+    w.place_bet(1, large, 0, 0, 300).unwrap();
+    w.place_bet(1, loser, 0, 1, 600).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    let market = market_pda(1);
+    let bets: Vec<Pubkey> = [small, large, loser]
+        .iter()
+        .map(|bettor| bet_pda(&market, &bettor.0, 0))
+        .collect();
+    let on_the_fly: Vec<u64> = bets[..2]
+        .iter()
+        .map(|bet| w.market(1).payout_for(&w.h.get(bet)).unwrap())
+        .collect();
+    assert_eq!(on_the_fly, vec![250, 750]);
+
+    let remaining: Vec<AccountMeta> = bets
+        .iter()
+        .map(|&bet| AccountMeta::new(bet, false))
+        .collect();
+    w.h.send_with(
+        accounts::PrecomputePayouts { market },
+        ix::PrecomputePayouts { market_id: 1 },
+        &remaining,
+    )
+    .unwrap();
+    let report = &w.h.events::<PayoutsPrecomputed>()[0];
+    assert_eq!((report.stored, report.skipped), (2, 1));
+    let stored: Vec<Option<u64>> = bets
+        .iter()
+        .map(|bet| w.h.get::<Bet>(bet).payout_amount)
+        .collect();
+    assert_eq!(stored, vec![Some(250), Some(750), None]);
+
+    // Repricing the pool no longer moves a stored payout
+    let mut repriced = w.market(1);
+    repriced.outcome_pools[1] = 300;
+    w.h.put(&market, &repriced);
+    w.claim(1, small, 0).unwrap();
+    assert_eq!(w.h.token_balance(&small.1), 900 + 250);
+}
+
+fn refund_all(w: &mut World, bettors: &[(Pubkey, Pubkey)]) -> Result {
+    let market = market_pda(1);
+    let remaining: Vec<AccountMeta> = bettors
+        .iter()
+        .flat_map(|&(bettor, token_account)| {
+            [
+                AccountMeta::new(bet_pda(&market, &bettor, 0), false),
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(position_pda(&market, &bettor), false),
+            ]
+        })
+        .collect();
+    let authority = w.authority;
+    w.h.send_with(
+        accounts::RefundAll {
+            market,
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            authority,
+            token_program: spl_token::ID,
+        },
+        ix::RefundAll { market_id: 1 },
+        &remaining,
+    )
+}
+
+#[test]
+fn a_cancelled_market_refunds_three_bettors_at_once() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettors: Vec<(Pubkey, Pubkey)> = [(0, 100), (1, 200), (0, 300)]
+        .into_iter()
+        .map(|(outcome, amount)| {
+            let bettor = w.bettor(1_000);
+            w.place_bet(1, bettor, 0, outcome, amount).unwrap();
+            bettor
+        })
+        .collect();
+
+    // Refunds wait for the market to be voided
+    assert_eq!(
+        refund_all(&mut w, &bettors).unwrap_err(),
+        err(ErrorCode::MarketNotVoided)
+    );
+    w.cancel_market(1).unwrap();
+    refund_all(&mut w, &bettors).unwrap();
+    let report = &w.h.events::<BetsRefunded>()[0];
+    assert_eq!((report.refunded, report.skipped), (3, 0));
+    for bettor in &bettors {
+        assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+        assert!(
+            w.h.get::<Bet>(&bet_pda(&market_pda(1), &bettor.0, 0))
+                .is_claimed
+        );
+    }
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+
+    // A second pass skips them all without paying twice
+    refund_all(&mut w, &bettors).unwrap();
+    let report = &w.h.events::<BetsRefunded>()[0];
+    assert_eq!((report.refunded, report.skipped), (0, 3));
+}
+
+/// Market 1 with every stake on `side`, resolved to the other outcome.
+fn resolved_against_everyone(w: &mut World, side: u8) -> Vec<(Pubkey, Pubkey)> {
+    w.create_market(1, 3_600, options());
+    let bettors: Vec<_> = [100, 250]
+        .into_iter()
+        .map(|amount| {
+            let bettor = w.bettor(1_000);
+            w.place_bet(1, bettor, 0, side, amount).unwrap();
+            bettor
+        })
+        .collect();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 1 - side).unwrap();
+    bettors
+}
+
+#[test]
+fn an_empty_winning_side_refunds_every_bettor() {
+    for side in [1, 0] {
+        let mut w = World::new();
+        let bettors = resolved_against_everyone(&mut w, side);
+
+        // Nobody backed the winner, so the stakes go back
+        let market = w.market(1);
+        assert!(market.refund_only);
+        assert_eq!(market.winning_outcome, None);
+        for bettor in bettors {
+            w.claim(1, bettor, 0).unwrap();
+            assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+        }
+        assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+    }
+}
+
+#[test]
+fn pricing_against_an_empty_winning_pool_is_refused() {
+    let mut w = World::new();
+    resolved_against_everyone(&mut w, 1);
+
+    // Forced to resolve anyway, the payout math refuses rather than divide by zero
+    let mut market = w.market(1);
+    market.refund_only = false;
+    market.winning_outcome = Some(0);
+    assert_eq!(
+        market.seed_payout().unwrap_err(),
+        anchor_lang::error!(ErrorCode::EmptyWinningPool)
+    );
+}
+
+/// Market 1 charging a 2.5% fee, with `stakes` on YES and NO, resolved YES.
+fn with_fee(w: &mut World, stakes: [u64; 2]) -> (Pubkey, Pubkey) {
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            fee_bps: 250,
+            ..options()
+        },
+    );
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, stakes[0]).unwrap();
+    w.place_bet(1, no, 0, 1, stakes[1]).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    yes
+}
+
+#[test]
+fn the_fee_goes_to_the_treasury_and_the_rest_to_the_winner() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    let yes = with_fee(&mut w, [200, 600]);
+
+    // 2.5% of the 800 payout
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), 20);
+    assert_eq!(w.h.token_balance(&yes.1), 800 + 780);
+    let collected = &w.h.events::<FeeCollected>()[0];
+    assert_eq!((collected.amount, collected.treasury_total), (20, 20));
+    assert_eq!(w.h.get::<Treasury>(&treasury_pda()).total_collected, 20);
+}
+
+#[test]
+fn a_fee_that_rounds_to_zero_takes_nothing() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    let yes = with_fee(&mut w, [10, 29]);
+
+    // 2.5% of 39 is under one unit
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), 0);
+    assert_eq!(w.h.token_balance(&yes.1), 990 + 39);
+}
+
+#[test]
+fn fees_are_capped_at_ten_percent() {
+    let mut w = World::new();
+    let options = MarketOptions {
+        fee_bps: 1_001,
+        ..options()
+    };
+    assert_eq!(
+        w.try_create_market(1, 3_600, options).unwrap_err(),
+        err(ErrorCode::FeeTooHigh)
+    );
+}
+
+fn close_losing(w: &mut World, bettor: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::CloseLosingBet {
+            market,
+            bet: bet_pda(&market, &bettor, 0),
+            dest_market: None,
+            bettor,
+        },
+        ix::CloseLosingBet {
+            market_id: 1,
+            _bet_index: 0,
+        },
+    )
+}
+
+#[test]
+fn settled_bets_return_their_rent() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    let rent = Rent::default().minimum_balance(8 + Bet::INIT_SPACE);
+
+    // A winning bet can only be closed by claiming it
+    assert_eq!(
+        close_losing(&mut w, yes.0).unwrap_err(),
+        err(ErrorCode::BetNotLosing)
+    );
+    let lamports = w.h.lamports(&yes.0);
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.lamports(&yes.0), lamports + rent);
+
+    let lamports = w.h.lamports(&no.0);
+    close_losing(&mut w, no.0).unwrap();
+    assert_eq!(w.h.lamports(&no.0), lamports + rent);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &no.0, 0)));
+    assert_eq!(w.market(1).live_bets, 0);
+}
+
+#[test]
+fn a_cancelled_market_refunds_both_sides() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    // Only the market authority cancels
+    let authority = w.authority;
+    w.authority = w.h.wallet();
+    assert_eq!(
+        w.cancel_market(1).unwrap_err(),
+        err(ErrorCode::UnauthorizedResolver)
+    );
+    w.authority = authority;
+    w.cancel_market(1).unwrap();
+    let cancelled = &w.h.events::<MarketCancelled>()[0];
+    assert_eq!((cancelled.market_id, cancelled.authority), (1, authority));
+
+    assert_eq!(
+        w.place_bet(1, yes, 1, 0, 100).unwrap_err(),
+        err(ErrorCode::MarketCancelled)
+    );
+    w.claim(1, yes, 0).unwrap();
+    w.claim(1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_000);
+    assert_eq!(w.h.token_balance(&no.1), 1_000);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn a_short_vault_pays_what_it_holds() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let winners: Vec<(Pubkey, Pubkey)> = (0..4)
+        .map(|_| {
+            let bettor = w.bettor(1_000);
+            w.place_bet(1, bettor, 0, 0, 100).unwrap();
+            bettor
+        })
+        .collect();
+    let loser = w.bettor(1_000);
+    w.place_bet(1, loser, 0, 1, 200).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // Each winner is owed 150 of the 600 pool
+    w.claim(1, winners[0], 0).unwrap();
+    w.claim(1, winners[1], 0).unwrap();
+    assert_eq!(w.h.token_balance(&winners[1].1), 1_050);
+
+    // The vault comes up short: the next claim takes what is left
+    let vault = vault_pda(&market_pda(1));
+    w.h.set_token_balance(vault, 100);
+    w.claim(1, winners[2], 0).unwrap();
+    assert_eq!(w.h.token_balance(&winners[2].1), 1_000);
+    assert_eq!(w.h.events::<WinningsClaimed>()[0].amount, 100);
+    assert_eq!(w.h.token_balance(&vault), 0);
+
+    assert_eq!(
+        w.claim(1, winners[3], 0).unwrap_err(),
+        err(ErrorCode::InsufficientVaultBalance)
+    );
+}
+
+fn withdraw_treasury(w: &mut World, admin: Pubkey, destination: Pubkey, amount: u64) -> Result {
+    w.h.send(
+        accounts::WithdrawTreasury {
+            config: config_pda(),
+            treasury: treasury_pda(),
+            treasury_vault: pda(&[b"treasury_vault", w.mint.as_ref()]),
+            mint: w.mint,
+            destination,
+            admin,
+            token_program: spl_token::ID,
+        },
+        ix::WithdrawTreasury { amount },
+    )
+}
+
+#[test]
+fn fees_accumulate_across_claims_and_only_the_admin_withdraws() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            fee_bps: 500,
+            ..options()
+        },
+    );
+    let winners: Vec<(Pubkey, Pubkey)> = [100, 300]
+        .into_iter()
+        .map(|amount| {
+            let bettor = w.bettor(1_000);
+            w.place_bet(1, bettor, 0, 0, amount).unwrap();
+            bettor
+        })
+        .collect();
+    let no = w.bettor(1_000);
+    w.place_bet(1, no, 0, 1, 400).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // 5% of the 200 and 600 payouts
+    w.claim(1, winners[0], 0).unwrap();
+    assert_eq!(w.h.get::<Treasury>(&treasury_pda()).total_collected, 10);
+    w.claim(1, winners[1], 0).unwrap();
+    assert_eq!(w.h.get::<Treasury>(&treasury_pda()).total_collected, 40);
+    assert_eq!(w.h.token_balance(&treasury_vault), 40);
+
+    let stranger = w.h.wallet();
+    let destination = w.h.create_token_account(w.mint, stranger, 0);
+    assert_eq!(
+        withdraw_treasury(&mut w, stranger, destination, 40).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    let admin = w.admin;
+    withdraw_treasury(&mut w, admin, destination, 40).unwrap();
+    assert_eq!(w.h.token_balance(&destination), 40);
+    assert_eq!(w.h.token_balance(&treasury_vault), 0);
+}
+
+fn withdraw_creator_fees(w: &mut World, authority: Pubkey, to: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::WithdrawCreatorFees {
+            market,
+            market_vault: vault_pda(&market),
+            creator_fee_vault: pda(&[b"creator_fee_vault", market.as_ref()]),
+            mint: w.mint,
+            authority_token_account: to,
+            authority,
+            token_program: spl_token::ID,
+        },
+        ix::WithdrawCreatorFees { market_id: 1 },
+    )
+}
+
+#[test]
+fn creator_fees_accrue_across_claims_until_withdrawn() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            creator_fee_bps: 500,
+            ..options()
+        },
+    );
+    let small = w.bettor(1_000);
+    let large = w.bettor(1_000);
+    let loser = w.bettor(1_000);
+    w.place_bet(1, small, 0, 0, 100).unwrap();
+    w.place_bet(1, large, 0, 0, 300).unwrap();
+    w.place_bet(1, loser, 0, 1, 400).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    let authority = w.authority;
+    let to = w.h.create_token_account(w.mint, authority, 0);
+    assert_eq!(
+        withdraw_creator_fees(&mut w, authority, to).unwrap_err(),
+        err(ErrorCode::NoFeesToWithdraw)
+    );
+
+    // 5% of the 200 and 600 payouts
+    w.claim(1, small, 0).unwrap();
+    assert_eq!(w.market(1).creator_fees_accrued, 10);
+    w.claim(1, large, 0).unwrap();
+    assert_eq!(w.market(1).creator_fees_accrued, 40);
+    assert_eq!(w.h.token_balance(&small.1), 900 + 190);
+    assert_eq!(w.h.token_balance(&large.1), 700 + 570);
+
+    let stranger = w.h.wallet();
+    let theirs = w.h.create_token_account(w.mint, stranger, 0);
+    assert_eq!(
+        withdraw_creator_fees(&mut w, stranger, theirs).unwrap_err(),
+        err(ErrorCode::UnauthorizedResolver)
+    );
+    withdraw_creator_fees(&mut w, authority, to).unwrap();
+    assert_eq!(w.h.token_balance(&to), 40);
+    assert_eq!(w.market(1).creator_fees_accrued, 0);
+    assert_eq!(
+        withdraw_creator_fees(&mut w, authority, to).unwrap_err(),
+        err(ErrorCode::NoFeesToWithdraw)
+    );
+}
+
+#[test]
+fn a_claimed_bet_is_never_paid_twice() {
+    let mut w = World::new();
+    let (yes, _) = resolved(&mut w);
+    let bet_key = bet_pda(&market_pda(1), &yes.0, 0);
+    let mut bet: Bet = w.h.get(&bet_key);
+
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+    assert_eq!(w.claim(1, yes, 0).unwrap_err(), err_missing());
+
+    // A bet left open with its flag set, as it is mid-claim, pays nothing
+    bet.is_claimed = true;
+    let mut data = Vec::new();
+    bet.try_serialize(&mut data).unwrap();
+    w.h.set_account(bet_key, prediction_market::ID, data);
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err(ErrorCode::AlreadyClaimed)
+    );
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+}
+
+#[test]
+fn early_winners_take_more_of_the_winning_side() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        10_000,
+        MarketOptions {
+            early_bird_bps: 5_000,
+            ..options()
+        },
+    );
+    let early = w.bettor(1_000);
+    let late = w.bettor(1_000);
+    let loser = w.bettor(1_000);
+    w.place_bet(1, early, 0, 0, 100).unwrap();
+    w.place_bet(1, loser, 0, 1, 200).unwrap();
+
+    // A quarter of the lifetime left earns a quarter of the bonus
+    w.h.warp(7_500);
+    w.place_bet(1, late, 0, 0, 100).unwrap();
+    let market = market_pda(1);
+    assert_eq!(
+        w.h.get::<Bet>(&bet_pda(&market, &early.0, 0))
+            .weighted_amount,
+        150
+    );
+    assert_eq!(
+        w.h.get::<Bet>(&bet_pda(&market, &late.0, 0))
+            .weighted_amount,
+        112
+    );
+
+    w.h.warp(2_500);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, early, 0).unwrap();
+    w.claim(1, late, 0).unwrap();
+
+    // 400 split 150:112 across the winning side, never more than the pool
+    assert_eq!(w.h.token_balance(&early.1), 900 + 229);
+    assert_eq!(w.h.token_balance(&late.1), 900 + 170);
+    assert_eq!(w.h.token_balance(&vault_pda(&market)), 1);
+}
+
+fn claim_with_charity(w: &mut World, bettor: (Pubkey, Pubkey), charity_account: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::ClaimWinnings {
+            market,
+            bet: bet_pda(&market, &bettor.0, 0),
+            market_vault: vault_pda(&market),
+            mint: w.mint,
+            bettor_token_account: bettor.1,
+            recipient_token_account: None,
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: Some(charity_account),
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&market, &bettor.0),
+            bettor: bettor.0,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimWinnings {
+            market_id: 1,
+            _bet_index: 0,
+        },
+    )
+}
+
+#[test]
+fn a_charity_takes_its_cut_of_each_payout() {
+    let mut w = World::new();
+    let charity = w.h.wallet();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            charity: Some(charity),
+            charity_bps: 1_000,
+            ..options()
+        },
+    );
+    let small = w.bettor(1_000);
+    let large = w.bettor(1_000);
+    let loser = w.bettor(1_000);
+    w.place_bet(1, small, 0, 0, 100).unwrap();
+    w.place_bet(1, large, 0, 0, 300).unwrap();
+    w.place_bet(1, loser, 0, 1, 400).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // The cut has to go to an account the charity owns
+    assert_eq!(
+        w.claim(1, small, 0).unwrap_err(),
+        err(ErrorCode::CharityAccountMissing)
+    );
+    let imposter = w.h.create_token_account(w.mint, small.0, 0);
+    assert_eq!(
+        claim_with_charity(&mut w, small, imposter).unwrap_err(),
+        err_anchor(anchor_lang::error::ErrorCode::ConstraintTokenOwner)
+    );
+
+    // 10% of the 200 and 600 payouts, the rest as normal
+    let charity_account = w.h.create_token_account(w.mint, charity, 0);
+    claim_with_charity(&mut w, small, charity_account).unwrap();
+    let donation = &w.h.events::<CharityDonation>()[0];
+    assert_eq!((donation.charity, donation.amount), (charity, 20));
+    claim_with_charity(&mut w, large, charity_account).unwrap();
+    assert_eq!(w.h.token_balance(&small.1), 900 + 180);
+    assert_eq!(w.h.token_balance(&large.1), 700 + 540);
+    assert_eq!(w.h.token_balance(&charity_account), 80);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn claims_sign_for_the_vault_with_its_stored_bump() {
+    let mut w = World::new();
+    let (yes, _) = resolved(&mut w);
+    let market = market_pda(1);
+    let (_, canonical) =
+        Pubkey::find_program_address(&[b"market_vault", market.as_ref()], &prediction_market::ID);
+    let stored = w.market(1);
+    assert_eq!(stored.vault_bump, canonical);
+
+    // A claim goes by the stored bump, so a wrong one cannot sign
+    let mut corrupted = stored.clone();
+    corrupted.vault_bump = canonical.wrapping_sub(1);
+    w.h.put(&market, &corrupted);
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err_anchor(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+
+    w.h.put(&market, &stored);
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+}
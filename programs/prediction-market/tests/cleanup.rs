@@ -0,0 +1,258 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{accounts, instruction as ix, Archive, ErrorCode};
+
+fn close_batch(w: &mut World, market_id: u64, bets: &[(Pubkey, Pubkey)]) -> Result {
+    let market = market_pda(market_id);
+    let remaining: Vec<AccountMeta> = bets
+        .iter()
+        .flat_map(|&(bettor, _)| {
+            [
+                AccountMeta::new(bet_pda(&market, &bettor, 0), false),
+                AccountMeta::new(bettor, false),
+            ]
+        })
+        .collect();
+    w.h.send_with(
+        accounts::CloseBetsBatch {
+            market,
+            dest_market: None,
+        },
+        ix::CloseBetsBatch { market_id },
+        &remaining,
+    )
+}
+
+#[test]
+fn a_batch_closes_settled_bets_and_skips_the_rest() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let winner = w.bettor(1_000);
+    let claimed = w.bettor(1_000);
+    let losers = [w.bettor(1_000), w.bettor(1_000), w.bettor(1_000)];
+    w.place_bet(1, winner, 0, 0, 100).unwrap();
+    w.place_bet(1, claimed, 0, 0, 100).unwrap();
+    for loser in losers {
+        w.place_bet(1, loser, 0, 1, 100).unwrap();
+    }
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+
+    // Nothing closes before resolution
+    assert_eq!(
+        close_batch(&mut w, 1, &losers).unwrap_err(),
+        err(ErrorCode::MarketNotResolved)
+    );
+    w.resolve(1, 0).unwrap();
+    w.claim(1, claimed, 0).unwrap();
+
+    let market = market_pda(1);
+    let rent = w.h.lamports(&bet_pda(&market, &losers[0].0, 0));
+    let before: Vec<u64> = losers.iter().map(|l| w.h.lamports(&l.0)).collect();
+    let mut batch = losers.to_vec();
+    batch.push(winner);
+    close_batch(&mut w, 1, &batch).unwrap();
+
+    // Each loser has their rent back; the unclaimed winner is left alone
+    for (loser, before) in losers.iter().zip(before) {
+        assert!(!w.h.exists(&bet_pda(&market, &loser.0, 0)));
+        assert_eq!(w.h.lamports(&loser.0), before + rent);
+    }
+    assert!(w.h.exists(&bet_pda(&market, &winner.0, 0)));
+    assert_eq!(w.market(1).live_bets, 1);
+}
+
+#[test]
+fn a_batch_only_returns_rent_to_the_bettor() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    let market = market_pda(1);
+    let thief = w.h.wallet();
+    let result = w.h.send_with(
+        accounts::CloseBetsBatch {
+            market,
+            dest_market: None,
+        },
+        ix::CloseBetsBatch { market_id: 1 },
+        &[
+            AccountMeta::new(bet_pda(&market, &no.0, 0), false),
+            AccountMeta::new(thief, false),
+        ],
+    );
+    assert_eq!(result.unwrap_err(), err(ErrorCode::UnauthorizedClaimer));
+}
+
+#[test]
+fn closing_refunds_the_sponsor_rather_than_the_authority() {
+    let mut w = World::new();
+    let sponsor = w.h.wallet();
+    w.sponsor = sponsor;
+    w.create_market(1, 3_600, options());
+    assert_eq!(w.market(1).rent_sponsor, sponsor);
+
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    close_batch(&mut w, 1, &[no]).unwrap();
+
+    let market = market_pda(1);
+    let rent = w.h.lamports(&market) + w.h.lamports(&vault_pda(&market));
+    let sponsor_before = w.h.lamports(&sponsor);
+    let authority = w.authority;
+    let authority_before = w.h.lamports(&authority);
+    w.close_market(1).unwrap();
+    assert!(!w.h.exists(&market));
+    assert_eq!(w.h.lamports(&sponsor), sponsor_before + rent);
+    assert_eq!(w.h.lamports(&authority), authority_before);
+}
+
+#[test]
+fn a_market_closes_once_its_vault_is_empty() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(1, 3_600, options());
+    let winners = [w.bettor(1_000), w.bettor(1_000), w.bettor(1_000)];
+    let loser = w.bettor(1_000);
+    for winner in winners {
+        w.place_bet(1, winner, 0, 0, 100).unwrap();
+    }
+    w.place_bet(1, loser, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    for winner in winners {
+        w.claim(1, winner, 0).unwrap();
+    }
+    close_batch(&mut w, 1, &[loser]).unwrap();
+
+    // Three floored shares of 400 leave a unit behind
+    let market = market_pda(1);
+    assert_eq!(w.h.token_balance(&vault_pda(&market)), 1);
+    assert_eq!(
+        w.close_market(1).unwrap_err(),
+        err(ErrorCode::VaultNotEmpty)
+    );
+    w.h.send(
+        accounts::SweepDust {
+            market,
+            market_vault: vault_pda(&market),
+            treasury: treasury_pda(),
+            treasury_vault,
+            mint: w.mint,
+            token_program: spl_token::ID,
+        },
+        ix::SweepDust { market_id: 1 },
+    )
+    .unwrap();
+
+    let rent = w.h.lamports(&market) + w.h.lamports(&vault_pda(&market));
+    let authority = w.authority;
+    let before = w.h.lamports(&authority);
+    w.close_market(1).unwrap();
+    assert!(!w.h.exists(&market));
+    assert!(!w.h.exists(&vault_pda(&market)));
+    assert_eq!(w.h.lamports(&authority), before + rent);
+}
+
+#[test]
+fn markets_with_open_bets_stay_open() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(
+        w.close_market(1).unwrap_err(),
+        err(ErrorCode::BetsOutstanding)
+    );
+}
+
+fn archive_market(w: &mut World, market_id: u64) -> Result {
+    let market = market_pda(market_id);
+    let rent_sponsor = w.market(market_id).rent_sponsor;
+    let authority = w.authority;
+    w.h.send(
+        accounts::ArchiveMarket {
+            market,
+            market_vault: vault_pda(&market),
+            archive: pda(&[b"archive", market_id.to_le_bytes().as_ref()]),
+            rent_sponsor,
+            authority,
+            authority_stats: Some(stats_pda(&authority)),
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ArchiveMarket { market_id },
+    )
+}
+
+#[test]
+fn a_settled_market_archives_to_a_summary() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let created_at = w.h.now;
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 300).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // The winner is still owed
+    assert_eq!(
+        archive_market(&mut w, 1).unwrap_err(),
+        err(ErrorCode::MarketNotFullySettled)
+    );
+    w.claim(1, yes, 0).unwrap();
+    close_batch(&mut w, 1, &[no]).unwrap();
+
+    let market = market_pda(1);
+    let rent = w.h.lamports(&market) + w.h.lamports(&vault_pda(&market));
+    let authority = w.authority;
+    let before = w.h.lamports(&authority);
+    w.h.warp(60);
+    archive_market(&mut w, 1).unwrap();
+    assert!(!w.h.exists(&market));
+    assert!(!w.h.exists(&vault_pda(&market)));
+
+    // The authority, sponsoring here, gets the market's rent back and pays
+    // the archive's
+    let archive_key = pda(&[b"archive", 1u64.to_le_bytes().as_ref()]);
+    assert_eq!(
+        w.h.lamports(&authority),
+        before + rent - w.h.lamports(&archive_key)
+    );
+    let archive: Archive = w.h.get(&archive_key);
+    assert_eq!(archive.market_id, 1);
+    assert_eq!(archive.authority, authority);
+    assert_eq!(archive.winning_outcome, Some(0));
+    assert!(!archive.refund_only);
+    assert_eq!(archive.outcome_pools, vec![100, 300]);
+    assert_eq!(archive.total_paid, 400);
+    assert_eq!(archive.created_at, created_at);
+    assert_eq!(archive.end_time, created_at + 3_600);
+    assert_eq!(archive.resolved_at, created_at + 3_600);
+    assert_eq!(archive.archived_at, created_at + 3_660);
+}
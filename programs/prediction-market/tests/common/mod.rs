@@ -0,0 +1,1242 @@
+//! A small in-process runtime for driving the program from tests: accounts
+//! live in a bank, each instruction is serialized into the loader's input
+//! layout and passed to `prediction_market::entry`, and CPIs into the
+//! system and token programs (SPL Token and Token-2022) are executed by
+//! syscall stubs.
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+pub use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::entrypoint::{deserialize, MAX_PERMITTED_DATA_INCREASE};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_lang::{
+    AccountDeserialize, AccountSerialize, AnchorDeserialize, Event, InstructionData, ToAccountMetas,
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use prediction_market::{
+    instruction as ix, Bet, CreatedMarket, Currency, ErrorCode, HedgeLeg, LogLevel, Market,
+    MarketOptions, MarketType, Resolution, ResolutionSource,
+};
+use spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeConfig};
+use spl_token_2022::extension::{
+    BaseStateWithExtensions, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensions,
+    StateWithExtensionsMut,
+};
+
+/// Anchor's custom error for `code`, as the runtime reports it.
+pub fn err(code: ErrorCode) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// What a processed instruction returns: its return data, or the error.
+pub type Result = std::result::Result<Vec<u8>, ProgramError>;
+
+/// What Anchor reports when a required account was closed or never made.
+pub fn err_missing() -> ProgramError {
+    err_anchor(anchor_lang::error::ErrorCode::AccountNotInitialized)
+}
+
+pub fn err_anchor(code: anchor_lang::error::ErrorCode) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// The system program's `AccountAlreadyInUse`, which `init` meets on an
+/// account that already exists.
+pub fn err_in_use() -> ProgramError {
+    ProgramError::Custom(0)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+thread_local! {
+    static NOW: RefCell<i64> = const { RefCell::new(0) };
+    static SLOT: RefCell<u64> = const { RefCell::new(0) };
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    static LOGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        LOGS.with(|logs| logs.borrow_mut().push(message.to_string()));
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(fields.concat()));
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT.with(|slot| *slot.borrow()),
+            unix_timestamp: NOW.with(|now| *now.borrow()),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|data| data.borrow().clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        RETURN_DATA.with(|slot| *slot.borrow_mut() = Some((prediction_market::ID, data.to_vec())));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> std::result::Result<(), ProgramError> {
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &prediction_market::ID))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+
+        let mut infos = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut info = account_infos
+                .iter()
+                .find(|info| info.key == &meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            if meta.is_signer {
+                if !info.is_signer && !signers.contains(&meta.pubkey) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                info.is_signer = true;
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            infos.push(info);
+        }
+
+        if instruction.program_id == system_program::ID {
+            process_system(&infos, &instruction.data)
+        } else if instruction.program_id == spl_token::ID {
+            spl_token::processor::Processor::process(&spl_token::ID, &infos, &instruction.data)
+        } else if instruction.program_id == spl_token_2022::ID {
+            spl_token_2022::processor::Processor::process(
+                &spl_token_2022::ID,
+                &infos,
+                &instruction.data,
+            )
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+}
+
+/// The handful of system instructions Anchor issues, decoded from their
+/// bincode layout.
+fn process_system(infos: &[AccountInfo], data: &[u8]) -> std::result::Result<(), ProgramError> {
+    let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+    let pubkey_at = |at: usize| Pubkey::try_from(&data[at..at + 32]).unwrap();
+    let transfer = |from: &AccountInfo, to: &AccountInfo, lamports: u64| {
+        if !from.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let remaining = from
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **from.try_borrow_mut_lamports()? = remaining;
+        **to.try_borrow_mut_lamports()? += lamports;
+        Ok(())
+    };
+
+    match u32::from_le_bytes(data[..4].try_into().unwrap()) {
+        // CreateAccount { lamports, space, owner }
+        0 => {
+            let (from, to) = (&infos[0], &infos[1]);
+            if !to.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if to.lamports() > 0 || !to.data_is_empty() {
+                return Err(err_in_use());
+            }
+            transfer(from, to, u64_at(4))?;
+            to.resize(u64_at(12) as usize)?;
+            to.assign(&pubkey_at(20));
+            Ok(())
+        }
+        // Assign { owner }
+        1 => {
+            infos[0].assign(&pubkey_at(4));
+            Ok(())
+        }
+        // Transfer { lamports }
+        2 => transfer(&infos[0], &infos[1], u64_at(4)),
+        // Allocate { space }
+        8 => {
+            if !infos[0].data_is_empty() || infos[0].owner != &system_program::ID {
+                return Err(err_in_use());
+            }
+            infos[0].resize(u64_at(4) as usize)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+pub struct Harness {
+    pub accounts: HashMap<Pubkey, Account>,
+    pub now: i64,
+    pub slot: u64,
+    pub payer: Pubkey,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        let mut harness = Self {
+            accounts: HashMap::new(),
+            now: 1_000_000,
+            slot: 1,
+            payer: Pubkey::new_unique(),
+        };
+        for program in [system_program::ID, spl_token::ID, spl_token_2022::ID] {
+            harness.accounts.insert(
+                program,
+                Account {
+                    lamports: 1,
+                    executable: true,
+                    ..Account::default()
+                },
+            );
+        }
+        harness.deploy(Some(harness.payer));
+        harness.accounts.insert(
+            sysvar::rent::ID,
+            Account {
+                lamports: 1,
+                data: bincode_rent(),
+                owner: sysvar::ID,
+                executable: false,
+            },
+        );
+        let payer = harness.payer;
+        harness.airdrop(payer, 1_000 * LAMPORTS_PER_SOL);
+        harness
+    }
+
+    /// Loads the program behind the upgradeable loader, with `authority` as
+    /// its upgrade authority.
+    pub fn deploy(&mut self, authority: Option<Pubkey>) {
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend_from_slice(program_data_address().as_ref());
+        self.accounts.insert(
+            prediction_market::ID,
+            Account {
+                lamports: 1,
+                data: program,
+                owner: ProgramData::owner(),
+                executable: true,
+            },
+        );
+
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        match authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend_from_slice(authority.as_ref());
+            }
+            None => data.push(0),
+        }
+        self.accounts.insert(
+            program_data_address(),
+            Account {
+                lamports: 1,
+                data,
+                owner: ProgramData::owner(),
+                executable: false,
+            },
+        );
+    }
+
+    pub fn airdrop(&mut self, key: Pubkey, lamports: u64) {
+        let account = self.accounts.entry(key).or_default();
+        account.lamports += lamports;
+    }
+
+    /// A fresh system-owned wallet holding some SOL for rent.
+    pub fn wallet(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.airdrop(key, 10 * LAMPORTS_PER_SOL);
+        key
+    }
+
+    pub fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let key = Pubkey::new_unique();
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            mint_authority: Some(self.payer).into(),
+            supply: u64::MAX / 2,
+            decimals,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        }
+        .pack_into_slice(&mut data);
+        self.insert_token_state(key, data);
+        key
+    }
+
+    /// A Token-2022 mint whose transfers withhold `fee_bps` of the amount.
+    pub fn create_fee_mint(&mut self, decimals: u8, fee_bps: u16) -> Pubkey {
+        let key = Pubkey::new_unique();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        let mut data = vec![0; space];
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+                .unwrap();
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: fee_bps.into(),
+        };
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = fee;
+        config.newer_transfer_fee = fee;
+        state.base = spl_token_2022::state::Mint {
+            mint_authority: Some(self.payer).into(),
+            supply: u64::MAX / 2,
+            decimals,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        self.set_account(key, spl_token_2022::ID, data);
+        key
+    }
+
+    /// A token account for `mint`, under whichever token program owns it.
+    pub fn create_token_account(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        if self.accounts[&mint].owner == spl_token_2022::ID {
+            let mint_data = &self.accounts[&mint].data;
+            let mint_state =
+                StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data).unwrap();
+            let extensions = ExtensionType::get_required_init_account_extensions(
+                &mint_state.get_extension_types().unwrap(),
+            );
+            let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(
+                &extensions,
+            )
+            .unwrap();
+            let mut data = vec![0; space];
+            let mut state =
+                StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(
+                    &mut data,
+                )
+                .unwrap();
+            for extension in extensions {
+                state.init_account_extension_from_type(extension).unwrap();
+            }
+            state.base = spl_token_2022::state::Account {
+                mint,
+                owner,
+                amount,
+                state: spl_token_2022::state::AccountState::Initialized,
+                ..spl_token_2022::state::Account::default()
+            };
+            state.pack_base();
+            state.init_account_type().unwrap();
+            self.set_account(key, spl_token_2022::ID, data);
+            return key;
+        }
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        }
+        .pack_into_slice(&mut data);
+        self.insert_token_state(key, data);
+        key
+    }
+
+    /// Freezes a token account the way a mint's freeze authority would.
+    pub fn freeze(&mut self, key: Pubkey) {
+        let account = self.accounts.get_mut(&key).unwrap();
+        let mut state = spl_token::state::Account::unpack(&account.data).unwrap();
+        state.state = spl_token::state::AccountState::Frozen;
+        state.pack_into_slice(&mut account.data);
+    }
+
+    /// Approves `delegate` to move `amount` out of a token account, as the
+    /// owner would with an spl-token Approve.
+    pub fn approve(&mut self, key: Pubkey, delegate: Pubkey, amount: u64) {
+        let account = self.accounts.get_mut(&key).unwrap();
+        let mut state = spl_token::state::Account::unpack(&account.data).unwrap();
+        state.delegate = Some(delegate).into();
+        state.delegated_amount = amount;
+        state.pack_into_slice(&mut account.data);
+    }
+
+    /// Sets a token account's balance without moving tokens, to stage a
+    /// vault holding less than its pools account for.
+    pub fn set_token_balance(&mut self, key: Pubkey, amount: u64) {
+        let account = self.accounts.get_mut(&key).unwrap();
+        let mut state = spl_token::state::Account::unpack(&account.data).unwrap();
+        state.amount = amount;
+        state.pack_into_slice(&mut account.data);
+    }
+
+    fn insert_token_state(&mut self, key: Pubkey, data: Vec<u8>) {
+        let lamports = Rent::default().minimum_balance(data.len());
+        self.accounts.insert(
+            key,
+            Account {
+                lamports,
+                data,
+                owner: spl_token::ID,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let lamports = Rent::default().minimum_balance(data.len());
+        self.accounts.insert(
+            key,
+            Account {
+                lamports,
+                data,
+                owner,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn exists(&self, key: &Pubkey) -> bool {
+        self.accounts
+            .get(key)
+            .is_some_and(|account| account.lamports > 0)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    /// The balance of an SPL Token or Token-2022 account, whose base layouts
+    /// match ahead of any extensions.
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        let account = &self.accounts[key];
+        spl_token::state::Account::unpack(&account.data[..spl_token::state::Account::LEN])
+            .unwrap()
+            .amount
+    }
+
+    pub fn get<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self
+            .accounts
+            .get(key)
+            .unwrap_or_else(|| panic!("missing account {key}"));
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Overwrites an Anchor account's data in place, keeping its length and
+    /// lamports, to stage states no instruction can reach.
+    pub fn put<T: AccountSerialize>(&mut self, key: &Pubkey, value: &T) {
+        let account = self.accounts.get_mut(key).unwrap();
+        let mut data = Vec::new();
+        value.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= account.data.len(), "value outgrew {key}");
+        account.data[..data.len()].copy_from_slice(&data);
+    }
+
+    pub fn logs(&self) -> Vec<String> {
+        LOGS.with(|logs| logs.borrow().clone())
+    }
+
+    /// The `T` events emitted by the last instruction, in order.
+    pub fn events<T: Event + AnchorDeserialize>(&self) -> Vec<T> {
+        EVENTS.with(|events| {
+            events
+                .borrow()
+                .iter()
+                .filter_map(|data| data.strip_prefix(T::DISCRIMINATOR))
+                .map(|mut data| T::deserialize(&mut data).unwrap())
+                .collect()
+        })
+    }
+
+    /// Builds and runs one program instruction, committing its account
+    /// changes only if it succeeds. Returns the instruction's return data.
+    pub fn send(&mut self, accounts: impl ToAccountMetas, data: impl InstructionData) -> Result {
+        self.send_with(accounts, data, &[])
+    }
+
+    /// Like `send`, with extra accounts appended as `remaining_accounts`.
+    pub fn send_with(
+        &mut self,
+        accounts: impl ToAccountMetas,
+        data: impl InstructionData,
+        remaining: &[AccountMeta],
+    ) -> Result {
+        let mut metas = accounts.to_account_metas(None);
+        metas.extend_from_slice(remaining);
+        self.process(&Instruction {
+            program_id: prediction_market::ID,
+            accounts: metas,
+            data: data.data(),
+        })
+    }
+
+    pub fn process(&mut self, instruction: &Instruction) -> Result {
+        NOW.with(|now| *now.borrow_mut() = self.now);
+        SLOT.with(|slot| *slot.borrow_mut() = self.slot);
+        RETURN_DATA.with(|data| *data.borrow_mut() = None);
+        LOGS.with(|logs| logs.borrow_mut().clear());
+        EVENTS.with(|events| events.borrow_mut().clear());
+
+        // Merge repeated accounts the way the runtime does
+        let mut keys: Vec<(Pubkey, bool, bool)> = Vec::new();
+        let mut positions = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            match keys.iter().position(|(key, _, _)| *key == meta.pubkey) {
+                Some(index) => {
+                    keys[index].1 |= meta.is_signer;
+                    keys[index].2 |= meta.is_writable;
+                    positions.push(index);
+                }
+                None => {
+                    positions.push(keys.len());
+                    keys.push((meta.pubkey, meta.is_signer, meta.is_writable));
+                }
+            }
+        }
+
+        // Serialize the loader's input buffer
+        let mut input: Vec<u8> = Vec::new();
+        let mut layout = Vec::with_capacity(keys.len());
+        input.extend_from_slice(&(positions.len() as u64).to_le_bytes());
+        let mut seen = vec![false; keys.len()];
+        for (i, &index) in positions.iter().enumerate() {
+            if seen[index] {
+                let first = positions.iter().position(|&p| p == index).unwrap();
+                input.push(first as u8);
+                input.extend_from_slice(&[0; 7]);
+                continue;
+            }
+            seen[index] = true;
+            let (key, is_signer, is_writable) = keys[index];
+            let account = self.accounts.get(&key).cloned().unwrap_or_default();
+            input.push(u8::MAX);
+            input.push(is_signer as u8);
+            input.push(is_writable as u8);
+            input.push(account.executable as u8);
+            input.extend_from_slice(&[0; 4]);
+            input.extend_from_slice(key.as_ref());
+            let start = input.len();
+            input.extend_from_slice(account.owner.as_ref());
+            input.extend_from_slice(&account.lamports.to_le_bytes());
+            input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            input.extend_from_slice(&account.data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            input.resize(input.len().next_multiple_of(8), 0);
+            input.extend_from_slice(&u64::MAX.to_le_bytes());
+            layout.push((i, key, is_writable, start));
+        }
+        input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&instruction.data);
+        input.extend_from_slice(instruction.program_id.as_ref());
+
+        // Run the program on an 8-byte aligned copy
+        let mut words = vec![0u64; input.len().div_ceil(8)];
+        let buffer =
+            unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, input.len()) };
+        buffer.copy_from_slice(&input);
+        let result = {
+            let (program_id, infos, data) = unsafe { deserialize(buffer.as_mut_ptr()) };
+            let infos: &[AccountInfo] = Box::leak(infos.into_boxed_slice());
+            prediction_market::entry(program_id, infos, data)
+        };
+        result?;
+
+        // Commit writable accounts back to the bank
+        let read_u64 = |at: usize| u64::from_le_bytes(buffer[at..at + 8].try_into().unwrap());
+        for (_, key, is_writable, start) in layout {
+            if !is_writable {
+                continue;
+            }
+            let owner = Pubkey::try_from(&buffer[start..start + 32]).unwrap();
+            let lamports = read_u64(start + 32);
+            let len = read_u64(start + 40) as usize;
+            let data = buffer[start + 48..start + 48 + len].to_vec();
+            if lamports == 0 {
+                self.accounts.remove(&key);
+                continue;
+            }
+            let account = self.accounts.entry(key).or_default();
+            account.owner = owner;
+            account.lamports = lamports;
+            account.data = data;
+        }
+
+        Ok(RETURN_DATA
+            .with(|data| data.borrow_mut().take())
+            .map(|(_, data)| data)
+            .unwrap_or_default())
+    }
+
+    pub fn set_time(&mut self, now: i64) {
+        self.now = now;
+    }
+
+    pub fn warp(&mut self, seconds: i64) {
+        self.now += seconds;
+    }
+}
+
+fn bincode_rent() -> Vec<u8> {
+    let rent = Rent::default();
+    let mut data = Vec::with_capacity(17);
+    data.extend_from_slice(&rent.lamports_per_byte_year.to_le_bytes());
+    data.extend_from_slice(&rent.exemption_threshold.to_le_bytes());
+    data.push(rent.burn_percent);
+    data
+}
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+/// Market options with every feature switched off, for tests to adjust.
+pub fn options() -> MarketOptions {
+    MarketOptions {
+        odds_decimals: 4,
+        vesting_period: 0,
+        outcome_count: 2,
+        outcome_caps: vec![],
+        high_value_threshold: 0,
+        min_bet_pool_bps: 0,
+        resolution_source: ResolutionSource::ManualAuthority,
+        majority_margin_bps: 0,
+        max_payout_multiplier_bps: 0,
+        claim_cooldown: 0,
+        log_level: LogLevel::Full,
+        min_side_pool: 0,
+        seed_amount: 0,
+        prior_yes_bps: None,
+        reveal_window: 0,
+        resolution_deadline: 0,
+        fee_bps: 0,
+        max_bet_amount: None,
+        challenge_period: 0,
+        dispute_bond: 0,
+        oracle_feed: None,
+        resolution_threshold: 0,
+        max_oracle_staleness: 0,
+        metadata_uri: String::new(),
+        resolver_bond: 0,
+        min_pool_to_settle: 0,
+        market_type: MarketType::Parimutuel,
+        resolvers: vec![],
+        resolver_threshold: 0,
+        creator_fee_bps: 0,
+        betting_cutoff_offset: 0,
+        resolution_criteria: String::new(),
+        currency: Currency::Spl,
+        authority_cannot_bet: false,
+        whitelist_root: None,
+        early_bird_bps: 0,
+        max_bettors: None,
+        charity: None,
+        charity_bps: 0,
+        oracle_feed_id: [0; 32],
+        creator_fee_exempt: false,
+        closing_fee_bps: None,
+        oracle_feeds: vec![],
+        min_oracle_feeds: 0,
+        dispute_bond_bps: 0,
+        initial_yes: 0,
+        initial_no: 0,
+    }
+}
+
+/// A Pyth receiver `PriceUpdateV2` account publishing `price` for `feed_id`.
+pub fn pyth_price(feed_id: [u8; 32], price: i64, publish_time: i64, verified: bool) -> Vec<u8> {
+    let mut data = anchor_lang::solana_program::hash::hashv(&[b"account:PriceUpdateV2"]).to_bytes()
+        [..8]
+        .to_vec();
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    if verified {
+        data.push(1);
+    } else {
+        data.extend_from_slice(&[0, 3]);
+    }
+    data.extend_from_slice(&feed_id);
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&10u64.to_le_bytes());
+    data.extend_from_slice(&(-8i32).to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes());
+    data
+}
+
+/// A hedge leg staking `amount` on `outcome` as the bettor's first bet.
+pub fn leg(outcome: u8, amount: u64) -> HedgeLeg {
+    HedgeLeg {
+        bet_index: 0,
+        outcome,
+        amount,
+    }
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &prediction_market::ID).0
+}
+
+/// The program's data account, where its upgrade authority is recorded.
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[prediction_market::ID.as_ref()], &ProgramData::owner()).0
+}
+
+pub fn config_pda() -> Pubkey {
+    pda(&[b"config"])
+}
+
+pub fn treasury_pda() -> Pubkey {
+    pda(&[b"treasury"])
+}
+
+pub fn market_pda(market_id: u64) -> Pubkey {
+    pda(&[b"market", &market_id.to_le_bytes()])
+}
+
+pub fn vault_pda(market: &Pubkey) -> Pubkey {
+    pda(&[b"market_vault", market.as_ref()])
+}
+
+pub fn bet_pda(market: &Pubkey, bettor: &Pubkey, bet_index: u64) -> Pubkey {
+    pda(&[
+        b"bet",
+        market.as_ref(),
+        bettor.as_ref(),
+        &bet_index.to_le_bytes(),
+    ])
+}
+
+pub fn profile_pda(bettor: &Pubkey) -> Pubkey {
+    pda(&[b"user_profile", bettor.as_ref()])
+}
+
+pub fn position_pda(market: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    pda(&[b"user_position", market.as_ref(), bettor.as_ref()])
+}
+
+pub fn stats_pda(authority: &Pubkey) -> Pubkey {
+    pda(&[b"authority_stats", authority.as_ref()])
+}
+
+pub fn campaign_pda(campaign_id: u64) -> Pubkey {
+    pda(&[b"campaign", &campaign_id.to_le_bytes()])
+}
+
+/// A harness with the config and treasury set up, a mint and a market
+/// authority, ready for markets to be created.
+pub struct World {
+    pub h: Harness,
+    pub admin: Pubkey,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// The program owning `mint`: SPL Token unless built with a fee mint.
+    pub token_program: Pubkey,
+    /// Given to every market created; merging requires matching text.
+    pub description: String,
+    /// Pays for, and is refunded, every market created; the authority
+    /// unless a test sponsors creation.
+    pub sponsor: Pubkey,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::with_mint(|h| h.create_mint(6), spl_token::ID)
+    }
+
+    /// A world whose mint is a Token-2022 mint withholding `fee_bps` of
+    /// every transfer.
+    pub fn with_transfer_fee(fee_bps: u16) -> Self {
+        Self::with_mint(|h| h.create_fee_mint(6, fee_bps), spl_token_2022::ID)
+    }
+
+    fn with_mint(create_mint: impl FnOnce(&mut Harness) -> Pubkey, token_program: Pubkey) -> Self {
+        let mut h = Harness::new();
+        let admin = h.wallet();
+        let authority = h.wallet();
+        let mint = create_mint(&mut h);
+        let payer = h.payer;
+
+        h.send(
+            prediction_market::accounts::InitializeConfig {
+                config: config_pda(),
+                payer,
+                upgrade_authority: payer,
+                program: prediction_market::ID,
+                program_data: program_data_address(),
+                system_program: system_program::ID,
+            },
+            ix::InitializeConfig { admin },
+        )
+        .unwrap();
+
+        Self {
+            h,
+            admin,
+            authority,
+            mint,
+            token_program,
+            description: "Will it rain tomorrow?".to_string(),
+            sponsor: authority,
+        }
+    }
+
+    /// Creates market `market_id` ending `duration` seconds from now,
+    /// returning the addresses it reports back.
+    pub fn create_market(
+        &mut self,
+        market_id: u64,
+        duration: i64,
+        options: MarketOptions,
+    ) -> CreatedMarket {
+        self.try_create_market(market_id, duration, options)
+            .unwrap()
+    }
+
+    pub fn try_create_market(
+        &mut self,
+        market_id: u64,
+        duration: i64,
+        options: MarketOptions,
+    ) -> std::result::Result<CreatedMarket, ProgramError> {
+        let market = market_pda(market_id);
+        let authority = self.authority;
+        let needs_creator_account = options.seed_amount + options.initial_yes + options.initial_no
+            > 0
+            || options.resolver_bond > 0
+            || matches!(options.market_type, MarketType::Lmsr { .. });
+        let authority_token_account = needs_creator_account.then(|| {
+            self.h
+                .create_token_account(self.mint, authority, 1_000_000_000)
+        });
+        let creator_fee_vault =
+            (options.creator_fee_bps > 0).then(|| pda(&[b"creator_fee_vault", market.as_ref()]));
+        let bond_vault =
+            (options.resolver_bond > 0).then(|| pda(&[b"bond_vault", market.as_ref()]));
+        let sol_vault = (options.currency == Currency::NativeSol)
+            .then(|| pda(&[b"sol_vault", market.as_ref()]));
+        let end_time = self.h.now + duration;
+        let mut options = options;
+        if options.resolution_deadline == 0 {
+            options.resolution_deadline = end_time + 7 * 24 * 60 * 60;
+        }
+        let data = self.h.send(
+            prediction_market::accounts::CreateMarket {
+                market,
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                authority,
+                config: config_pda(),
+                authority_stats: stats_pda(&authority),
+                authority_token_account,
+                bond_vault,
+                creator_fee_vault,
+                sol_vault,
+                payer: self.sponsor,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                rent: sysvar::rent::ID,
+            },
+            ix::CreateMarket {
+                market_id,
+                description: self.description.clone(),
+                end_time,
+                min_bet_amount: 1,
+                options,
+            },
+        )?;
+        Ok(CreatedMarket::deserialize(&mut data.as_slice()).unwrap())
+    }
+
+    /// A bettor wallet with a funded token account.
+    pub fn bettor(&mut self, amount: u64) -> (Pubkey, Pubkey) {
+        let bettor = self.h.wallet();
+        let token_account = self.h.create_token_account(self.mint, bettor, amount);
+        (bettor, token_account)
+    }
+
+    pub fn place_bet(
+        &mut self,
+        market_id: u64,
+        bettor: (Pubkey, Pubkey),
+        bet_index: u64,
+        outcome: u8,
+        amount: u64,
+    ) -> Result {
+        self.place_campaign_bet(market_id, bettor, bet_index, outcome, amount, None)
+    }
+
+    /// Like `place_bet`, attributing the bet to `campaign_id` and passing
+    /// its volume counter.
+    pub fn place_campaign_bet(
+        &mut self,
+        market_id: u64,
+        bettor: (Pubkey, Pubkey),
+        bet_index: u64,
+        outcome: u8,
+        amount: u64,
+        campaign_id: Option<u64>,
+    ) -> Result {
+        let market = market_pda(market_id);
+        self.h.send(
+            prediction_market::accounts::PlaceBet {
+                market,
+                bet: bet_pda(&market, &bettor.0, bet_index),
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                config: config_pda(),
+                bettor_token_account: bettor.1,
+                user_profile: profile_pda(&bettor.0),
+                user_position: position_pda(&market, &bettor.0),
+                bettor: bettor.0,
+                campaign: campaign_id.map(campaign_pda),
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::PlaceBet {
+                market_id,
+                bet_index,
+                bet_outcome: outcome,
+                amount,
+                campaign_id,
+                min_expected_ratio: None,
+                whitelist_proof: None,
+            },
+        )
+    }
+
+    /// Tops up `bettor`'s first bet on `market_id`, on `outcome`.
+    pub fn increase_bet(
+        &mut self,
+        market_id: u64,
+        bettor: (Pubkey, Pubkey),
+        outcome: u8,
+        amount: u64,
+    ) -> Result {
+        let market = market_pda(market_id);
+        let campaign = self.bet_campaign(&market, &bettor.0, 0);
+        self.h.send(
+            prediction_market::accounts::IncreaseBet {
+                market,
+                bet: bet_pda(&market, &bettor.0, 0),
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                config: config_pda(),
+                bettor_token_account: bettor.1,
+                user_profile: profile_pda(&bettor.0),
+                user_position: position_pda(&market, &bettor.0),
+                bettor: bettor.0,
+                campaign,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::IncreaseBet {
+                market_id,
+                bet_index: 0,
+                bet_outcome: outcome,
+                amount,
+            },
+        )
+    }
+
+    pub fn cancel_bet(
+        &mut self,
+        market_id: u64,
+        bettor: (Pubkey, Pubkey),
+        bet_index: u64,
+    ) -> Result {
+        let market = market_pda(market_id);
+        let campaign = self.bet_campaign(&market, &bettor.0, bet_index);
+        self.h.send(
+            prediction_market::accounts::CancelBet {
+                market,
+                bet: bet_pda(&market, &bettor.0, bet_index),
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                bettor_token_account: bettor.1,
+                user_position: position_pda(&market, &bettor.0),
+                user_profile: profile_pda(&bettor.0),
+                bettor: bettor.0,
+                campaign,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::CancelBet {
+                market_id,
+                bet_index,
+            },
+        )
+    }
+
+    pub fn reduce_bet(
+        &mut self,
+        market_id: u64,
+        bettor: (Pubkey, Pubkey),
+        bet_index: u64,
+        withdraw_amount: u64,
+    ) -> Result {
+        let market = market_pda(market_id);
+        let campaign = self.bet_campaign(&market, &bettor.0, bet_index);
+        self.h.send(
+            prediction_market::accounts::ReduceBet {
+                market,
+                bet: bet_pda(&market, &bettor.0, bet_index),
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                bettor_token_account: bettor.1,
+                user_position: position_pda(&market, &bettor.0),
+                user_profile: profile_pda(&bettor.0),
+                bettor: bettor.0,
+                campaign,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::ReduceBet {
+                market_id,
+                bet_index,
+                withdraw_amount,
+            },
+        )
+    }
+
+    /// The counter of the campaign a bet is tagged with, if it exists.
+    fn bet_campaign(&self, market: &Pubkey, bettor: &Pubkey, bet_index: u64) -> Option<Pubkey> {
+        let bet = bet_pda(market, bettor, bet_index);
+        if !self.h.exists(&bet) {
+            return None;
+        }
+        self.h.get::<Bet>(&bet).campaign_id.map(campaign_pda)
+    }
+
+    pub fn close_betting(&mut self, market_id: u64) -> Result {
+        let caller = self.h.payer;
+        self.h.send(
+            prediction_market::accounts::CloseBetting {
+                market: market_pda(market_id),
+                caller,
+            },
+            ix::CloseBetting { market_id },
+        )
+    }
+
+    pub fn resolve(&mut self, market_id: u64, outcome: u8) -> Result {
+        self.resolve_as(market_id, Resolution::Outcome(outcome))
+    }
+
+    pub fn resolve_as(&mut self, market_id: u64, resolution: Resolution) -> Result {
+        self.h.send(
+            prediction_market::accounts::ResolveMarket {
+                market: market_pda(market_id),
+                authority: self.authority,
+            },
+            ix::ResolveMarket {
+                market_id,
+                resolution,
+            },
+        )
+    }
+
+    /// Claims a bet, passing the fee accounts that exist: the treasury and
+    /// its vault once `init_treasury` ran, and the market's creator fee vault.
+    pub fn claim(&mut self, market_id: u64, bettor: (Pubkey, Pubkey), bet_index: u64) -> Result {
+        let market = market_pda(market_id);
+        let treasury_vault = Some(pda(&[b"treasury_vault", self.mint.as_ref()]))
+            .filter(|vault| self.h.exists(vault));
+        let creator_fee_vault = Some(pda(&[b"creator_fee_vault", market.as_ref()]))
+            .filter(|vault| self.h.exists(vault));
+        self.h.send(
+            prediction_market::accounts::ClaimWinnings {
+                market,
+                bet: bet_pda(&market, &bettor.0, bet_index),
+                market_vault: vault_pda(&market),
+                mint: self.mint,
+                bettor_token_account: bettor.1,
+                recipient_token_account: None,
+                treasury: treasury_vault.map(|_| treasury_pda()),
+                treasury_vault,
+                creator_fee_vault,
+                charity_token_account: None,
+                user_profile: profile_pda(&bettor.0),
+                user_position: position_pda(&market, &bettor.0),
+                bettor: bettor.0,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::ClaimWinnings {
+                market_id,
+                _bet_index: bet_index,
+            },
+        )
+    }
+
+    pub fn cancel_market(&mut self, market_id: u64) -> Result {
+        let authority = self.authority;
+        self.h.send(
+            prediction_market::accounts::CancelMarket {
+                market: market_pda(market_id),
+                authority,
+            },
+            ix::CancelMarket { market_id },
+        )
+    }
+
+    pub fn close_market(&mut self, market_id: u64) -> Result {
+        let market = market_pda(market_id);
+        let rent_sponsor = self.market(market_id).rent_sponsor;
+        self.h.send(
+            prediction_market::accounts::CloseMarket {
+                market,
+                market_vault: vault_pda(&market),
+                sol_vault: None,
+                rent_sponsor,
+                authority: self.authority,
+                authority_stats: Some(stats_pda(&self.authority)),
+                token_program: self.token_program,
+            },
+            ix::CloseMarket { market_id },
+        )
+    }
+
+    /// Sets up the treasury and its vault for the world's mint.
+    pub fn init_treasury(&mut self) -> Pubkey {
+        let payer = self.h.payer;
+        let treasury_vault = pda(&[b"treasury_vault", self.mint.as_ref()]);
+        self.h
+            .send(
+                prediction_market::accounts::InitializeTreasury {
+                    treasury: treasury_pda(),
+                    payer,
+                    upgrade_authority: payer,
+                    program: prediction_market::ID,
+                    program_data: program_data_address(),
+                    system_program: system_program::ID,
+                },
+                ix::InitializeTreasury { admin: self.admin },
+            )
+            .unwrap();
+        self.h
+            .send(
+                prediction_market::accounts::CreateTreasuryVault {
+                    treasury: treasury_pda(),
+                    treasury_vault,
+                    mint: self.mint,
+                    payer,
+                    system_program: system_program::ID,
+                    token_program: self.token_program,
+                },
+                ix::CreateTreasuryVault {},
+            )
+            .unwrap();
+        treasury_vault
+    }
+
+    /// Runs a view instruction taking only the market and decodes its
+    /// return data.
+    pub fn view<T: AnchorDeserialize>(&mut self, market_id: u64, data: impl InstructionData) -> T {
+        let data = self
+            .h
+            .send(
+                prediction_market::accounts::ViewMarket {
+                    market: market_pda(market_id),
+                },
+                data,
+            )
+            .unwrap();
+        T::deserialize(&mut &data[..]).unwrap()
+    }
+
+    /// Hedges `bettor` across markets 1 and 2 with `first` and `second`.
+    pub fn hedge(&mut self, bettor: (Pubkey, Pubkey), first: HedgeLeg, second: HedgeLeg) -> Result {
+        let (first_market, second_market) = (market_pda(1), market_pda(2));
+        let first_bet = bet_pda(&first_market, &bettor.0, first.bet_index);
+        let second_bet = bet_pda(&second_market, &bettor.0, second.bet_index);
+        self.h.send(
+            prediction_market::accounts::HedgeBet {
+                first_market,
+                second_market,
+                first_bet,
+                second_bet,
+                hedge: pda(&[b"hedge", first_bet.as_ref(), second_bet.as_ref()]),
+                first_vault: vault_pda(&first_market),
+                second_vault: vault_pda(&second_market),
+                mint: self.mint,
+                config: config_pda(),
+                bettor_token_account: bettor.1,
+                first_position: position_pda(&first_market, &bettor.0),
+                second_position: position_pda(&second_market, &bettor.0),
+                user_profile: profile_pda(&bettor.0),
+                bettor: bettor.0,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+            },
+            ix::HedgeBet {
+                first_market_id: 1,
+                second_market_id: 2,
+                first,
+                second,
+            },
+        )
+    }
+
+    pub fn market(&self, market_id: u64) -> Market {
+        self.h.get(&market_pda(market_id))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,247 @@
+mod common;
+
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::{system_program, AnchorDeserialize};
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, AuthorityStats, Config, CreatedMarket, ErrorCode, MarketOptions,
+    MarketRegistry, Treasury,
+};
+
+fn set_paused(w: &mut World, admin: Pubkey, paused: bool) -> Result {
+    w.h.send(
+        accounts::SetPaused {
+            config: config_pda(),
+            admin,
+        },
+        ix::SetPaused { paused },
+    )
+}
+
+#[test]
+fn pausing_stops_bets_but_not_claims() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    let stranger = w.h.wallet();
+    assert_eq!(
+        set_paused(&mut w, stranger, true).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    let admin = w.admin;
+    set_paused(&mut w, admin, true).unwrap();
+    assert!(w.h.get::<Config>(&config_pda()).paused);
+    assert_eq!(
+        w.place_bet(1, yes, 1, 0, 100).unwrap_err(),
+        err(ErrorCode::ProgramPaused)
+    );
+
+    // Bettors can still exit
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+
+    set_paused(&mut w, admin, false).unwrap();
+    w.create_market(2, 3_600, options());
+    w.place_bet(2, no, 0, 0, 100).unwrap();
+}
+
+/// Sends the three singleton initializers signed by `upgrade_authority`.
+fn initialize(h: &mut Harness, upgrade_authority: Pubkey) -> [Result; 3] {
+    let payer = h.payer;
+    let admin = h.wallet();
+    [
+        h.send(
+            accounts::InitializeConfig {
+                config: config_pda(),
+                payer,
+                upgrade_authority,
+                program: prediction_market::ID,
+                program_data: program_data_address(),
+                system_program: system_program::ID,
+            },
+            ix::InitializeConfig { admin },
+        ),
+        h.send(
+            accounts::InitializeTreasury {
+                treasury: treasury_pda(),
+                payer,
+                upgrade_authority,
+                program: prediction_market::ID,
+                program_data: program_data_address(),
+                system_program: system_program::ID,
+            },
+            ix::InitializeTreasury { admin },
+        ),
+        h.send(
+            accounts::InitializeRegistry {
+                registry: pda(&[b"market_registry"]),
+                payer,
+                upgrade_authority,
+                program: prediction_market::ID,
+                program_data: program_data_address(),
+                system_program: system_program::ID,
+            },
+            ix::InitializeRegistry {
+                first_market_id: 1_000,
+            },
+        ),
+    ]
+}
+
+#[test]
+fn only_the_upgrade_authority_initializes() {
+    let mut h = Harness::new();
+    let deployer = h.wallet();
+    h.deploy(Some(deployer));
+
+    // Whoever pays first cannot name themselves admin
+    let payer = h.payer;
+    for result in initialize(&mut h, payer) {
+        assert_eq!(result.unwrap_err(), err(ErrorCode::NotUpgradeAuthority));
+    }
+    assert!(!h.exists(&config_pda()));
+
+    for result in initialize(&mut h, deployer) {
+        result.unwrap();
+    }
+    assert!(h.exists(&config_pda()));
+    assert!(h.exists(&treasury_pda()));
+    let registry: MarketRegistry = h.get(&pda(&[b"market_registry"]));
+    assert_eq!(registry.next_market_id, 1_000);
+    assert_eq!(h.get::<Treasury>(&treasury_pda()).total_collected, 0);
+}
+
+#[test]
+fn a_frozen_program_cannot_be_initialized() {
+    let mut h = Harness::new();
+    h.deploy(None);
+
+    let payer = h.payer;
+    for result in initialize(&mut h, payer) {
+        assert_eq!(result.unwrap_err(), err(ErrorCode::NotUpgradeAuthority));
+    }
+}
+
+fn registry_pda() -> Pubkey {
+    pda(&[b"market_registry"])
+}
+
+fn create_market_auto(w: &mut World) -> CreatedMarket {
+    let market_id = w.h.get::<MarketRegistry>(&registry_pda()).next_market_id;
+    let market = market_pda(market_id);
+    let authority = w.authority;
+    let payer = w.h.payer;
+    let end_time = w.h.now + 3_600;
+    let data =
+        w.h.send(
+            accounts::CreateMarketAuto {
+                registry: registry_pda(),
+                market,
+                market_vault: vault_pda(&market),
+                mint: w.mint,
+                authority,
+                config: config_pda(),
+                authority_stats: stats_pda(&authority),
+                authority_token_account: None,
+                bond_vault: None,
+                creator_fee_vault: None,
+                sol_vault: None,
+                payer,
+                system_program: system_program::ID,
+                token_program: w.token_program,
+                rent: sysvar::rent::ID,
+            },
+            ix::CreateMarketAuto {
+                description: w.description.clone(),
+                end_time,
+                min_bet_amount: 1,
+                options: MarketOptions {
+                    resolution_deadline: end_time + 86_400,
+                    ..options()
+                },
+            },
+        )
+        .unwrap();
+    CreatedMarket::deserialize(&mut data.as_slice()).unwrap()
+}
+
+#[test]
+fn the_registry_hands_out_sequential_ids() {
+    let mut w = World::new();
+    let payer = w.h.payer;
+    w.h.send(
+        accounts::InitializeRegistry {
+            registry: registry_pda(),
+            payer,
+            upgrade_authority: payer,
+            program: prediction_market::ID,
+            program_data: program_data_address(),
+            system_program: system_program::ID,
+        },
+        ix::InitializeRegistry {
+            first_market_id: 100,
+        },
+    )
+    .unwrap();
+
+    let first = create_market_auto(&mut w);
+    let second = create_market_auto(&mut w);
+    assert_eq!((first.market_id, second.market_id), (100, 101));
+    assert_eq!(first.market, market_pda(100));
+    assert_eq!(second.market_vault, vault_pda(&market_pda(101)));
+    assert_eq!(w.market(101).market_id, 101);
+    assert_eq!(
+        w.h.get::<MarketRegistry>(&registry_pda()).next_market_id,
+        102
+    );
+
+    // Ids picked by hand below the registry's stay available
+    w.create_market(1, 3_600, options());
+    assert_eq!(create_market_auto(&mut w).market_id, 102);
+}
+
+#[test]
+fn an_authority_is_held_to_the_open_market_cap() {
+    let mut w = World::new();
+    let admin = w.admin;
+    w.h.send(
+        accounts::SetMaxOpenMarkets {
+            config: config_pda(),
+            admin,
+        },
+        ix::SetMaxOpenMarkets {
+            max_open_markets: 2,
+        },
+    )
+    .unwrap();
+
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 7_200, options());
+    assert_eq!(
+        w.try_create_market(3, 3_600, options()).unwrap_err(),
+        err(ErrorCode::TooManyOpenMarkets)
+    );
+    let stats: AuthorityStats = w.h.get(&stats_pda(&w.authority));
+    assert_eq!(stats.open_market_count, 2);
+
+    // Resolving alone keeps the slot; closing frees it
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    assert!(w.try_create_market(3, 3_600, options()).is_err());
+    w.close_market(1).unwrap();
+    w.create_market(3, 3_600, options());
+    let stats: AuthorityStats = w.h.get(&stats_pda(&w.authority));
+    assert_eq!(stats.open_market_count, 2);
+
+    // The cap is per authority
+    w.authority = w.h.wallet();
+    w.create_market(4, 3_600, options());
+}
@@ -0,0 +1,413 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, BondReleased, ErrorCode, MarketOptions, MarketStatus,
+    MarketVoided, Treasury, VoidReason, ADJUDICATION_PERIOD,
+};
+
+const CHALLENGE_PERIOD: i64 = 86_400;
+
+fn dispute_options() -> MarketOptions {
+    MarketOptions {
+        challenge_period: CHALLENGE_PERIOD,
+        dispute_bond: 50,
+        ..options()
+    }
+}
+
+fn bond_vault() -> Pubkey {
+    pda(&[b"dispute_bond_vault", market_pda(1).as_ref()])
+}
+
+fn dispute(w: &mut World, disputer: (Pubkey, Pubkey)) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::DisputeResolution {
+            market,
+            bet: bet_pda(&market, &disputer.0, 0),
+            disputer_token_account: disputer.1,
+            market_vault: vault_pda(&market),
+            dispute_bond_vault: bond_vault(),
+            mint: w.mint,
+            disputer: disputer.0,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        },
+        ix::DisputeResolution {
+            market_id: 1,
+            _bet_index: 0,
+        },
+    )
+}
+
+fn adjudicate(w: &mut World, disputer: (Pubkey, Pubkey), admin: Pubkey, uphold: bool) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::AdjudicateDispute {
+            market,
+            market_vault: vault_pda(&market),
+            dispute_bond_vault: bond_vault(),
+            disputer_token_account: disputer.1,
+            treasury: treasury_pda(),
+            treasury_vault: pda(&[b"treasury_vault", w.mint.as_ref()]),
+            mint: w.mint,
+            disputer: disputer.0,
+            config: config_pda(),
+            admin,
+            token_program: spl_token::ID,
+        },
+        ix::AdjudicateDispute {
+            market_id: 1,
+            uphold,
+        },
+    )
+}
+
+fn void_unadjudicated(w: &mut World, disputer: (Pubkey, Pubkey)) -> Result {
+    let market = market_pda(1);
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::VoidUnadjudicated {
+            market,
+            market_vault: vault_pda(&market),
+            dispute_bond_vault: bond_vault(),
+            disputer_token_account: disputer.1,
+            mint: w.mint,
+            disputer: disputer.0,
+            caller,
+            token_program: spl_token::ID,
+        },
+        ix::VoidUnadjudicated { market_id: 1 },
+    )
+}
+
+/// Market 1 resolved to YES with `stakes` on YES and NO, each bettor funded
+/// with `funds`.
+fn resolved_with(
+    options: MarketOptions,
+    stakes: [u64; 2],
+    funds: u64,
+) -> (World, (Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    let mut w = World::new();
+    w.init_treasury();
+    w.create_market(1, 3_600, options);
+    let yes = w.bettor(funds);
+    let no = w.bettor(funds);
+    w.place_bet(1, yes, 0, 0, stakes[0]).unwrap();
+    w.place_bet(1, no, 0, 1, stakes[1]).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    (w, yes, no)
+}
+
+fn scaled_bond() -> MarketOptions {
+    MarketOptions {
+        dispute_bond_bps: 5_000,
+        ..dispute_options()
+    }
+}
+
+#[test]
+fn the_bond_scales_with_the_pool() {
+    // Half of a 400 pool
+    let (mut w, _, no) = resolved_with(scaled_bond(), [300, 100], 1_000);
+    assert_eq!(w.market(1).required_dispute_bond().unwrap(), 200);
+    dispute(&mut w, no).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 1_000 - 100 - 200);
+    assert_eq!(w.h.token_balance(&bond_vault()), 200);
+
+    // Half of a pool ten times larger
+    let (mut w, _, no) = resolved_with(scaled_bond(), [3_000, 1_000], 10_000);
+    dispute(&mut w, no).unwrap();
+    assert_eq!(w.h.token_balance(&bond_vault()), 2_000);
+}
+
+#[test]
+fn the_flat_bond_is_the_floor_on_small_pools() {
+    // Half of a 60 pool is under the flat 50
+    let (mut w, _, no) = resolved_with(scaled_bond(), [40, 20], 1_000);
+    dispute(&mut w, no).unwrap();
+    assert_eq!(w.h.token_balance(&bond_vault()), 50);
+}
+
+#[test]
+fn an_underfunded_dispute_is_refused() {
+    // 225 is owed and only 150 is left after the stake
+    let (mut w, _, no) = resolved_with(scaled_bond(), [300, 150], 300);
+    assert_eq!(
+        dispute(&mut w, no).unwrap_err(),
+        err(ErrorCode::InsufficientDisputeBond)
+    );
+    assert!(!w.market(1).dispute_pending);
+    assert_eq!(w.h.token_balance(&no.1), 150);
+}
+
+/// Market 1 resolved to YES with 300 on YES and 100 on NO.
+fn resolved() -> (World, (Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    resolved_with(dispute_options(), [300, 100], 1_000)
+}
+
+#[test]
+fn claims_wait_out_the_challenge_window() {
+    let (mut w, yes, _) = resolved();
+
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err(ErrorCode::ChallengeWindowActive)
+    );
+    w.h.warp(CHALLENGE_PERIOD);
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+}
+
+#[test]
+fn an_upheld_dispute_forfeits_the_bond() {
+    let (mut w, yes, no) = resolved();
+
+    dispute(&mut w, no).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 850);
+    assert!(w.market(1).dispute_pending);
+
+    // Nothing leaves the vault while the dispute is pending
+    w.h.warp(CHALLENGE_PERIOD);
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err(ErrorCode::DisputePending)
+    );
+
+    let stranger = w.h.wallet();
+    assert_eq!(
+        adjudicate(&mut w, no, stranger, true).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    let admin = w.admin;
+    adjudicate(&mut w, no, admin, true).unwrap();
+
+    let treasury_vault = pda(&[b"treasury_vault", w.mint.as_ref()]);
+    assert_eq!(w.h.token_balance(&treasury_vault), 50);
+    assert_eq!(w.h.get::<Treasury>(&treasury_pda()).total_collected, 50);
+    assert!(!w.h.exists(&bond_vault()));
+    assert_eq!(w.market(1).winning_outcome, Some(0));
+
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+}
+
+#[test]
+fn an_overturned_dispute_refunds_everyone() {
+    let (mut w, yes, no) = resolved();
+
+    dispute(&mut w, no).unwrap();
+    let admin = w.admin;
+    adjudicate(&mut w, no, admin, false).unwrap();
+
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert!(!market.dispute_pending);
+    assert_eq!(w.h.token_balance(&no.1), 900);
+
+    w.claim(1, yes, 0).unwrap();
+    w.claim(1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_000);
+    assert_eq!(w.h.token_balance(&no.1), 1_000);
+}
+
+#[test]
+fn disputes_close_with_the_challenge_window() {
+    let (mut w, _, no) = resolved();
+
+    w.h.warp(CHALLENGE_PERIOD);
+    assert_eq!(
+        dispute(&mut w, no).unwrap_err(),
+        err(ErrorCode::ChallengeWindowClosed)
+    );
+}
+
+#[test]
+fn a_market_takes_one_dispute_at_a_time() {
+    let (mut w, yes, no) = resolved();
+
+    // The second bond vault cannot be created over the first
+    dispute(&mut w, no).unwrap();
+    assert!(dispute(&mut w, yes).is_err());
+    assert_eq!(w.h.token_balance(&yes.1), 700);
+}
+
+#[test]
+fn an_unruled_dispute_voids_the_market_after_the_deadline() {
+    let (mut w, yes, no) = resolved();
+    dispute(&mut w, no).unwrap();
+    let deadline = w.market(1).adjudication_deadline();
+    assert_eq!(deadline, w.market(1).disputed_until + ADJUDICATION_PERIOD);
+
+    // Until the deadline only the admin can settle it
+    w.h.set_time(deadline);
+    assert_eq!(
+        void_unadjudicated(&mut w, no).unwrap_err(),
+        err(ErrorCode::AdjudicationWindowOpen)
+    );
+
+    // After it the admin can no longer rule, and anyone can void
+    w.h.set_time(deadline + 1);
+    let admin = w.admin;
+    assert_eq!(
+        adjudicate(&mut w, no, admin, true).unwrap_err(),
+        err(ErrorCode::AdjudicationDeadlinePassed)
+    );
+    assert_eq!(
+        void_unadjudicated(&mut w, (no.0, yes.1)).unwrap_err(),
+        err(ErrorCode::UnauthorizedClaimer)
+    );
+    void_unadjudicated(&mut w, no).unwrap();
+
+    let market = w.market(1);
+    assert_eq!(market.status, MarketStatus::Cancelled);
+    assert!(market.refund_only);
+    assert!(!market.dispute_pending);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::Unadjudicated);
+    assert!(!w.h.exists(&bond_vault()));
+
+    // The bond comes back and every stake is refunded
+    assert_eq!(w.h.token_balance(&no.1), 900);
+    w.claim(1, yes, 0).unwrap();
+    w.claim(1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_000);
+    assert_eq!(w.h.token_balance(&no.1), 1_000);
+    assert_eq!(void_unadjudicated(&mut w, no).unwrap_err(), err_missing());
+}
+
+const RESOLVER_BOND: u64 = 200;
+
+fn resolver_bond_vault() -> Pubkey {
+    pda(&[b"bond_vault", market_pda(1).as_ref()])
+}
+
+/// Releases the resolver bond into `recipient`, or into the treasury when
+/// `recipient` is None.
+fn release_bond(w: &mut World, recipient: Option<Pubkey>) -> Result {
+    let market = market_pda(1);
+    let rent_sponsor = w.market(1).rent_sponsor;
+    let to_treasury = recipient.is_none();
+    w.h.send(
+        accounts::ReleaseBond {
+            market,
+            market_vault: vault_pda(&market),
+            bond_vault: resolver_bond_vault(),
+            mint: w.mint,
+            recipient_token_account: recipient,
+            treasury: to_treasury.then(treasury_pda),
+            treasury_vault: to_treasury.then(|| pda(&[b"treasury_vault", w.mint.as_ref()])),
+            rent_sponsor,
+            token_program: spl_token::ID,
+        },
+        ix::ReleaseBond { market_id: 1 },
+    )
+}
+
+fn bonded() -> MarketOptions {
+    MarketOptions {
+        resolver_bond: RESOLVER_BOND,
+        ..dispute_options()
+    }
+}
+
+#[test]
+fn an_undisputed_resolution_returns_the_bond() {
+    let (mut w, _, no) = resolved_with(bonded(), [300, 100], 1_000);
+    let authority = w.authority;
+    let authority_account = w.h.create_token_account(w.mint, authority, 0);
+    assert_eq!(w.h.token_balance(&resolver_bond_vault()), RESOLVER_BOND);
+
+    // The bond waits out the challenge window
+    assert_eq!(
+        release_bond(&mut w, Some(authority_account)).unwrap_err(),
+        err(ErrorCode::BondNotReleasable)
+    );
+    w.h.warp(CHALLENGE_PERIOD);
+    assert_eq!(
+        release_bond(&mut w, Some(no.1)).unwrap_err(),
+        err(ErrorCode::UnauthorizedClaimer)
+    );
+    release_bond(&mut w, Some(authority_account)).unwrap();
+    assert_eq!(w.h.token_balance(&authority_account), RESOLVER_BOND);
+    assert!(!w.h.exists(&resolver_bond_vault()));
+    let released = &w.h.events::<BondReleased>()[0];
+    assert_eq!(
+        (released.recipient, released.amount, released.forfeited),
+        (authority, RESOLVER_BOND, false)
+    );
+    assert_eq!(w.market(1).resolver_bond, 0);
+}
+
+#[test]
+fn an_overturned_resolution_forfeits_the_bond_to_the_disputer() {
+    let (mut w, _, no) = resolved_with(bonded(), [300, 100], 1_000);
+    dispute(&mut w, no).unwrap();
+
+    // Nothing is released while the dispute is pending
+    assert_eq!(
+        release_bond(&mut w, Some(no.1)).unwrap_err(),
+        err(ErrorCode::BondNotReleasable)
+    );
+    let admin = w.admin;
+    adjudicate(&mut w, no, admin, false).unwrap();
+
+    // The bond is owed at once, without waiting for the window
+    release_bond(&mut w, Some(no.1)).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 1_000 - 100 + RESOLVER_BOND);
+    let released = &w.h.events::<BondReleased>()[0];
+    assert_eq!((released.recipient, released.forfeited), (no.0, true));
+}
+
+#[test]
+fn a_cancelled_market_forfeits_the_bond_to_the_treasury() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(1, 3_600, bonded());
+    let authority = w.authority;
+    let authority_account = w.h.create_token_account(w.mint, authority, 0);
+    w.cancel_market(1).unwrap();
+
+    assert_eq!(
+        release_bond(&mut w, Some(authority_account)).unwrap_err(),
+        err(ErrorCode::BondRecipientMissing)
+    );
+    release_bond(&mut w, None).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), RESOLVER_BOND);
+    assert_eq!(
+        w.h.get::<Treasury>(&treasury_pda()).total_collected,
+        RESOLVER_BOND
+    );
+    assert_eq!(w.h.token_balance(&authority_account), 0);
+    let released = &w.h.events::<BondReleased>()[0];
+    assert_eq!(
+        (released.recipient, released.forfeited),
+        (treasury_pda(), true)
+    );
+}
+
+#[test]
+fn a_market_left_unresolved_forfeits_the_bond_to_the_treasury() {
+    let mut w = World::new();
+    let treasury_vault = w.init_treasury();
+    w.create_market(1, 3_600, bonded());
+    let deadline = w.market(1).resolution_deadline;
+    w.h.set_time(deadline + 1);
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::VoidUnresolved {
+            market: market_pda(1),
+            caller,
+        },
+        ix::VoidUnresolved { market_id: 1 },
+    )
+    .unwrap();
+    assert!(w.market(1).resolver_defaulted);
+
+    release_bond(&mut w, None).unwrap();
+    assert_eq!(w.h.token_balance(&treasury_vault), RESOLVER_BOND);
+}
@@ -0,0 +1,292 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, EmergencyWithdrawCancelled, EmergencyWithdrawn, ErrorCode,
+    MarketStatus, MarketVoided, VoidReason, EMERGENCY_TIMELOCK,
+};
+
+fn emergency_pda(market_id: u64) -> Pubkey {
+    pda(&[b"emergency_withdrawal", market_pda(market_id).as_ref()])
+}
+
+fn request_emergency_withdraw(w: &mut World, market_id: u64, destination: Pubkey) -> Result {
+    let admin = w.admin;
+    w.h.send(
+        accounts::RequestEmergencyWithdraw {
+            config: config_pda(),
+            market: market_pda(market_id),
+            emergency_withdrawal: emergency_pda(market_id),
+            admin,
+            system_program: system_program::ID,
+        },
+        ix::RequestEmergencyWithdraw {
+            market_id,
+            destination,
+        },
+    )
+}
+
+fn emergency_withdraw(w: &mut World, admin: Pubkey, destination: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::EmergencyWithdraw {
+            config: config_pda(),
+            market,
+            market_vault: vault_pda(&market),
+            emergency_withdrawal: emergency_pda(1),
+            mint: w.mint,
+            destination,
+            admin,
+            token_program: spl_token::ID,
+        },
+        ix::EmergencyWithdraw { market_id: 1 },
+    )
+}
+
+fn cancel_emergency_withdraw(w: &mut World, admin: Pubkey) -> Result {
+    w.h.send(
+        accounts::CancelEmergencyWithdraw {
+            config: config_pda(),
+            market: market_pda(1),
+            emergency_withdrawal: emergency_pda(1),
+            admin,
+        },
+        ix::CancelEmergencyWithdraw { market_id: 1 },
+    )
+}
+
+/// Market 1 with 100 on YES and 200 on NO.
+fn staked(w: &mut World) -> ((Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 200).unwrap();
+    (yes, no)
+}
+
+/// A replacement mint and an account of `admin` holding plenty of it.
+struct Escape {
+    mint: Pubkey,
+    admin_account: Pubkey,
+}
+
+impl Escape {
+    fn new(w: &mut World) -> Self {
+        let mint = w.h.create_mint(6);
+        let admin_account = w.h.create_token_account(mint, w.admin, 1_000_000);
+        Self {
+            mint,
+            admin_account,
+        }
+    }
+
+    fn run(&self, w: &mut World, admin: Pubkey, rate_num: u64, rate_den: u64) -> Result {
+        let market = market_pda(1);
+        w.h.send(
+            accounts::EscapeToMint {
+                config: config_pda(),
+                market,
+                market_vault: vault_pda(&market),
+                emergency_withdrawal: emergency_pda(1),
+                new_mint: self.mint,
+                escape_vault: pda(&[b"escape_vault", market.as_ref()]),
+                admin_token_account: self.admin_account,
+                admin,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            ix::EscapeToMint {
+                market_id: 1,
+                rate_num,
+                rate_den,
+            },
+        )
+    }
+
+    fn claim(&self, w: &mut World, bettor: Pubkey, to: Pubkey) -> Result {
+        let market = market_pda(1);
+        w.h.send(
+            accounts::ClaimEscaped {
+                market,
+                bet: bet_pda(&market, &bettor, 0),
+                market_vault: vault_pda(&market),
+                escape_vault: pda(&[b"escape_vault", market.as_ref()]),
+                escape_mint: self.mint,
+                bettor_token_account: to,
+                user_position: position_pda(&market, &bettor),
+                bettor,
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+            },
+            ix::ClaimEscaped {
+                market_id: 1,
+                _bet_index: 0,
+            },
+        )
+    }
+}
+
+#[test]
+fn an_escape_voids_the_market_and_refunds_in_the_new_mint() {
+    let mut w = World::new();
+    let (yes, no) = staked(&mut w);
+    let escape = Escape::new(&mut w);
+    let destination = w.h.create_token_account(w.mint, w.admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+    w.h.warp(EMERGENCY_TIMELOCK);
+
+    // Two new units per old one fund the 300 staked
+    let admin = w.admin;
+    escape.run(&mut w, admin, 2, 1).unwrap();
+    assert_eq!(w.h.token_balance(&escape.admin_account), 1_000_000 - 600);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::MintEscaped);
+    assert!(!w.h.exists(&emergency_pda(1)));
+
+    for (bettor, stake) in [(yes, 100), (no, 200)] {
+        let account = w.h.create_token_account(escape.mint, bettor.0, 0);
+        escape.claim(&mut w, bettor.0, account).unwrap();
+        assert_eq!(w.h.token_balance(&account), stake * 2);
+    }
+
+    // The escaped claim closed the bet, so it cannot be paid twice
+    assert_eq!(w.claim(1, yes, 0).unwrap_err(), err_missing());
+}
+
+#[test]
+fn an_escape_needs_an_elapsed_request_from_the_admin() {
+    let mut w = World::new();
+    staked(&mut w);
+    let escape = Escape::new(&mut w);
+    let admin = w.admin;
+
+    // Without a request there is nothing to consume
+    assert_eq!(escape.run(&mut w, admin, 1, 1).unwrap_err(), err_missing());
+
+    let destination = w.h.create_token_account(w.mint, admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+    assert_eq!(
+        escape.run(&mut w, admin, 1, 1).unwrap_err(),
+        err(ErrorCode::TimelockNotElapsed)
+    );
+
+    w.h.warp(EMERGENCY_TIMELOCK);
+    let stranger = w.h.wallet();
+    assert_eq!(
+        escape.run(&mut w, stranger, 1, 1).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    assert_eq!(
+        escape.run(&mut w, admin, 1, 0).unwrap_err(),
+        err(ErrorCode::InvalidConversionRate)
+    );
+    escape.run(&mut w, admin, 1, 1).unwrap();
+}
+
+#[test]
+fn a_market_that_has_paid_out_cannot_escape() {
+    let mut w = World::new();
+    let (yes, _) = staked(&mut w);
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+
+    let escape = Escape::new(&mut w);
+    let destination = w.h.create_token_account(w.mint, w.admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+    w.h.warp(EMERGENCY_TIMELOCK);
+    let admin = w.admin;
+    assert_eq!(
+        escape.run(&mut w, admin, 1, 1).unwrap_err(),
+        err(ErrorCode::EscapeNotAllowed)
+    );
+}
+
+#[test]
+fn a_drain_waits_out_the_timelock_while_bettors_exit() {
+    let mut w = World::new();
+    let (yes, no) = staked(&mut w);
+    let destination = w.h.create_token_account(w.mint, w.admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+
+    // Only new stake is refused; a bettor can still pull theirs out
+    let late = w.bettor(1_000);
+    assert_eq!(
+        w.place_bet(1, late, 0, 0, 100).unwrap_err(),
+        err(ErrorCode::MarketHalted)
+    );
+    w.cancel_bet(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_000);
+
+    let admin = w.admin;
+    assert_eq!(
+        emergency_withdraw(&mut w, admin, destination).unwrap_err(),
+        err(ErrorCode::TimelockNotElapsed)
+    );
+    w.h.warp(EMERGENCY_TIMELOCK);
+    let stranger = w.h.wallet();
+    assert_eq!(
+        emergency_withdraw(&mut w, stranger, destination).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    emergency_withdraw(&mut w, admin, destination).unwrap();
+
+    assert_eq!(w.h.token_balance(&destination), 200);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+    assert_eq!(w.h.events::<EmergencyWithdrawn>()[0].amount, 200);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::EmergencyWithdrawn);
+    assert_eq!(w.market(1).status, MarketStatus::Cancelled);
+    assert!(!w.h.exists(&emergency_pda(1)));
+    assert_eq!(w.h.token_balance(&no.1), 800);
+}
+
+#[test]
+fn claims_are_paid_during_the_timelock() {
+    let mut w = World::new();
+    let (yes, _) = staked(&mut w);
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    let destination = w.h.create_token_account(w.mint, w.admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_200);
+
+    // The claimed funds are no longer there to drain
+    w.h.warp(EMERGENCY_TIMELOCK);
+    let admin = w.admin;
+    emergency_withdraw(&mut w, admin, destination).unwrap();
+    assert_eq!(w.h.token_balance(&destination), 0);
+}
+
+#[test]
+fn a_cancelled_request_reopens_betting() {
+    let mut w = World::new();
+    staked(&mut w);
+    let destination = w.h.create_token_account(w.mint, w.admin, 0);
+    request_emergency_withdraw(&mut w, 1, destination).unwrap();
+
+    let stranger = w.h.wallet();
+    assert_eq!(
+        cancel_emergency_withdraw(&mut w, stranger).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    let admin = w.admin;
+    cancel_emergency_withdraw(&mut w, admin).unwrap();
+    assert_eq!(w.h.events::<EmergencyWithdrawCancelled>()[0].market_id, 1);
+    assert!(!w.h.exists(&emergency_pda(1)));
+    assert!(!w.market(1).emergency_halted);
+
+    let late = w.bettor(1_000);
+    w.place_bet(1, late, 0, 0, 100).unwrap();
+    w.h.warp(EMERGENCY_TIMELOCK);
+    assert_eq!(
+        emergency_withdraw(&mut w, admin, destination).unwrap_err(),
+        err_missing()
+    );
+}
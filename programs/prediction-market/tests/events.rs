@@ -0,0 +1,108 @@
+mod common;
+
+use common::*;
+use prediction_market::{
+    BetIncreased, BetPlaced, HedgePlaced, LogLevel, MarketOptions, WinningsClaimed,
+};
+
+fn with_log_level(w: &mut World, market_id: u64, log_level: LogLevel) {
+    w.create_market(
+        market_id,
+        3_600,
+        MarketOptions {
+            log_level,
+            ..options()
+        },
+    );
+}
+
+#[test]
+fn full_logs_every_bet_and_top_up() {
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::Full);
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    let placed = w.h.events::<BetPlaced>();
+    assert_eq!(placed.len(), 1);
+    assert_eq!(placed[0].amount, 100);
+
+    w.increase_bet(1, bettor, 0, 50).unwrap();
+    let increased = w.h.events::<BetIncreased>();
+    assert_eq!(increased.len(), 1);
+    assert_eq!(increased[0].total_amount, 150);
+}
+
+#[test]
+fn minimal_logs_claims_but_not_bets() {
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::Minimal);
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    assert!(w.h.events::<BetPlaced>().is_empty());
+    w.increase_bet(1, yes, 0, 50).unwrap();
+    assert!(w.h.events::<BetIncreased>().is_empty());
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    let claimed = w.h.events::<WinningsClaimed>();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].amount, 250);
+}
+
+#[test]
+fn none_logs_neither_bets_nor_claims() {
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::None);
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    assert!(w.h.events::<BetPlaced>().is_empty());
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    assert!(w.h.events::<WinningsClaimed>().is_empty());
+}
+
+#[test]
+fn hedges_are_logged_when_either_market_logs_in_full() {
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::Minimal);
+    with_log_level(&mut w, 2, LogLevel::Minimal);
+    let bettor = w.bettor(1_000);
+    w.hedge(bettor, leg(0, 100), leg(1, 100)).unwrap();
+    assert!(w.h.events::<HedgePlaced>().is_empty());
+
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::Minimal);
+    with_log_level(&mut w, 2, LogLevel::Full);
+    let bettor = w.bettor(1_000);
+    w.hedge(bettor, leg(0, 100), leg(1, 100)).unwrap();
+    assert_eq!(w.h.events::<HedgePlaced>().len(), 1);
+    assert_eq!(w.h.events::<BetPlaced>().len(), 1);
+}
+
+#[test]
+fn bets_report_the_odds_they_leave_behind() {
+    let mut w = World::new();
+    with_log_level(&mut w, 1, LogLevel::Full);
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+
+    let mut place = |bettor, bet_index, outcome, amount| {
+        w.place_bet(1, bettor, bet_index, outcome, amount).unwrap();
+        let placed = w.h.events::<BetPlaced>().remove(0);
+        (placed.outcome_pools, placed.implied_probabilities_bps)
+    };
+    assert_eq!(place(yes, 0, 0, 100), (vec![100, 0], vec![10_000, 0]));
+    assert_eq!(place(no, 0, 1, 300), (vec![100, 300], vec![2_500, 7_500]));
+    assert_eq!(place(yes, 1, 0, 200), (vec![300, 300], vec![5_000, 5_000]));
+    // 300 / 700 and 400 / 700, floored
+    assert_eq!(place(no, 1, 1, 100), (vec![300, 400], vec![4_285, 5_714]));
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::*;
+use prediction_market::{Bet, ErrorCode, MarketOptions, MarketType};
+
+const LIQUIDITY: u64 = 1_000;
+
+fn lmsr_options() -> MarketOptions {
+    MarketOptions {
+        market_type: MarketType::Lmsr {
+            liquidity: LIQUIDITY,
+        },
+        ..options()
+    }
+}
+
+/// b ln(e^(q0/b) + e^(q1/b)), in floating point, as a reference.
+fn cost(q0: u64, q1: u64) -> f64 {
+    let b = LIQUIDITY as f64;
+    b * ((q0 as f64 / b).exp() + (q1 as f64 / b).exp()).ln()
+}
+
+#[test]
+fn creation_funds_the_worst_case_loss() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, lmsr_options());
+
+    // b ln 2 = 693.15, rounded up with a unit to spare
+    let vault = w.h.token_balance(&vault_pda(&market_pda(1)));
+    assert_eq!(vault, 695);
+    assert_eq!(w.market(1).lmsr_subsidy, 695);
+}
+
+#[test]
+fn shares_cost_the_change_in_the_cost_function() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, lmsr_options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+
+    let quoted = w.market(1).stake_for(0, 100).unwrap();
+    assert_eq!(quoted, (cost(100, 0) - cost(0, 0)).ceil() as u64);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_000 - quoted);
+
+    let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &yes.0, 0));
+    assert_eq!(bet.shares, 100);
+    assert_eq!(bet.amount, quoted);
+
+    // Buying the other side back to even is cheaper
+    let market = w.market(1);
+    assert_eq!(market.outcome_shares, vec![100, 0]);
+    let counter = market.stake_for(1, 100).unwrap();
+    assert_eq!(counter, (cost(100, 100) - cost(100, 0)).ceil() as u64);
+    assert!(counter < quoted);
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    assert_eq!(w.market(1).outcome_shares, vec![100, 100]);
+}
+
+#[test]
+fn prices_follow_the_shares_sold() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, lmsr_options());
+    assert_eq!(w.market(1).implied_probability_at(0, 1_000_000), 500_000);
+
+    let yes = w.bettor(10_000);
+    w.place_bet(1, yes, 0, 0, 500).unwrap();
+
+    // 1 / (1 + e^-0.5) = 0.622459
+    let market = w.market(1);
+    let yes_price = market.implied_probability_at(0, 1_000_000);
+    let no_price = market.implied_probability_at(1, 1_000_000);
+    assert!(yes_price.abs_diff(622_459) <= 2, "{yes_price}");
+    assert!((yes_price + no_price).abs_diff(1_000_000) <= 2);
+}
+
+#[test]
+fn sequential_buys_cost_more_and_raise_the_price() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, lmsr_options());
+    let yes = w.bettor(100_000);
+
+    let mut last_cost = 0;
+    let mut last_price = w.market(1).implied_probability_at(0, 1_000_000);
+    for bet_index in 0..5 {
+        let quoted = w.market(1).stake_for(0, 200).unwrap();
+        let before = w.h.token_balance(&yes.1);
+        w.place_bet(1, yes, bet_index, 0, 200).unwrap();
+        assert_eq!(before - w.h.token_balance(&yes.1), quoted);
+
+        let price = w.market(1).implied_probability_at(0, 1_000_000);
+        assert!(quoted > last_cost, "{quoted} after {last_cost}");
+        assert!(price > last_price, "{price} after {last_price}");
+        (last_cost, last_price) = (quoted, price);
+    }
+
+    // Each lot costs the change in the cost function, never below it
+    let shares = w.market(1).outcome_shares[0];
+    assert_eq!(shares, 1_000);
+    assert!(last_cost as f64 >= cost(1_000, 0) - cost(800, 0));
+    assert!(last_price < 1_000_000);
+}
+
+#[test]
+fn each_winning_share_pays_one_unit() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, lmsr_options());
+    let yes = w.bettor(10_000);
+    let no = w.bettor(10_000);
+    w.place_bet(1, yes, 0, 0, 800).unwrap();
+    w.place_bet(1, no, 0, 1, 300).unwrap();
+    let paid = 20_000 - w.h.token_balance(&yes.1) - w.h.token_balance(&no.1);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    let before = w.h.token_balance(&yes.1);
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), before + 800);
+    assert_eq!(w.claim(1, no, 0).unwrap_err(), err(ErrorCode::LosingBet));
+
+    // The subsidy covers whatever the market maker lost
+    let vault = w.h.token_balance(&vault_pda(&market_pda(1)));
+    assert_eq!(vault, 695 + paid - 800);
+}
+
+#[test]
+fn lmsr_markets_refuse_seed_liquidity() {
+    let mut w = World::new();
+    let result = w.try_create_market(
+        1,
+        3_600,
+        MarketOptions {
+            seed_amount: 100,
+            ..lmsr_options()
+        },
+    );
+    assert_eq!(result.unwrap_err(), err(ErrorCode::InvalidLmsrConfig));
+    let result = w.try_create_market(
+        1,
+        3_600,
+        MarketOptions {
+            market_type: MarketType::Lmsr { liquidity: 0 },
+            ..options()
+        },
+    );
+    assert_eq!(result.unwrap_err(), err(ErrorCode::InvalidLmsrConfig));
+}
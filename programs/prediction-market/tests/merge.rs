@@ -0,0 +1,264 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{accounts, instruction as ix, AuthorityStats, ErrorCode, MarketOptions};
+
+fn merge(w: &mut World, admin: Pubkey) -> Result {
+    let source = market_pda(1);
+    let dest = market_pda(2);
+    let authority = w.market(1).authority;
+    w.h.send(
+        accounts::MergeMarkets {
+            source_market: source,
+            source_vault: vault_pda(&source),
+            dest_market: dest,
+            dest_vault: vault_pda(&dest),
+            mint: w.mint,
+            source_authority_stats: Some(stats_pda(&authority)),
+            config: config_pda(),
+            admin,
+            token_program: spl_token::ID,
+        },
+        ix::MergeMarkets {
+            source_market_id: 1,
+            dest_market_id: 2,
+        },
+    )
+}
+
+fn claim_merged(w: &mut World, bettor: (Pubkey, Pubkey)) -> Result {
+    let source = market_pda(1);
+    let dest = market_pda(2);
+    w.h.send(
+        accounts::ClaimMergedWinnings {
+            source_market: source,
+            bet: bet_pda(&source, &bettor.0, 0),
+            dest_market: dest,
+            dest_vault: vault_pda(&dest),
+            mint: w.mint,
+            bettor_token_account: bettor.1,
+            treasury: None,
+            treasury_vault: None,
+            creator_fee_vault: None,
+            charity_token_account: None,
+            user_profile: profile_pda(&bettor.0),
+            user_position: position_pda(&source, &bettor.0),
+            bettor: bettor.0,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::ClaimMergedWinnings {
+            source_market_id: 1,
+            dest_market_id: 2,
+            _bet_index: 0,
+        },
+    )
+}
+
+#[test]
+fn merged_bettors_claim_from_the_destination() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let source_yes = w.bettor(1_000);
+    let dest_yes = w.bettor(1_000);
+    let dest_no = w.bettor(1_000);
+    w.place_bet(1, source_yes, 0, 0, 100).unwrap();
+    w.place_bet(2, dest_yes, 0, 0, 300).unwrap();
+    w.place_bet(2, dest_no, 0, 1, 400).unwrap();
+
+    let admin = w.admin;
+    merge(&mut w, admin).unwrap();
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(2))), 800);
+    assert_eq!(w.market(1).merged_into, Some(2));
+    let dest = w.market(2);
+    assert_eq!(dest.outcome_pools, vec![400, 400]);
+    assert_eq!(dest.bettor_count, 3);
+
+    // The source is frozen and its authority's slot freed
+    let authority = w.authority;
+    assert_eq!(
+        w.h.get::<AuthorityStats>(&stats_pda(&authority))
+            .open_market_count,
+        1
+    );
+    let late = w.bettor(1_000);
+    assert_eq!(
+        w.place_bet(1, late, 0, 0, 100).unwrap_err(),
+        err(ErrorCode::MarketMerged)
+    );
+
+    w.h.warp(3_600);
+    w.close_betting(2).unwrap();
+    w.resolve(2, 0).unwrap();
+
+    assert!(w.claim(1, source_yes, 0).is_err());
+    claim_merged(&mut w, source_yes).unwrap();
+    w.claim(2, dest_yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&source_yes.1), 1_100);
+    assert_eq!(w.h.token_balance(&dest_yes.1), 1_300);
+    assert!(!w.h.exists(&bet_pda(&market_pda(1), &source_yes.0, 0)));
+    assert_eq!(w.market(1).live_bets, 0);
+}
+
+#[test]
+fn only_the_admin_merges() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+
+    let authority = w.authority;
+    assert_eq!(
+        merge(&mut w, authority).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+}
+
+#[test]
+fn merged_markets_must_share_their_terms() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(
+        2,
+        3_600,
+        MarketOptions {
+            min_side_pool: 10,
+            ..options()
+        },
+    );
+    w.description = "Will it snow tomorrow?".to_string();
+    w.create_market(3, 3_600, options());
+    w.h.warp(1);
+    w.create_market(4, 3_599, options());
+
+    let admin = w.admin;
+    assert_eq!(
+        merge(&mut w, admin).unwrap_err(),
+        err(ErrorCode::MarketsNotMergeable)
+    );
+    for dest_market_id in [3, 4] {
+        let source = market_pda(1);
+        let dest = market_pda(dest_market_id);
+        let result = w.h.send(
+            accounts::MergeMarkets {
+                source_market: source,
+                source_vault: vault_pda(&source),
+                dest_market: dest,
+                dest_vault: vault_pda(&dest),
+                mint: w.mint,
+                source_authority_stats: None,
+                config: config_pda(),
+                admin,
+                token_program: spl_token::ID,
+            },
+            ix::MergeMarkets {
+                source_market_id: 1,
+                dest_market_id,
+            },
+        );
+        assert_eq!(result.unwrap_err(), err(ErrorCode::MarketsNotMergeable));
+    }
+}
+
+#[test]
+fn settled_markets_cannot_merge() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    w.h.warp(3_600);
+    w.close_betting(2).unwrap();
+    w.resolve(2, 0).unwrap();
+
+    let admin = w.admin;
+    assert!(merge(&mut w, admin).is_err());
+    assert_eq!(w.market(1).merged_into, None);
+}
+
+#[test]
+fn losing_source_bets_close_and_the_source_closes() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let source_yes = w.bettor(1_000);
+    let source_no = w.bettor(1_000);
+    let dest_no = w.bettor(1_000);
+    w.place_bet(1, source_yes, 0, 0, 100).unwrap();
+    w.place_bet(1, source_no, 0, 1, 100).unwrap();
+    w.place_bet(2, dest_no, 0, 1, 100).unwrap();
+
+    let admin = w.admin;
+    merge(&mut w, admin).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(2).unwrap();
+    w.resolve(2, 0).unwrap();
+    claim_merged(&mut w, source_yes).unwrap();
+    assert_eq!(
+        claim_merged(&mut w, source_no).unwrap_err(),
+        err(ErrorCode::LosingBet)
+    );
+
+    // The loss is judged by the destination, which must be passed
+    let source = market_pda(1);
+    let losing_bet = bet_pda(&source, &source_no.0, 0);
+    let close_losing = |w: &mut World, dest_market| {
+        w.h.send(
+            accounts::CloseLosingBet {
+                market: source,
+                bet: losing_bet,
+                dest_market,
+                bettor: source_no.0,
+            },
+            ix::CloseLosingBet {
+                market_id: 1,
+                _bet_index: 0,
+            },
+        )
+    };
+    assert_eq!(
+        close_losing(&mut w, None).unwrap_err(),
+        err(ErrorCode::MarketNotMerged)
+    );
+    close_losing(&mut w, Some(market_pda(2))).unwrap();
+    assert!(!w.h.exists(&losing_bet));
+    assert_eq!(w.market(1).live_bets, 0);
+
+    w.close_market(1).unwrap();
+    assert!(!w.h.exists(&source));
+}
+
+#[test]
+fn merged_losing_bets_close_in_a_batch() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let source_no = w.bettor(1_000);
+    let dest_yes = w.bettor(1_000);
+    w.place_bet(1, source_no, 0, 1, 100).unwrap();
+    w.place_bet(2, dest_yes, 0, 0, 100).unwrap();
+
+    let admin = w.admin;
+    merge(&mut w, admin).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(2).unwrap();
+    w.resolve(2, 0).unwrap();
+
+    let source = market_pda(1);
+    let losing_bet = bet_pda(&source, &source_no.0, 0);
+    w.h.send_with(
+        accounts::CloseBetsBatch {
+            market: source,
+            dest_market: Some(market_pda(2)),
+        },
+        ix::CloseBetsBatch { market_id: 1 },
+        &[
+            AccountMeta::new(losing_bet, false),
+            AccountMeta::new(source_no.0, false),
+        ],
+    )
+    .unwrap();
+    assert!(!w.h.exists(&losing_bet));
+    assert_eq!(w.market(1).live_bets, 0);
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use anchor_lang::system_program;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, ErrorCode, MarketOptions, MetadataUpdated, Resolution,
+    MAX_DESCRIPTION_LEN, MAX_EXTENDED_DESCRIPTION_LEN, MAX_METADATA_URI_LEN,
+    MAX_RESOLUTION_CRITERIA_LEN,
+};
+
+fn update_metadata(w: &mut World, description: String) -> Result {
+    let rent_sponsor = w.market(1).rent_sponsor;
+    let authority = w.authority;
+    w.h.send(
+        accounts::UpdateMetadata {
+            market: market_pda(1),
+            rent_sponsor,
+            authority,
+            system_program: system_program::ID,
+        },
+        ix::UpdateMetadata {
+            market_id: 1,
+            description,
+        },
+    )
+}
+
+#[test]
+fn a_longer_description_grows_the_account_and_reads_back() {
+    let mut w = World::new();
+    let sponsor = w.h.wallet();
+    w.sponsor = sponsor;
+    w.create_market(1, 3_600, options());
+    let market = market_pda(1);
+    let len_before = w.h.accounts[&market].data.len();
+    let authority = w.authority;
+    let authority_before = w.h.lamports(&authority);
+
+    let long = "Rain means at least 1mm at the city gauge. ".repeat(20);
+    update_metadata(&mut w, long.clone()).unwrap();
+    let grown = &w.h.accounts[&market];
+    assert_eq!(
+        grown.data.len(),
+        len_before + long.len() - MAX_DESCRIPTION_LEN
+    );
+    let paid = authority_before - w.h.lamports(&authority);
+    assert!(paid > 0);
+    let read_back = w.market(1);
+    assert_eq!(read_back.description, long);
+    assert_eq!(read_back.authority_rent, paid);
+
+    // Shrinking back hands the authority its growth rent, not the sponsor
+    let sponsor_before = w.h.lamports(&sponsor);
+    update_metadata(&mut w, "Will it rain?".to_string()).unwrap();
+    assert_eq!(w.h.accounts[&market].data.len(), len_before);
+    assert_eq!(w.h.lamports(&authority), authority_before);
+    assert_eq!(w.h.lamports(&sponsor), sponsor_before);
+    assert_eq!(w.market(1).authority_rent, 0);
+}
+
+#[test]
+fn closing_a_grown_market_refunds_the_authoritys_growth_rent() {
+    let mut w = World::new();
+    let sponsor = w.h.wallet();
+    w.sponsor = sponsor;
+    w.create_market(1, 3_600, options());
+    let market = market_pda(1);
+    let sponsored = w.h.lamports(&market) + w.h.lamports(&vault_pda(&market));
+    update_metadata(&mut w, "x".repeat(MAX_EXTENDED_DESCRIPTION_LEN)).unwrap();
+    let paid = w.market(1).authority_rent;
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve_as(1, Resolution::Void).unwrap();
+    let authority = w.authority;
+    let authority_before = w.h.lamports(&authority);
+    let sponsor_before = w.h.lamports(&sponsor);
+    w.close_market(1).unwrap();
+    assert_eq!(w.h.lamports(&authority), authority_before + paid);
+    assert_eq!(w.h.lamports(&sponsor), sponsor_before + sponsored);
+}
+
+#[test]
+fn the_description_is_fixed_once_bets_exist() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    assert_eq!(
+        update_metadata(&mut w, "x".repeat(MAX_EXTENDED_DESCRIPTION_LEN + 1)).unwrap_err(),
+        err(ErrorCode::DescriptionTooLong)
+    );
+
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    assert_eq!(
+        update_metadata(&mut w, "Will it rain?".to_string()).unwrap_err(),
+        err(ErrorCode::MarketHasBets)
+    );
+}
+
+fn update_metadata_uri(w: &mut World, metadata_uri: String) -> Result {
+    let authority = w.authority;
+    w.h.send(
+        accounts::UpdateMetadataUri {
+            market: market_pda(1),
+            authority,
+        },
+        ix::UpdateMetadataUri {
+            market_id: 1,
+            metadata_uri,
+        },
+    )
+}
+
+#[test]
+fn the_metadata_uri_is_bounded_and_fixed_at_resolution() {
+    let mut w = World::new();
+    let too_long = "u".repeat(MAX_METADATA_URI_LEN + 1);
+    assert_eq!(
+        w.try_create_market(
+            1,
+            3_600,
+            MarketOptions {
+                metadata_uri: too_long.clone(),
+                ..options()
+            },
+        )
+        .unwrap_err(),
+        err(ErrorCode::MetadataUriTooLong)
+    );
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            metadata_uri: "https://example.com/rain.json".to_string(),
+            ..options()
+        },
+    );
+    assert_eq!(w.market(1).metadata_uri, "https://example.com/rain.json");
+
+    let uri = "u".repeat(MAX_METADATA_URI_LEN);
+    update_metadata_uri(&mut w, uri.clone()).unwrap();
+    assert_eq!(w.market(1).metadata_uri, uri);
+    assert_eq!(w.h.events::<MetadataUpdated>()[0].metadata_uri, uri);
+    assert_eq!(
+        update_metadata_uri(&mut w, too_long).unwrap_err(),
+        err(ErrorCode::MetadataUriTooLong)
+    );
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    assert_eq!(
+        update_metadata_uri(&mut w, "https://example.com/new.json".to_string()).unwrap_err(),
+        err(ErrorCode::MarketAlreadyResolved)
+    );
+}
+
+#[test]
+fn description_and_criteria_are_bounded_separately() {
+    let mut w = World::new();
+    let with_criteria = |len: usize| MarketOptions {
+        resolution_criteria: "c".repeat(len),
+        ..options()
+    };
+
+    w.description = "d".repeat(MAX_DESCRIPTION_LEN + 1);
+    assert_eq!(
+        w.try_create_market(1, 3_600, options()).unwrap_err(),
+        err(ErrorCode::DescriptionTooLong)
+    );
+    w.description = "d".repeat(MAX_DESCRIPTION_LEN);
+    assert_eq!(
+        w.try_create_market(1, 3_600, with_criteria(MAX_RESOLUTION_CRITERIA_LEN + 1))
+            .unwrap_err(),
+        err(ErrorCode::ResolutionCriteriaTooLong)
+    );
+
+    // Both at their limits fit together
+    w.create_market(1, 3_600, with_criteria(MAX_RESOLUTION_CRITERIA_LEN));
+    let market = w.market(1);
+    assert_eq!(market.description.len(), MAX_DESCRIPTION_LEN);
+    assert_eq!(
+        market.resolution_criteria.len(),
+        MAX_RESOLUTION_CRITERIA_LEN
+    );
+}
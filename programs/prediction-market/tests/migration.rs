@@ -0,0 +1,244 @@
+mod common;
+
+use anchor_lang::prelude::Rent;
+use anchor_lang::{system_program, AccountSerialize, Discriminator, Space};
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, Bet, ErrorCode, Market, BET_VERSION, MARKET_VERSION,
+};
+
+/// Bytes the fields appended after `Market::version` take at their
+/// defaults: live_bets through resolver_defaulted.
+const MARKET_V1_TAIL: usize = 8 + 32 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 4 + 1 + 2 + 1 + 8 + 1;
+/// `Bet::fee_bps` (None), `Bet::claim_approved` and `Bet::claim_held_at`.
+const BET_V1_TAIL: usize = 1 + 1 + 8;
+
+fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn migrate_market(w: &mut World, admin: Pubkey) -> Result {
+    w.h.send(
+        accounts::MigrateMarket {
+            config: config_pda(),
+            market: market_pda(1),
+            admin,
+            system_program: system_program::ID,
+        },
+        ix::MigrateMarket { market_id: 1 },
+    )
+}
+
+fn migrate_bet(w: &mut World, bettor: Pubkey, admin: Pubkey) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::MigrateBet {
+            config: config_pda(),
+            market,
+            bet: bet_pda(&market, &bettor, 0),
+            admin,
+            system_program: system_program::ID,
+        },
+        ix::MigrateBet {
+            market_id: 1,
+            _bettor: bettor,
+            _bet_index: 0,
+        },
+    )
+}
+
+/// Rewrites the market as a version 1 account: written at its old, shorter
+/// size, before the fields that followed `version` existed.
+fn write_v1_market(w: &mut World) {
+    let key = market_pda(1);
+    let mut market: Market = w.h.get(&key);
+    market.version = 1;
+    market.live_bets = 0;
+    let mut data = serialize(&market);
+    data.truncate(data.len() - MARKET_V1_TAIL);
+    w.h.set_account(key, prediction_market::ID, data);
+}
+
+/// Rewrites the market as an unversioned account, whose weighted pools and
+/// counters were not yet tracked.
+fn write_v0_market(w: &mut World) {
+    let key = market_pda(1);
+    let len = w.h.accounts[&key].data.len();
+    let mut market: Market = w.h.get(&key);
+    market.version = 0;
+    market.vault_bump = 0;
+    market.outcome_weighted_pools = vec![];
+    market.bettor_count = 0;
+    market.live_bets = 0;
+    let mut data = serialize(&market);
+    data.resize(len, 0);
+    w.h.set_account(key, prediction_market::ID, data);
+}
+
+fn write_old_bet(w: &mut World, bettor: Pubkey, version: u8) {
+    let key = bet_pda(&market_pda(1), &bettor, 0);
+    let mut bet: Bet = w.h.get(&key);
+    bet.version = version;
+    bet.fee_bps = None;
+    if version == 0 {
+        bet.weighted_amount = 0;
+    }
+    let mut data = serialize(&bet);
+    if version == 1 {
+        data.truncate(data.len() - BET_V1_TAIL);
+    }
+    w.h.set_account(key, prediction_market::ID, data);
+}
+
+/// A market with one bet either side.
+fn market() -> (World, (Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    (w, yes, no)
+}
+
+#[test]
+fn version_1_market_grows_to_the_current_layout() {
+    let (mut w, yes, no) = market();
+    let current = w.market(1);
+    write_v1_market(&mut w);
+
+    // The short account no longer loads as a Market
+    w.h.warp(3_600);
+    assert!(w.close_betting(1).is_err());
+
+    let admin = w.admin;
+    migrate_market(&mut w, admin).unwrap();
+    let migrated = w.market(1);
+    assert_eq!(migrated.version, MARKET_VERSION);
+    assert_eq!(migrated.live_bets, 2);
+    assert_eq!(migrated.outcome_pools, current.outcome_pools);
+
+    // Appended fields come up zeroed
+    assert!(!migrated.dispute_pending && !migrated.emergency_halted);
+    assert_eq!(migrated.escape_mint, None);
+    assert!(migrated.oracle_feeds.is_empty());
+    assert_eq!(migrated.authority_rent, 0);
+    assert!(!migrated.resolver_defaulted);
+    assert_eq!(migrated.vault_bump, current.vault_bump);
+    let len = w.h.accounts[&market_pda(1)].data.len();
+    assert_eq!(
+        len,
+        8 + Market::space_with_description(current.description.len())
+    );
+    assert!(w.h.lamports(&market_pda(1)) >= Rent::default().minimum_balance(len));
+
+    // The migrated market settles as usual
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+    assert_eq!(w.claim(1, no, 0).unwrap_err(), err(ErrorCode::LosingBet));
+}
+
+#[test]
+fn unversioned_market_rebuilds_its_counters() {
+    let (mut w, yes, _) = market();
+    let current = w.market(1);
+    write_v0_market(&mut w);
+
+    let admin = w.admin;
+    migrate_market(&mut w, admin).unwrap();
+    let migrated = w.market(1);
+    assert_eq!(migrated.version, MARKET_VERSION);
+    assert_eq!(migrated.outcome_weighted_pools, current.outcome_pools);
+    assert_eq!(migrated.bettor_count, 2);
+    assert_eq!(migrated.live_bets, 2);
+    assert_eq!(migrated.vault_bump, current.vault_bump);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, yes, 0).unwrap();
+    assert_eq!(w.h.token_balance(&yes.1), 1_100);
+}
+
+#[test]
+fn old_bets_migrate_and_claim() {
+    let (mut w, yes, no) = market();
+    write_old_bet(&mut w, yes.0, 1);
+    write_old_bet(&mut w, no.0, 0);
+    let admin = w.admin;
+
+    migrate_bet(&mut w, yes.0, admin).unwrap();
+    migrate_bet(&mut w, no.0, admin).unwrap();
+    let key = bet_pda(&market_pda(1), &no.0, 0);
+    let bet: Bet = w.h.get(&key);
+    assert_eq!(bet.version, BET_VERSION);
+    assert_eq!(bet.weighted_amount, 100);
+    let bet: Bet = w.h.get(&bet_pda(&market_pda(1), &yes.0, 0));
+    assert_eq!(
+        (bet.fee_bps, bet.claim_approved, bet.claim_held_at),
+        (None, false, 0)
+    );
+    assert_eq!(w.h.accounts[&key].data.len(), 8 + Bet::INIT_SPACE);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 1).unwrap();
+    w.claim(1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 1_300);
+}
+
+#[test]
+fn migrations_are_admin_only_and_run_once() {
+    let (mut w, yes, _) = market();
+    let admin = w.admin;
+    let authority = w.authority;
+
+    assert_eq!(
+        migrate_market(&mut w, admin).unwrap_err(),
+        err(ErrorCode::AlreadyMigrated)
+    );
+    assert_eq!(
+        migrate_bet(&mut w, yes.0, admin).unwrap_err(),
+        err(ErrorCode::AlreadyMigrated)
+    );
+
+    write_v1_market(&mut w);
+    write_old_bet(&mut w, yes.0, 1);
+    assert_eq!(
+        migrate_market(&mut w, authority).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+    assert_eq!(
+        migrate_bet(&mut w, yes.0, authority).unwrap_err(),
+        err(ErrorCode::UnauthorizedAdmin)
+    );
+
+    migrate_market(&mut w, admin).unwrap();
+    assert_eq!(
+        migrate_market(&mut w, admin).unwrap_err(),
+        err(ErrorCode::AlreadyMigrated)
+    );
+}
+
+#[test]
+fn only_program_markets_migrate() {
+    let (mut w, yes, _) = market();
+    let admin = w.admin;
+    let key = market_pda(1);
+    write_v1_market(&mut w);
+    let v1 = w.h.accounts[&key].data.clone();
+
+    // A market-shaped account the program does not own
+    w.h.set_account(key, Pubkey::new_unique(), v1);
+    assert!(migrate_market(&mut w, admin).is_err());
+
+    // A bet passed off as the market fails the discriminator check
+    let bet = w.h.accounts[&bet_pda(&key, &yes.0, 0)].data.clone();
+    assert_ne!(&bet[..8], Market::DISCRIMINATOR);
+    w.h.set_account(key, prediction_market::ID, bet);
+    assert!(migrate_market(&mut w, admin).is_err());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use anchor_lang::system_program;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, Currency, ErrorCode, MarketOptions, SolVault, WinningsClaimed,
+};
+
+fn native_options() -> MarketOptions {
+    MarketOptions {
+        currency: Currency::NativeSol,
+        ..options()
+    }
+}
+
+fn sol_vault(market_id: u64) -> Pubkey {
+    pda(&[b"sol_vault", market_pda(market_id).as_ref()])
+}
+
+fn place_native(
+    w: &mut World,
+    market_id: u64,
+    bettor: Pubkey,
+    bet_index: u64,
+    outcome: u8,
+    amount: u64,
+) -> Result {
+    let market = market_pda(market_id);
+    w.h.send(
+        accounts::PlaceBetNative {
+            market,
+            bet: bet_pda(&market, &bettor, bet_index),
+            sol_vault: sol_vault(market_id),
+            config: config_pda(),
+            user_profile: profile_pda(&bettor),
+            user_position: position_pda(&market, &bettor),
+            bettor,
+            system_program: system_program::ID,
+        },
+        ix::PlaceBetNative {
+            market_id,
+            bet_index,
+            bet_outcome: outcome,
+            amount,
+        },
+    )
+}
+
+fn claim_native(w: &mut World, market_id: u64, bettor: Pubkey, bet_index: u64) -> Result {
+    let market = market_pda(market_id);
+    w.h.send(
+        accounts::ClaimWinningsNative {
+            market,
+            bet: bet_pda(&market, &bettor, bet_index),
+            sol_vault: sol_vault(market_id),
+            user_profile: profile_pda(&bettor),
+            user_position: position_pda(&market, &bettor),
+            bettor,
+            system_program: system_program::ID,
+        },
+        ix::ClaimWinningsNative {
+            market_id,
+            _bet_index: bet_index,
+        },
+    )
+}
+
+#[test]
+fn a_native_market_pays_out_like_an_spl_one() {
+    let stakes = [(0, 100), (0, 250), (1, 333)];
+
+    // The same pool settled in tokens
+    let mut spl = World::new();
+    spl.create_market(1, 3_600, options());
+    let bettors: Vec<_> = stakes.iter().map(|_| spl.bettor(1_000)).collect();
+    for (bettor, &(outcome, amount)) in bettors.iter().zip(&stakes) {
+        spl.place_bet(1, *bettor, 0, outcome, amount).unwrap();
+    }
+    spl.h.warp(3_600);
+    spl.close_betting(1).unwrap();
+    spl.resolve(1, 0).unwrap();
+    let mut spl_payouts = Vec::new();
+    for (bettor, &(_, amount)) in bettors.iter().zip(&stakes).take(2) {
+        spl.claim(1, *bettor, 0).unwrap();
+        spl_payouts.push(spl.h.token_balance(&bettor.1) + amount - 1_000);
+    }
+
+    let mut w = World::new();
+    w.create_market(1, 3_600, native_options());
+    let vault = sol_vault(1);
+    assert_eq!(w.h.get::<SolVault>(&vault).market, market_pda(1));
+    let rent = w.h.lamports(&vault);
+    let bettors: Vec<_> = stakes.iter().map(|_| w.h.wallet()).collect();
+    for (bettor, &(outcome, amount)) in bettors.iter().zip(&stakes) {
+        place_native(&mut w, 1, *bettor, 0, outcome, amount).unwrap();
+    }
+    assert_eq!(w.h.lamports(&vault), rent + 683);
+    assert_eq!(w.market(1).outcome_pools, vec![350, 333]);
+
+    // Token bets are refused on a lamport market and vice versa
+    let token_bettor = w.bettor(1_000);
+    assert_eq!(
+        w.place_bet(1, token_bettor, 0, 0, 100).unwrap_err(),
+        err(ErrorCode::CurrencyMismatch)
+    );
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    assert_eq!(
+        claim_native(&mut w, 1, bettors[2], 0).unwrap_err(),
+        err(ErrorCode::LosingBet)
+    );
+    let mut payouts = Vec::new();
+    for bettor in &bettors[..2] {
+        let before = w.h.lamports(bettor);
+        claim_native(&mut w, 1, *bettor, 0).unwrap();
+        let paid = w.h.events::<WinningsClaimed>()[0].amount;
+        // The bet's rent comes back on top of the payout
+        assert!(w.h.lamports(bettor) > before + paid);
+        payouts.push(paid);
+        assert!(!w.h.exists(&bet_pda(&market_pda(1), bettor, 0)));
+    }
+    assert_eq!(payouts, spl_payouts);
+    assert_eq!(payouts, vec![195, 487]);
+
+    // Only the rounding dust stays behind with the vault's rent
+    assert_eq!(w.h.lamports(&vault), rent + 1);
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, ErrorCode, MarketOptions, MarketStatus, OracleFeed,
+    ResolutionSource, PYTH_RECEIVER_PROGRAM_ID,
+};
+
+const FEED_ID: [u8; 32] = [7; 32];
+const THRESHOLD: i64 = 100_000;
+const STALENESS: i64 = 600;
+
+/// Publishes `price` for `feed_id` on the `feed` account, `age` seconds ago.
+fn publish(w: &mut World, feed: Pubkey, feed_id: [u8; 32], price: i64, age: i64) {
+    let publish_time = w.h.now - age;
+    w.h.set_account(
+        feed,
+        PYTH_RECEIVER_PROGRAM_ID,
+        pyth_price(feed_id, price, publish_time, true),
+    );
+}
+
+fn oracle_options(feed: Pubkey) -> MarketOptions {
+    MarketOptions {
+        resolution_source: ResolutionSource::Oracle,
+        oracle_feed: Some(feed),
+        oracle_feed_id: FEED_ID,
+        resolution_threshold: THRESHOLD,
+        max_oracle_staleness: STALENESS,
+        ..options()
+    }
+}
+
+fn resolve_from_oracle(w: &mut World, feed: Pubkey) -> Result {
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::ResolveFromOracle {
+            market: market_pda(1),
+            oracle_feed: feed,
+            caller,
+        },
+        ix::ResolveMarketFromOracle { market_id: 1 },
+    )
+}
+
+fn resolve_from_feeds(w: &mut World, feeds: &[Pubkey]) -> Result {
+    let caller = w.h.payer;
+    let remaining: Vec<_> = feeds
+        .iter()
+        .map(|feed| AccountMeta::new_readonly(*feed, false))
+        .collect();
+    w.h.send_with(
+        accounts::ResolveFromMultiOracle {
+            market: market_pda(1),
+            caller,
+        },
+        ix::ResolveFromMultiOracle { market_id: 1 },
+        &remaining,
+    )
+}
+
+/// A closed oracle market with stake on both sides.
+fn closed(options: MarketOptions) -> World {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options);
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w
+}
+
+/// Three feed accounts, feed `i` publishing under id `[i + 1; 32]`, and a
+/// market needing two of them fresh.
+fn three_feeds() -> (Vec<Pubkey>, World) {
+    let feeds: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let w = closed(MarketOptions {
+        resolution_source: ResolutionSource::Oracle,
+        oracle_feeds: feeds
+            .iter()
+            .enumerate()
+            .map(|(i, account)| OracleFeed {
+                account: *account,
+                feed_id: [i as u8 + 1; 32],
+            })
+            .collect(),
+        min_oracle_feeds: 2,
+        resolution_threshold: THRESHOLD,
+        max_oracle_staleness: STALENESS,
+        ..options()
+    });
+    (feeds, w)
+}
+
+#[test]
+fn multiple_feeds_resolve_on_the_median() {
+    let (feeds, mut w) = three_feeds();
+
+    // One outlier above the threshold does not carry the median
+    publish(&mut w, feeds[0], [1; 32], THRESHOLD * 10, 0);
+    publish(&mut w, feeds[1], [2; 32], THRESHOLD - 5, 0);
+    publish(&mut w, feeds[2], [3; 32], THRESHOLD - 1, 0);
+
+    assert_eq!(
+        resolve_from_feeds(&mut w, &feeds[..1]).unwrap_err(),
+        err(ErrorCode::StaleOracleData)
+    );
+    assert_eq!(
+        resolve_from_feeds(&mut w, &[feeds[0], feeds[0]]).unwrap_err(),
+        err(ErrorCode::OracleFeedMismatch)
+    );
+    resolve_from_feeds(&mut w, &feeds).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(1));
+}
+
+#[test]
+fn stale_feeds_are_left_out_of_the_median() {
+    let (feeds, mut w) = three_feeds();
+    w.h.warp(STALENESS + 1);
+
+    // The stale feed would have pulled the median below the threshold
+    publish(&mut w, feeds[0], [1; 32], THRESHOLD - 1, STALENESS + 1);
+    publish(&mut w, feeds[1], [2; 32], THRESHOLD, 0);
+    publish(&mut w, feeds[2], [3; 32], THRESHOLD + 1, STALENESS);
+    resolve_from_feeds(&mut w, &feeds).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(0));
+}
+
+#[test]
+fn too_few_fresh_feeds_are_refused() {
+    let (feeds, mut w) = three_feeds();
+    w.h.warp(STALENESS + 1);
+    publish(&mut w, feeds[0], [1; 32], THRESHOLD, STALENESS + 1);
+    publish(&mut w, feeds[1], [2; 32], THRESHOLD, STALENESS + 1);
+    publish(&mut w, feeds[2], [3; 32], THRESHOLD, 0);
+    assert_eq!(
+        resolve_from_feeds(&mut w, &feeds).unwrap_err(),
+        err(ErrorCode::StaleOracleData)
+    );
+
+    // A feed outside the market's set is refused outright
+    let stranger = Pubkey::new_unique();
+    publish(&mut w, stranger, [1; 32], THRESHOLD, 0);
+    assert_eq!(
+        resolve_from_feeds(&mut w, &[feeds[2], stranger]).unwrap_err(),
+        err(ErrorCode::OracleFeedMismatch)
+    );
+}
+
+#[test]
+fn a_price_at_the_threshold_resolves_yes() {
+    let feed = Pubkey::new_unique();
+    let mut w = World::new();
+    w.create_market(1, 3_600, oracle_options(feed));
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+
+    publish(&mut w, feed, FEED_ID, THRESHOLD, 0);
+    assert_eq!(
+        resolve_from_oracle(&mut w, feed).unwrap_err(),
+        err(ErrorCode::BettingNotClosed)
+    );
+    w.close_betting(1).unwrap();
+    resolve_from_oracle(&mut w, feed).unwrap();
+
+    let market = w.market(1);
+    assert_eq!(market.status, MarketStatus::Resolved);
+    assert_eq!(market.winning_outcome, Some(0));
+}
+
+#[test]
+fn a_price_below_the_threshold_resolves_no() {
+    let feed = Pubkey::new_unique();
+    let mut w = closed(oracle_options(feed));
+
+    publish(&mut w, feed, FEED_ID, THRESHOLD - 1, 0);
+    resolve_from_oracle(&mut w, feed).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(1));
+}
+
+#[test]
+fn a_stale_price_is_refused() {
+    let feed = Pubkey::new_unique();
+    let mut w = closed(oracle_options(feed));
+
+    w.h.warp(STALENESS + 1);
+    publish(&mut w, feed, FEED_ID, THRESHOLD, STALENESS + 1);
+    assert_eq!(
+        resolve_from_oracle(&mut w, feed).unwrap_err(),
+        err(ErrorCode::StaleOracleData)
+    );
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+}
+
+#[test]
+fn only_the_configured_verified_feed_counts() {
+    let feed = Pubkey::new_unique();
+    let mut w = closed(oracle_options(feed));
+
+    let other = Pubkey::new_unique();
+    publish(&mut w, other, FEED_ID, THRESHOLD, 0);
+    assert_eq!(
+        resolve_from_oracle(&mut w, other).unwrap_err(),
+        err(ErrorCode::OracleFeedMismatch)
+    );
+
+    publish(&mut w, feed, [8; 32], THRESHOLD, 0);
+    assert_eq!(
+        resolve_from_oracle(&mut w, feed).unwrap_err(),
+        err(ErrorCode::OracleFeedMismatch)
+    );
+
+    let now = w.h.now;
+    w.h.set_account(
+        feed,
+        PYTH_RECEIVER_PROGRAM_ID,
+        pyth_price(FEED_ID, THRESHOLD, now, false),
+    );
+    assert_eq!(
+        resolve_from_oracle(&mut w, feed).unwrap_err(),
+        err(ErrorCode::OracleNotFullyVerified)
+    );
+
+    // The right bytes under the wrong owner are not a Pyth update
+    w.h.set_account(
+        feed,
+        Pubkey::new_unique(),
+        pyth_price(FEED_ID, THRESHOLD, now, true),
+    );
+    assert_eq!(
+        resolve_from_oracle(&mut w, feed).unwrap_err(),
+        err(ErrorCode::OracleFeedMismatch)
+    );
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+}
+
+#[test]
+fn manual_resolution_is_refused_on_oracle_markets() {
+    let mut w = closed(oracle_options(Pubkey::new_unique()));
+    assert_eq!(
+        w.resolve(1, 1).unwrap_err(),
+        err(ErrorCode::WrongResolutionSource)
+    );
+}
+
+#[test]
+fn a_one_sided_oracle_market_is_voided() {
+    let feed = Pubkey::new_unique();
+    let mut w = World::new();
+    w.create_market(1, 3_600, oracle_options(feed));
+    let no = w.bettor(1_000);
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+
+    publish(&mut w, feed, FEED_ID, THRESHOLD, 0);
+    resolve_from_oracle(&mut w, feed).unwrap();
+    assert!(w.market(1).refund_only);
+    w.claim(1, no, 0).unwrap();
+    assert_eq!(w.h.token_balance(&no.1), 1_000);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::*;
+use prediction_market::{accounts, instruction as ix, ErrorCode};
+
+/// Market 1 with 100 on YES and 200 on NO.
+fn staked(w: &mut World) -> ((Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 200).unwrap();
+    (yes, no)
+}
+
+#[test]
+fn bet_counts_past_u64_fail_the_claim() {
+    let mut w = World::new();
+    let (yes, _) = staked(&mut w);
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // A voided market owes every bet; counts summing past u64 must not wrap
+    let mut market = w.market(1);
+    market.refund_only = true;
+    market.winning_outcome = None;
+    market.outcome_bet_counts = vec![u64::MAX, 1];
+    w.h.put(&market_pda(1), &market);
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err(ErrorCode::MathOverflow)
+    );
+}
+
+#[test]
+fn cuts_past_the_payout_fail_the_previews() {
+    let mut w = World::new();
+    staked(&mut w);
+
+    let mut market = w.market(1);
+    market.creator_fee_bps = 10_000;
+    market.charity_bps = 10_000;
+    w.h.put(&market_pda(1), &market);
+
+    let view = || accounts::ViewMarket {
+        market: market_pda(1),
+    };
+    let preview = ix::PreviewWinnings {
+        _market_id: 1,
+        outcome: 0,
+        amount: 100,
+        bettor: Pubkey::new_unique(),
+    };
+    assert_eq!(
+        w.h.send(view(), preview).unwrap_err(),
+        err(ErrorCode::MathUnderflow)
+    );
+    let break_even = ix::BreakEven {
+        _market_id: 1,
+        outcome: 0,
+        amount: 100,
+        bettor: Pubkey::new_unique(),
+    };
+    assert_eq!(
+        w.h.send(view(), break_even).unwrap_err(),
+        err(ErrorCode::MathUnderflow)
+    );
+}
+
+#[test]
+fn a_pool_past_u64_raises_the_minimum_instead_of_wrapping() {
+    let mut w = World::new();
+    staked(&mut w);
+
+    // Truncated, a full-pool minimum of exactly 2^64 would wrap to 0
+    let mut market = w.market(1);
+    market.outcome_pools = vec![u64::MAX, 1];
+    market.min_bet_pool_bps = 10_000;
+    w.h.put(&market_pda(1), &market);
+    assert_eq!(market.effective_min_bet(), u64::MAX);
+
+    let late = w.bettor(1_000);
+    assert_eq!(
+        w.place_bet(1, late, 0, 1, 100).unwrap_err(),
+        err(ErrorCode::BetTooSmall)
+    );
+}
+
+#[test]
+fn a_pool_past_u64_fails_the_payout() {
+    let mut w = World::new();
+    let (yes, _) = staked(&mut w);
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+
+    // Pools summing one past u64::MAX must error rather than wrap or abort
+    let mut market = w.market(1);
+    market.outcome_pools = vec![u64::MAX - 199, 200];
+    w.h.put(&market_pda(1), &market);
+    assert_eq!(
+        w.claim(1, yes, 0).unwrap_err(),
+        err(ErrorCode::MathOverflow)
+    );
+    assert_eq!(w.h.token_balance(&yes.1), 900);
+}
@@ -0,0 +1,256 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use prediction_market::{accounts, instruction as ix, ErrorCode, P2pOffer, Resolution};
+
+fn offer_pda(maker: &Pubkey) -> Pubkey {
+    pda(&[b"p2p_offer", market_pda(1).as_ref(), maker.as_ref()])
+}
+
+fn escrow_pda(maker: &Pubkey) -> Pubkey {
+    pda(&[b"p2p_escrow", offer_pda(maker).as_ref()])
+}
+
+fn create_offer(
+    w: &mut World,
+    maker: (Pubkey, Pubkey),
+    outcome: u8,
+    stake: u64,
+    odds_bps: u64,
+) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::CreateP2pOffer {
+            market,
+            market_vault: vault_pda(&market),
+            offer: offer_pda(&maker.0),
+            escrow: escrow_pda(&maker.0),
+            mint: w.mint,
+            config: config_pda(),
+            maker_token_account: maker.1,
+            maker: maker.0,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        },
+        ix::CreateP2pOffer {
+            market_id: 1,
+            outcome,
+            stake,
+            odds_bps,
+        },
+    )
+}
+
+fn accept_offer(w: &mut World, maker: Pubkey, taker: (Pubkey, Pubkey)) -> Result {
+    w.h.send(
+        accounts::AcceptP2pOffer {
+            market: market_pda(1),
+            offer: offer_pda(&maker),
+            escrow: escrow_pda(&maker),
+            mint: w.mint,
+            config: config_pda(),
+            taker_token_account: taker.1,
+            taker: taker.0,
+            token_program: spl_token::ID,
+        },
+        ix::AcceptP2pOffer { market_id: 1 },
+    )
+}
+
+fn cancel_offer(w: &mut World, maker: (Pubkey, Pubkey)) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::CancelP2pOffer {
+            market,
+            market_vault: vault_pda(&market),
+            offer: offer_pda(&maker.0),
+            escrow: escrow_pda(&maker.0),
+            mint: w.mint,
+            maker_token_account: maker.1,
+            maker: maker.0,
+            token_program: spl_token::ID,
+        },
+        ix::CancelP2pOffer { market_id: 1 },
+    )
+}
+
+fn settle(w: &mut World, maker: (Pubkey, Pubkey), taker: (Pubkey, Pubkey)) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::SettleP2p {
+            market,
+            market_vault: vault_pda(&market),
+            offer: offer_pda(&maker.0),
+            escrow: escrow_pda(&maker.0),
+            mint: w.mint,
+            maker_token_account: maker.1,
+            taker_token_account: taker.1,
+            maker: maker.0,
+            token_program: spl_token::ID,
+        },
+        ix::SettleP2p { market_id: 1 },
+    )
+}
+
+/// A market with pool stake on both sides so it can resolve.
+fn market() -> World {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    w
+}
+
+#[test]
+fn unmatched_offer_can_be_withdrawn() {
+    let mut w = market();
+    let maker = w.bettor(1_000);
+    create_offer(&mut w, maker, 1, 250, 20_000).unwrap();
+    let offer: P2pOffer = w.h.get(&offer_pda(&maker.0));
+    assert_eq!(offer.maker_stake, 250);
+    assert_eq!(w.h.token_balance(&maker.1), 750);
+    assert_eq!(w.h.token_balance(&escrow_pda(&maker.0)), 250);
+
+    cancel_offer(&mut w, maker).unwrap();
+    assert_eq!(w.h.token_balance(&maker.1), 1_000);
+    assert!(!w.h.exists(&offer_pda(&maker.0)));
+    assert!(!w.h.exists(&escrow_pda(&maker.0)));
+    assert_eq!(w.market(1).open_p2p_offers, 0);
+}
+
+#[test]
+fn matched_offer_cannot_be_cancelled() {
+    let mut w = market();
+    let maker = w.bettor(1_000);
+    let taker = w.bettor(1_000);
+    create_offer(&mut w, maker, 0, 200, 10_000).unwrap();
+    accept_offer(&mut w, maker.0, taker).unwrap();
+
+    assert_eq!(
+        cancel_offer(&mut w, maker).unwrap_err(),
+        err(ErrorCode::OfferAlreadyMatched)
+    );
+    assert_eq!(w.h.token_balance(&escrow_pda(&maker.0)), 400);
+    assert_eq!(w.market(1).open_p2p_offers, 1);
+}
+
+#[test]
+fn only_the_maker_cancels() {
+    let mut w = market();
+    let maker = w.bettor(1_000);
+    let stranger = w.bettor(1_000);
+    create_offer(&mut w, maker, 0, 200, 10_000).unwrap();
+
+    // The offer is seeded by its maker, so nobody else can name it
+    let result = w.h.send(
+        accounts::CancelP2pOffer {
+            market: market_pda(1),
+            market_vault: vault_pda(&market_pda(1)),
+            offer: offer_pda(&maker.0),
+            escrow: escrow_pda(&maker.0),
+            mint: w.mint,
+            maker_token_account: stranger.1,
+            maker: stranger.0,
+            token_program: spl_token::ID,
+        },
+        ix::CancelP2pOffer { market_id: 1 },
+    );
+    assert!(result.is_err());
+    assert_eq!(w.h.token_balance(&escrow_pda(&maker.0)), 200);
+}
+
+/// An accepted offer of 200 on YES at 1.5x, so the taker put up 300, with
+/// betting closed.
+fn matched() -> (World, (Pubkey, Pubkey), (Pubkey, Pubkey)) {
+    let mut w = market();
+    let maker = w.bettor(1_000);
+    let taker = w.bettor(1_000);
+    create_offer(&mut w, maker, 0, 200, 15_000).unwrap();
+    let offer: P2pOffer = w.h.get(&offer_pda(&maker.0));
+    assert_eq!(offer.taker_stake, 300);
+    assert_eq!(w.market(1).open_p2p_offers, 1);
+
+    assert_eq!(
+        accept_offer(&mut w, maker.0, maker).unwrap_err(),
+        err(ErrorCode::CannotAcceptOwnOffer)
+    );
+    accept_offer(&mut w, maker.0, taker).unwrap();
+    assert_eq!(w.h.token_balance(&escrow_pda(&maker.0)), 500);
+    let late = w.bettor(1_000);
+    assert_eq!(
+        accept_offer(&mut w, maker.0, late).unwrap_err(),
+        err(ErrorCode::OfferAlreadyAccepted)
+    );
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    assert_eq!(
+        settle(&mut w, maker, taker).unwrap_err(),
+        err(ErrorCode::MarketNotResolved)
+    );
+    (w, maker, taker)
+}
+
+#[test]
+fn the_taker_wins_both_stakes_when_the_maker_is_wrong() {
+    let (mut w, maker, taker) = matched();
+    w.resolve(1, 1).unwrap();
+    settle(&mut w, maker, taker).unwrap();
+
+    assert_eq!(w.h.token_balance(&maker.1), 800);
+    assert_eq!(w.h.token_balance(&taker.1), 1_200);
+    assert!(!w.h.exists(&offer_pda(&maker.0)));
+    assert!(!w.h.exists(&escrow_pda(&maker.0)));
+    assert_eq!(w.market(1).open_p2p_offers, 0);
+
+    // The pool is untouched by the side bet
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 200);
+}
+
+#[test]
+fn the_maker_wins_both_stakes_when_right() {
+    let (mut w, maker, taker) = matched();
+    w.resolve(1, 0).unwrap();
+    settle(&mut w, maker, taker).unwrap();
+
+    assert_eq!(w.h.token_balance(&maker.1), 1_300);
+    assert_eq!(w.h.token_balance(&taker.1), 700);
+    assert!(!w.h.exists(&offer_pda(&maker.0)));
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 200);
+}
+
+#[test]
+fn a_voided_market_returns_both_stakes() {
+    let (mut w, maker, taker) = matched();
+    w.resolve_as(1, Resolution::Void).unwrap();
+    settle(&mut w, maker, taker).unwrap();
+
+    assert_eq!(w.h.token_balance(&maker.1), 1_000);
+    assert_eq!(w.h.token_balance(&taker.1), 1_000);
+}
+
+#[test]
+fn an_unaccepted_offer_is_reclaimed_rather_than_settled() {
+    let mut w = market();
+    let maker = w.bettor(1_000);
+    create_offer(&mut w, maker, 1, 250, 20_000).unwrap();
+    let nobody = (
+        Pubkey::default(),
+        w.h.create_token_account(w.mint, Pubkey::default(), 0),
+    );
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 1).unwrap();
+
+    assert_eq!(
+        settle(&mut w, maker, nobody).unwrap_err(),
+        err(ErrorCode::OfferNotAccepted)
+    );
+    cancel_offer(&mut w, maker).unwrap();
+    assert_eq!(w.h.token_balance(&maker.1), 1_000);
+    assert_eq!(w.market(1).open_p2p_offers, 0);
+}
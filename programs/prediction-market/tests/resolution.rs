@@ -0,0 +1,746 @@
+mod common;
+
+use anchor_lang::system_program;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, outcome_commitment, tiebreak_draw, tiebreak_seed, BettingClosed,
+    ErrorCode, MarketOptions, MarketResolved, MarketStatus, MarketVoided, Resolution,
+    ResolutionSource, VoidReason,
+};
+
+fn community() -> MarketOptions {
+    MarketOptions {
+        resolution_source: ResolutionSource::Community,
+        ..options()
+    }
+}
+
+fn resolve_by_majority(w: &mut World, market_id: u64) -> Result {
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::ResolveByMajority {
+            market: market_pda(market_id),
+            caller,
+        },
+        ix::ResolveByMajority { market_id },
+    )
+}
+
+/// A closed Community market with `pools[i]` staked on outcome `i`.
+fn closed_with_pools(w: &mut World, market_id: u64, options: MarketOptions, pools: &[u64]) {
+    w.create_market(
+        market_id,
+        3_600,
+        MarketOptions {
+            outcome_count: pools.len() as u8,
+            ..options
+        },
+    );
+    for (outcome, &amount) in pools.iter().enumerate() {
+        let bettor = w.bettor(amount);
+        w.place_bet(market_id, bettor, 0, outcome as u8, amount)
+            .unwrap();
+    }
+    w.h.warp(3_600);
+    w.close_betting(market_id).unwrap();
+}
+
+#[test]
+fn a_tie_for_the_lead_is_drawn_from_the_market_and_slot() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, community(), &[300, 100, 300]);
+    w.h.slot = 77;
+    resolve_by_majority(&mut w, 1).unwrap();
+
+    // The seed is recorded, and recomputing it names the same winner
+    let market = w.market(1);
+    let seed = tiebreak_seed(&market_pda(1), 77);
+    let tied = [0, 2];
+    assert_eq!(market.status, MarketStatus::Resolved);
+    assert_eq!(market.tiebreak_seed, Some(seed));
+    assert_eq!(market.winning_outcome, Some(tied[tiebreak_draw(&seed, 2)]));
+}
+
+#[test]
+fn the_draw_reaches_every_tied_outcome() {
+    let market = market_pda(1);
+    for expected in [0u8, 1] {
+        let slot = (0..)
+            .find(|&slot| tiebreak_draw(&tiebreak_seed(&market, slot), 2) == expected as usize)
+            .unwrap();
+        let mut w = World::new();
+        closed_with_pools(&mut w, 1, community(), &[200, 200]);
+        w.h.slot = slot;
+        resolve_by_majority(&mut w, 1).unwrap();
+        assert_eq!(w.market(1).winning_outcome, Some(expected));
+    }
+}
+
+#[test]
+fn a_clear_leader_needs_no_draw() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, community(), &[200, 201]);
+    resolve_by_majority(&mut w, 1).unwrap();
+    let market = w.market(1);
+    assert_eq!(market.winning_outcome, Some(1));
+    assert_eq!(market.tiebreak_seed, None);
+}
+
+#[test]
+fn a_tie_voids_when_a_margin_is_required() {
+    let mut w = World::new();
+    closed_with_pools(
+        &mut w,
+        1,
+        MarketOptions {
+            majority_margin_bps: 100,
+            ..community()
+        },
+        &[200, 200],
+    );
+    resolve_by_majority(&mut w, 1).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    assert_eq!(market.tiebreak_seed, None);
+}
+
+fn with_margin(margin_bps: u16) -> MarketOptions {
+    MarketOptions {
+        majority_margin_bps: margin_bps,
+        ..community()
+    }
+}
+
+#[test]
+fn a_clear_majority_resolves() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, with_margin(1_000), &[560, 440]);
+    resolve_by_majority(&mut w, 1).unwrap();
+    let market = w.market(1);
+    assert_eq!(market.status, MarketStatus::Resolved);
+    assert_eq!(market.winning_outcome, Some(0));
+}
+
+#[test]
+fn a_near_tie_voids() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, with_margin(1_000), &[480, 520]);
+    resolve_by_majority(&mut w, 1).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+}
+
+#[test]
+fn majority_resolution_waits_for_the_close() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, community());
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    assert_eq!(
+        resolve_by_majority(&mut w, 1).unwrap_err(),
+        err(ErrorCode::BettingNotClosed)
+    );
+}
+
+#[test]
+fn each_source_only_accepts_its_own_instruction() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, options(), &[200, 100]);
+    closed_with_pools(&mut w, 2, community(), &[200, 100]);
+    closed_with_pools(
+        &mut w,
+        3,
+        MarketOptions {
+            resolution_source: ResolutionSource::Oracle,
+            oracle_feed: Some(Pubkey::new_unique()),
+            oracle_feed_id: [7; 32],
+            max_oracle_staleness: 600,
+            ..options()
+        },
+        &[200, 100],
+    );
+
+    // The manual market refuses the permissionless paths
+    assert_eq!(
+        resolve_by_majority(&mut w, 1).unwrap_err(),
+        err(ErrorCode::WrongResolutionSource)
+    );
+    let caller = w.h.payer;
+    assert_eq!(
+        w.h.send(
+            accounts::ResolveFromMultiOracle {
+                market: market_pda(1),
+                caller,
+            },
+            ix::ResolveFromMultiOracle { market_id: 1 },
+        )
+        .unwrap_err(),
+        err(ErrorCode::WrongResolutionSource)
+    );
+
+    // and the others refuse the authority
+    for market_id in [2, 3] {
+        assert_eq!(
+            w.resolve(market_id, 0).unwrap_err(),
+            err(ErrorCode::WrongResolutionSource)
+        );
+    }
+
+    w.resolve(1, 0).unwrap();
+    resolve_by_majority(&mut w, 2).unwrap();
+    assert_eq!(
+        w.market(1).resolution_source,
+        ResolutionSource::ManualAuthority
+    );
+    assert_eq!(w.market(2).winning_outcome, Some(0));
+}
+
+#[test]
+fn sources_without_a_resolve_instruction_are_refused() {
+    let mut w = World::new();
+    for resolution_source in [
+        ResolutionSource::Committee,
+        ResolutionSource::Governance,
+        ResolutionSource::Vrf,
+    ] {
+        assert_eq!(
+            w.try_create_market(
+                1,
+                3_600,
+                MarketOptions {
+                    resolution_source,
+                    ..options()
+                },
+            )
+            .unwrap_err(),
+            err(ErrorCode::UnsupportedResolutionSource)
+        );
+    }
+}
+
+#[test]
+fn a_market_with_an_empty_side_voids_and_refunds() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let also_yes = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, also_yes, 0, 0, 300).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+
+    // Nobody took the other side, so there is no losing stake to pay from
+    w.resolve(1, 0).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    let voided = w.h.events::<MarketVoided>();
+    assert!(voided[0].reason == VoidReason::OneSided);
+
+    for bettor in [yes, also_yes] {
+        w.claim(1, bettor, 0).unwrap();
+        assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+    }
+}
+
+#[test]
+fn each_side_needs_the_minimum_side_pool() {
+    let mut w = World::new();
+    let options = MarketOptions {
+        min_side_pool: 50,
+        ..options()
+    };
+
+    // 40 on NO is stake, but under the minimum
+    closed_with_pools(&mut w, 1, options.clone(), &[100, 40]);
+    w.resolve(1, 0).unwrap();
+    assert!(w.market(1).refund_only);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::OneSided);
+
+    closed_with_pools(&mut w, 2, options, &[100, 50]);
+    w.resolve(2, 0).unwrap();
+    let market = w.market(2);
+    assert!(!market.refund_only);
+    assert_eq!(market.winning_outcome, Some(0));
+}
+
+const SALT: [u8; 32] = [7; 32];
+
+/// A CommitReveal market with 100 on each side and `outcome` committed.
+fn committed(w: &mut World, outcome: u8) {
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            resolution_source: ResolutionSource::CommitReveal,
+            reveal_window: 600,
+            ..options()
+        },
+    );
+    for side in 0..2 {
+        let bettor = w.bettor(100);
+        w.place_bet(1, bettor, 0, side, 100).unwrap();
+    }
+    let authority = w.authority;
+    w.h.send(
+        accounts::CommitOutcome {
+            market: market_pda(1),
+            authority,
+        },
+        ix::CommitOutcome {
+            market_id: 1,
+            commitment: outcome_commitment(1, outcome, &SALT),
+        },
+    )
+    .unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+}
+
+fn reveal(w: &mut World, outcome: u8) -> Result {
+    let authority = w.authority;
+    w.h.send(
+        accounts::RevealOutcome {
+            market: market_pda(1),
+            authority,
+        },
+        ix::RevealOutcome {
+            market_id: 1,
+            winning_outcome: outcome,
+            salt: SALT,
+        },
+    )
+}
+
+fn void_unrevealed(w: &mut World) -> Result {
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::VoidUnrevealed {
+            market: market_pda(1),
+            caller,
+        },
+        ix::VoidUnrevealed { market_id: 1 },
+    )
+}
+
+#[test]
+fn a_matching_reveal_resolves() {
+    let mut w = World::new();
+    committed(&mut w, 1);
+    reveal(&mut w, 1).unwrap();
+    let market = w.market(1);
+    assert_eq!(market.status, MarketStatus::Resolved);
+    assert_eq!(market.winning_outcome, Some(1));
+    assert!(!market.refund_only);
+}
+
+#[test]
+fn a_mismatched_reveal_voids() {
+    let mut w = World::new();
+    committed(&mut w, 1);
+    reveal(&mut w, 0).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::CommitmentMismatch);
+}
+
+#[test]
+fn a_missing_reveal_voids_after_the_window() {
+    let mut w = World::new();
+    committed(&mut w, 1);
+    assert_eq!(
+        void_unrevealed(&mut w).unwrap_err(),
+        err(ErrorCode::RevealWindowOpen)
+    );
+
+    w.h.warp(600);
+    assert_eq!(
+        reveal(&mut w, 1).unwrap_err(),
+        err(ErrorCode::RevealWindowClosed)
+    );
+    void_unrevealed(&mut w).unwrap();
+    assert!(w.market(1).refund_only);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::Unrevealed);
+}
+
+fn void_unresolved(w: &mut World, market_id: u64) -> Result {
+    let caller = w.h.payer;
+    w.h.send(
+        accounts::VoidUnresolved {
+            market: market_pda(market_id),
+            caller,
+        },
+        ix::VoidUnresolved { market_id },
+    )
+}
+
+/// Options resolving within a day of the close.
+fn one_day_to_resolve(w: &World) -> MarketOptions {
+    MarketOptions {
+        resolution_deadline: w.h.now + 3_600 + 86_400,
+        ..options()
+    }
+}
+
+#[test]
+fn resolution_runs_up_to_the_deadline() {
+    let mut w = World::new();
+    let options = one_day_to_resolve(&w);
+    closed_with_pools(&mut w, 1, options, &[100, 100]);
+
+    // Nothing can void it while the window is open
+    w.h.warp(86_400);
+    assert_eq!(
+        void_unresolved(&mut w, 1).unwrap_err(),
+        err(ErrorCode::ResolutionWindowOpen)
+    );
+    w.resolve(1, 0).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(0));
+}
+
+#[test]
+fn past_the_deadline_the_market_can_only_void() {
+    let mut w = World::new();
+    let options = one_day_to_resolve(&w);
+    closed_with_pools(&mut w, 1, options, &[100, 100]);
+
+    w.h.warp(86_401);
+    assert_eq!(
+        w.resolve(1, 0).unwrap_err(),
+        err(ErrorCode::ResolutionDeadlinePassed)
+    );
+    void_unresolved(&mut w, 1).unwrap();
+    assert!(w.market(1).refund_only);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::ResolutionDeadlinePassed);
+}
+
+#[test]
+fn the_deadline_must_follow_the_close() {
+    let mut w = World::new();
+    let end_time = w.h.now + 3_600;
+    let options = MarketOptions {
+        resolution_deadline: end_time,
+        ..options()
+    };
+    assert_eq!(
+        w.try_create_market(1, 3_600, options).unwrap_err(),
+        err(ErrorCode::InvalidResolutionDeadline)
+    );
+}
+
+#[test]
+fn each_status_refuses_what_it_has_moved_past() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.create_market(2, 3_600, options());
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+    w.place_bet(2, bettor, 0, 0, 100).unwrap();
+
+    // Open: nothing to resolve or claim yet
+    assert_eq!(w.market(1).status, MarketStatus::Open);
+    assert_eq!(
+        w.resolve(1, 0).unwrap_err(),
+        err(ErrorCode::BettingNotClosed)
+    );
+    assert_eq!(
+        w.claim(1, bettor, 0).unwrap_err(),
+        err(ErrorCode::MarketNotResolved)
+    );
+
+    // Closed: betting and closing are behind it
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+    assert_eq!(
+        w.place_bet(1, bettor, 1, 0, 100).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(
+        w.close_betting(1).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(
+        w.claim(1, bettor, 0).unwrap_err(),
+        err(ErrorCode::MarketNotResolved)
+    );
+
+    // Resolved: final, it can be neither resolved again nor cancelled
+    w.resolve(1, 0).unwrap();
+    assert_eq!(w.market(1).status, MarketStatus::Resolved);
+    for refused in [
+        w.place_bet(1, bettor, 1, 0, 100),
+        w.close_betting(1),
+        w.resolve(1, 1),
+        w.cancel_market(1),
+    ] {
+        assert_eq!(refused.unwrap_err(), err(ErrorCode::MarketAlreadyResolved));
+    }
+    w.claim(1, bettor, 0).unwrap();
+
+    // Cancelled: also final
+    w.cancel_market(2).unwrap();
+    assert_eq!(w.market(2).status, MarketStatus::Cancelled);
+    for refused in [
+        w.place_bet(2, bettor, 1, 0, 100),
+        w.close_betting(2),
+        w.resolve(2, 0),
+        w.cancel_market(2),
+    ] {
+        assert_eq!(refused.unwrap_err(), err(ErrorCode::MarketCancelled));
+    }
+    w.claim(2, bettor, 0).unwrap();
+    assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+}
+
+#[test]
+fn the_resolution_event_snapshots_the_final_pools() {
+    let mut w = World::new();
+    closed_with_pools(&mut w, 1, options(), &[300, 100, 50]);
+    w.h.warp(120);
+    w.resolve(1, 1).unwrap();
+
+    let market = w.market(1);
+    let resolved = &w.h.events::<MarketResolved>()[0];
+    assert_eq!(resolved.market_id, 1);
+    assert_eq!(resolved.winning_outcome, 1);
+    assert_eq!(resolved.resolver, w.authority);
+    assert_eq!(resolved.outcome_pools, market.outcome_pools);
+    assert_eq!(resolved.total_pool, 450);
+    assert_eq!(resolved.resolved_at, market.resolved_at);
+    assert_eq!(resolved.resolved_at, w.h.now);
+}
+
+#[test]
+fn a_pool_under_the_settlement_minimum_refunds() {
+    let mut w = World::new();
+    let options = MarketOptions {
+        min_pool_to_settle: 500,
+        ..options()
+    };
+
+    // One short of the minimum voids instead of paying out
+    closed_with_pools(&mut w, 1, options.clone(), &[300, 199]);
+    w.resolve(1, 0).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::InsufficientLiquidity);
+    assert!(w.h.events::<MarketResolved>().is_empty());
+
+    // Exactly the minimum settles
+    closed_with_pools(&mut w, 2, options, &[300, 200]);
+    w.resolve(2, 0).unwrap();
+    let market = w.market(2);
+    assert!(!market.refund_only);
+    assert_eq!(market.winning_outcome, Some(0));
+    assert_eq!(w.h.events::<MarketResolved>()[0].market_id, 2);
+}
+
+#[test]
+fn a_market_without_bets_voids_and_closes_at_once() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+
+    w.resolve(1, 0).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::NoBets);
+
+    // Nothing is left to claim, so the market closes straight away
+    w.close_market(1).unwrap();
+    assert!(!w.h.exists(&market_pda(1)));
+}
+
+#[test]
+fn a_void_resolution_refunds_both_sides() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 300).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+
+    // A draw is only declared once betting has ended
+    assert!(w.resolve_as(1, Resolution::Void).is_err());
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve_as(1, Resolution::Void).unwrap();
+    let market = w.market(1);
+    assert!(market.refund_only);
+    assert_eq!(market.winning_outcome, None);
+    assert!(w.h.events::<MarketVoided>()[0].reason == VoidReason::ResolvedVoid);
+
+    // Nobody is paid more than their stake, and the void is final
+    assert!(w.resolve(1, 0).is_err());
+    for bettor in [yes, no] {
+        w.claim(1, bettor, 0).unwrap();
+        assert_eq!(w.h.token_balance(&bettor.1), 1_000);
+    }
+    assert_eq!(w.h.token_balance(&vault_pda(&market_pda(1))), 0);
+}
+
+#[test]
+fn betting_closes_once_the_market_ends() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let bettor = w.bettor(1_000);
+    w.place_bet(1, bettor, 0, 0, 100).unwrap();
+
+    w.h.warp(3_599);
+    assert_eq!(
+        w.close_betting(1).unwrap_err(),
+        err(ErrorCode::MarketNotExpired)
+    );
+    assert_eq!(
+        w.resolve(1, 0).unwrap_err(),
+        err(ErrorCode::BettingNotClosed)
+    );
+
+    // Anyone may close it at end_time
+    w.h.warp(1);
+    w.close_betting(1).unwrap();
+    let closed = &w.h.events::<BettingClosed>()[0];
+    assert_eq!(closed.market_id, 1);
+    assert_eq!(closed.outcome_pools, vec![100, 0]);
+    assert_eq!(closed.closed_at, w.h.now);
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+    assert_eq!(
+        w.place_bet(1, bettor, 1, 1, 100).unwrap_err(),
+        err(ErrorCode::BettingClosed)
+    );
+    assert_eq!(w.h.token_balance(&bettor.1), 900);
+}
+
+fn resolve_as_signer(w: &mut World, signer: Pubkey, outcome: u8) -> Result {
+    w.h.send(
+        accounts::ResolveMarket {
+            market: market_pda(1),
+            authority: signer,
+        },
+        ix::ResolveMarket {
+            market_id: 1,
+            resolution: Resolution::Outcome(outcome),
+        },
+    )
+}
+
+#[test]
+fn a_resolver_set_replaces_the_authority() {
+    let mut w = World::new();
+    let resolvers = [w.h.wallet(), w.h.wallet()];
+    closed_with_pools(
+        &mut w,
+        1,
+        MarketOptions {
+            resolvers: resolvers.to_vec(),
+            ..options()
+        },
+        &[300, 100],
+    );
+
+    let stranger = w.h.wallet();
+    let authority = w.authority;
+    for signer in [stranger, authority] {
+        assert_eq!(
+            resolve_as_signer(&mut w, signer, 0).unwrap_err(),
+            err(ErrorCode::NotInResolverSet)
+        );
+    }
+    resolve_as_signer(&mut w, resolvers[1], 1).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(1));
+    assert_eq!(w.h.events::<MarketResolved>()[0].resolver, resolvers[1]);
+}
+
+fn vote(w: &mut World, resolver: Pubkey, outcome: u8) -> Result {
+    let market = market_pda(1);
+    w.h.send(
+        accounts::SubmitResolutionVote {
+            market,
+            resolution_votes: pda(&[b"resolution_votes", market.as_ref()]),
+            resolver,
+            system_program: system_program::ID,
+        },
+        ix::SubmitResolutionVote {
+            market_id: 1,
+            outcome,
+        },
+    )
+}
+
+/// A closed market resolved by two of three resolvers.
+fn two_of_three(w: &mut World) -> [Pubkey; 3] {
+    let resolvers = [w.h.wallet(), w.h.wallet(), w.h.wallet()];
+    closed_with_pools(
+        w,
+        1,
+        MarketOptions {
+            resolvers: resolvers.to_vec(),
+            resolver_threshold: 2,
+            ..options()
+        },
+        &[300, 100],
+    );
+    resolvers
+}
+
+#[test]
+fn matching_votes_resolve_at_the_threshold() {
+    let mut w = World::new();
+    let [first, second, _] = two_of_three(&mut w);
+
+    // No single key can resolve a threshold market
+    assert_eq!(
+        resolve_as_signer(&mut w, first, 0).unwrap_err(),
+        err(ErrorCode::VoteThresholdRequired)
+    );
+    vote(&mut w, first, 0).unwrap();
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+    vote(&mut w, second, 0).unwrap();
+    let market = w.market(1);
+    assert_eq!(market.status, MarketStatus::Resolved);
+    assert_eq!(market.winning_outcome, Some(0));
+    assert_eq!(w.h.events::<MarketResolved>()[0].resolver, second);
+}
+
+#[test]
+fn a_split_vote_waits_for_a_majority() {
+    let mut w = World::new();
+    let [first, second, third] = two_of_three(&mut w);
+
+    vote(&mut w, first, 0).unwrap();
+    vote(&mut w, second, 1).unwrap();
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+    assert!(w.h.events::<MarketResolved>().is_empty());
+
+    vote(&mut w, third, 1).unwrap();
+    assert_eq!(w.market(1).winning_outcome, Some(1));
+}
+
+#[test]
+fn each_resolver_votes_once() {
+    let mut w = World::new();
+    let [first, _, _] = two_of_three(&mut w);
+
+    vote(&mut w, first, 0).unwrap();
+    assert_eq!(
+        vote(&mut w, first, 0).unwrap_err(),
+        err(ErrorCode::DuplicateVote)
+    );
+    assert_eq!(
+        vote(&mut w, first, 1).unwrap_err(),
+        err(ErrorCode::DuplicateVote)
+    );
+    let stranger = w.h.wallet();
+    assert_eq!(
+        vote(&mut w, stranger, 0).unwrap_err(),
+        err(ErrorCode::NotInResolverSet)
+    );
+    assert_eq!(w.market(1).status, MarketStatus::Closed);
+}
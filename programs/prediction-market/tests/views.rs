@@ -0,0 +1,344 @@
+mod common;
+
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::AnchorDeserialize;
+use common::*;
+use prediction_market::{
+    accounts, instruction as ix, BetResult, ErrorCode, MarketOptions, Resolution, MAX_ODDS_DECIMALS,
+};
+
+#[test]
+fn create_market_returns_its_addresses() {
+    let mut w = World::new();
+    let created = w.create_market(7, 3_600, options());
+
+    let (market, market_bump) =
+        Pubkey::find_program_address(&[b"market", &7u64.to_le_bytes()], &prediction_market::ID);
+    let (market_vault, vault_bump) =
+        Pubkey::find_program_address(&[b"market_vault", market.as_ref()], &prediction_market::ID);
+    assert_eq!(created.market_id, 7);
+    assert_eq!((created.market, created.market_bump), (market, market_bump));
+    assert_eq!(
+        (created.market_vault, created.vault_bump),
+        (market_vault, vault_bump)
+    );
+    assert_eq!(w.market(7).market_id, 7);
+}
+
+/// Market `market_id` with 100 staked on YES and 200 on NO.
+fn one_to_two(w: &mut World, market_id: u64, odds_decimals: u8) {
+    w.create_market(
+        market_id,
+        3_600,
+        MarketOptions {
+            odds_decimals,
+            ..options()
+        },
+    );
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(market_id, yes, 0, 0, 100).unwrap();
+    w.place_bet(market_id, no, 0, 1, 200).unwrap();
+}
+
+fn implied_probability(w: &mut World, market_id: u64, outcome: u8) -> u64 {
+    w.view(
+        market_id,
+        ix::ImpliedProbability {
+            _market_id: market_id,
+            outcome,
+        },
+    )
+}
+
+fn odds(w: &mut World, market_id: u64, outcome: u8) -> u64 {
+    w.view(
+        market_id,
+        ix::Odds {
+            _market_id: market_id,
+            outcome,
+        },
+    )
+}
+
+#[test]
+fn higher_precision_gives_finer_probabilities() {
+    let mut w = World::new();
+    one_to_two(&mut w, 1, 2);
+    one_to_two(&mut w, 2, 4);
+    one_to_two(&mut w, 3, MAX_ODDS_DECIMALS);
+
+    assert_eq!(implied_probability(&mut w, 1, 0), 33);
+    assert_eq!(implied_probability(&mut w, 2, 0), 3_333);
+    assert_eq!(implied_probability(&mut w, 3, 0), 333_333_333);
+    assert_eq!(implied_probability(&mut w, 3, 1), 666_666_666);
+
+    assert_eq!(odds(&mut w, 1, 1), 150);
+    assert_eq!(odds(&mut w, 2, 1), 15_000);
+    assert_eq!(odds(&mut w, 3, 0), 3_000_000_000);
+}
+
+#[test]
+fn odds_precision_is_bounded() {
+    let mut w = World::new();
+    let result = w.try_create_market(
+        1,
+        3_600,
+        MarketOptions {
+            odds_decimals: MAX_ODDS_DECIMALS + 1,
+            ..options()
+        },
+    );
+    assert_eq!(result.unwrap_err(), err(ErrorCode::InvalidOddsPrecision));
+}
+
+fn did_win(w: &mut World, market_id: u64, bettor: Pubkey) -> BetResult {
+    let market = market_pda(market_id);
+    let data =
+        w.h.send(
+            accounts::ViewBet {
+                market,
+                bet: bet_pda(&market, &bettor, 0),
+            },
+            ix::DidWin {
+                _market_id: market_id,
+                _bettor: bettor,
+                _bet_index: 0,
+            },
+        )
+        .unwrap();
+    BetResult::deserialize(&mut &data[..]).unwrap()
+}
+
+#[test]
+fn did_win_reports_pending_won_lost_and_voided() {
+    let mut w = World::new();
+    one_to_two(&mut w, 1, 4);
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    w.place_bet(1, no, 0, 1, 100).unwrap();
+    assert!(did_win(&mut w, 1, yes.0) == BetResult::Pending);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    assert!(did_win(&mut w, 1, yes.0) == BetResult::Pending);
+    w.resolve(1, 0).unwrap();
+    assert!(did_win(&mut w, 1, yes.0) == BetResult::Won);
+    assert!(did_win(&mut w, 1, no.0) == BetResult::Lost);
+
+    one_to_two(&mut w, 2, 4);
+    let voided = w.bettor(1_000);
+    w.place_bet(2, voided, 0, 0, 100).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(2).unwrap();
+    w.resolve_as(2, Resolution::Void).unwrap();
+    assert!(did_win(&mut w, 2, voided.0) == BetResult::Voided);
+}
+
+fn break_even(w: &mut World, market_id: u64, outcome: u8, amount: u64) -> u64 {
+    w.view(
+        market_id,
+        ix::BreakEven {
+            _market_id: market_id,
+            outcome,
+            amount,
+            bettor: Pubkey::new_unique(),
+        },
+    )
+}
+
+#[test]
+fn break_even_follows_the_pools_and_fees() {
+    let mut w = World::new();
+
+    // Alone in an empty market a bet only gets its stake back
+    w.create_market(1, 3_600, options());
+    assert_eq!(break_even(&mut w, 1, 0, 100), 10_000);
+
+    // 100 more on YES against 100 / 200 wins 200: even at 50%
+    one_to_two(&mut w, 2, 4);
+    assert_eq!(break_even(&mut w, 2, 0, 100), 5_000);
+    // 100 more on NO wins 133: 1 / 1.33, rounded up
+    assert_eq!(break_even(&mut w, 2, 1, 100), 7_519);
+
+    // A 5% fee leaves 190 of the 200, raising the bar
+    let mut fees = World::new();
+    fees.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            fee_bps: 500,
+            ..options()
+        },
+    );
+    let yes = fees.bettor(1_000);
+    let no = fees.bettor(1_000);
+    fees.place_bet(1, yes, 0, 0, 100).unwrap();
+    fees.place_bet(1, no, 0, 1, 200).unwrap();
+    assert_eq!(break_even(&mut fees, 1, 0, 100), 5_264);
+}
+
+#[test]
+fn seeded_odds_start_at_the_prior() {
+    let mut w = World::new();
+    w.create_market(
+        1,
+        3_600,
+        MarketOptions {
+            seed_amount: 10_000,
+            prior_yes_bps: Some(7_000),
+            ..options()
+        },
+    );
+    let market = w.market(1);
+    assert_eq!(market.seed_pools, vec![7_000, 3_000]);
+    assert_eq!(implied_probability(&mut w, 1, 0), 7_000);
+    assert_eq!(implied_probability(&mut w, 1, 1), 3_000);
+
+    // Without a prior the seed is split evenly
+    w.create_market(
+        2,
+        3_600,
+        MarketOptions {
+            seed_amount: 10_000,
+            ..options()
+        },
+    );
+    assert_eq!(implied_probability(&mut w, 2, 0), 5_000);
+}
+
+/// Folds one transition into `hash` the way `Market::chain_audit` does.
+fn chain(hash: [u8; 32], transition: &[u8], fields: &[&[u8]]) -> [u8; 32] {
+    let mut parts: Vec<&[u8]> = vec![&hash, transition];
+    parts.extend_from_slice(fields);
+    hashv(&parts).to_bytes()
+}
+
+fn audit_hash(w: &mut World, market_id: u64) -> [u8; 32] {
+    w.view(
+        market_id,
+        ix::GetAuditHash {
+            _market_id: market_id,
+        },
+    )
+}
+
+#[test]
+fn the_audit_hash_chains_every_transition() {
+    let mut w = World::new();
+    let created_at = w.h.now;
+    w.create_market(1, 3_600, options());
+    let end_time = created_at + 3_600;
+    let mut expected = chain(
+        [0; 32],
+        b"create",
+        &[
+            &1u64.to_le_bytes(),
+            w.authority.as_ref(),
+            &end_time.to_le_bytes(),
+            &0u64.to_le_bytes(),
+        ],
+    );
+    assert_eq!(audit_hash(&mut w, 1), expected);
+
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    expected = chain(
+        expected,
+        b"bet",
+        &[yes.0.as_ref(), &[0], &100u64.to_le_bytes()],
+    );
+    w.place_bet(1, no, 0, 1, 200).unwrap();
+    expected = chain(
+        expected,
+        b"bet",
+        &[no.0.as_ref(), &[1], &200u64.to_le_bytes()],
+    );
+    assert_eq!(audit_hash(&mut w, 1), expected);
+
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    expected = chain(expected, b"close", &[&end_time.to_le_bytes()]);
+    w.resolve(1, 0).unwrap();
+    expected = chain(expected, b"resolve", &[&[0], &end_time.to_le_bytes()]);
+    assert_eq!(audit_hash(&mut w, 1), expected);
+
+    w.claim(1, yes, 0).unwrap();
+    expected = chain(expected, b"payout", &[&300u64.to_le_bytes()]);
+    assert_eq!(audit_hash(&mut w, 1), expected);
+}
+
+#[test]
+fn preview_matches_the_claimed_winnings() {
+    let mut w = World::new();
+    one_to_two(&mut w, 1, 0);
+    let bettor = w.bettor(1_000);
+    let preview = |w: &mut World, outcome, amount| -> u64 {
+        w.view(
+            1,
+            ix::PreviewWinnings {
+                _market_id: 1,
+                outcome,
+                amount,
+                bettor: bettor.0,
+            },
+        )
+    };
+
+    // 150 more on YES takes its share of a 450 pool: 150 * 450 / 250
+    let previewed = preview(&mut w, 0, 150);
+    assert_eq!(previewed, 270);
+    assert_eq!(preview(&mut w, 1, 150), 150 * 450 / 350);
+    assert_eq!(
+        w.h.send(
+            accounts::ViewMarket {
+                market: market_pda(1),
+            },
+            ix::PreviewWinnings {
+                _market_id: 1,
+                outcome: 2,
+                amount: 150,
+                bettor: bettor.0,
+            },
+        )
+        .unwrap_err(),
+        err(ErrorCode::InvalidOutcome)
+    );
+
+    w.place_bet(1, bettor, 0, 0, 150).unwrap();
+    w.h.warp(3_600);
+    w.close_betting(1).unwrap();
+    w.resolve(1, 0).unwrap();
+    w.claim(1, bettor, 0).unwrap();
+    assert_eq!(w.h.token_balance(&bettor.1), 850 + previewed);
+}
+
+fn current_odds(w: &mut World, market_id: u64) -> Vec<u64> {
+    w.view(
+        market_id,
+        ix::CurrentOdds {
+            _market_id: market_id,
+        },
+    )
+}
+
+#[test]
+fn current_odds_follow_the_pools() {
+    let mut w = World::new();
+    w.create_market(1, 3_600, options());
+    let yes = w.bettor(1_000);
+    let no = w.bettor(1_000);
+
+    // Empty sides report the sentinel rather than dividing by zero
+    assert_eq!(current_odds(&mut w, 1), vec![u64::MAX, u64::MAX]);
+    w.place_bet(1, yes, 0, 0, 100).unwrap();
+    assert_eq!(current_odds(&mut w, 1), vec![10_000, u64::MAX]);
+
+    // 400 / 100 and 400 / 300, floored
+    w.place_bet(1, no, 0, 1, 300).unwrap();
+    assert_eq!(current_odds(&mut w, 1), vec![40_000, 13_333]);
+    w.place_bet(1, yes, 1, 0, 50).unwrap();
+    assert_eq!(current_odds(&mut w, 1), vec![30_000, 15_000]);
+}